@@ -0,0 +1,446 @@
+use clap::{Args, CommandFactory, Parser, Subcommand};
+use payments_engine::clients::ClientField;
+use payments_engine::ledger::LedgerConfig;
+use payments_engine::parser::{
+    read_client_master, read_client_tiers, read_credit_limits, read_fee_schedule, read_limits,
+    read_reserves, read_rules, Format, InputOptions, ParseError, ParseMode,
+};
+use payments_engine::precision::{Precision, Rounding, DEFAULT_DECIMAL_PLACES, MAX_DECIMAL_PLACES};
+use std::ffi::OsString;
+use std::fs;
+use std::io::{Error, ErrorKind};
+use std::path::{Path, PathBuf};
+
+/// Process client transactions and report the resulting account balances.
+///
+/// Running without a subcommand, e.g. `payments-engine transactions.csv`, is equivalent to
+/// `payments-engine process transactions.csv`.
+#[derive(Debug, Parser)]
+#[command(version)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Command,
+}
+
+impl Cli {
+    /// Parse the given command line arguments, running `process` when no subcommand is given.
+    pub fn try_parse_args(
+        args: impl IntoIterator<Item = impl Into<OsString>>,
+    ) -> Result<Self, clap::Error> {
+        let mut args = args.into_iter().map(Into::into).collect::<Vec<OsString>>();
+        let defaults_to_process = args.get(1).is_some_and(|arg| {
+            let is_subcommand = Cli::command()
+                .get_subcommands()
+                .any(|command| arg == command.get_name());
+            let is_top_level_flag = ["-h", "--help", "-V", "--version"].map(OsString::from);
+            !is_subcommand && !is_top_level_flag.contains(arg)
+        });
+        if defaults_to_process {
+            args.insert(1, "process".into());
+        }
+        Cli::try_parse_from(args)
+    }
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Apply transactions and write the resulting client accounts.
+    Process(IoArgs),
+    /// Dry run transactions, reporting every row which would be rejected without writing any
+    /// accounts.
+    Validate(InputArgs),
+    /// Apply transactions and write every transaction applied to a single client, with the
+    /// client's balances after each one.
+    Statement {
+        #[command(flatten)]
+        io: IoArgs,
+        /// The client to produce a statement for.
+        #[arg(long)]
+        client: u16,
+    },
+    /// Apply transactions up to and including the row creating the given transaction ID and write
+    /// the client accounts as they stood at that point.
+    Replay {
+        #[command(flatten)]
+        io: IoArgs,
+        /// The last transaction ID to apply. Rows referencing it, such as disputes, do not end the
+        /// replay.
+        #[arg(long)]
+        until: u32,
+    },
+}
+
+#[derive(Debug, Args)]
+pub struct InputArgs {
+    /// Input files, directories or glob patterns, or `-` to read from stdin. Inputs are
+    /// processed in the order given, with each directory or pattern expanded in name order.
+    #[arg(required = true)]
+    pub inputs: Vec<String>,
+
+    /// Format of the input file.
+    #[arg(long, value_name = "FORMAT", default_value = "csv")]
+    pub input_format: Format,
+
+    /// Reject non-positive amounts, amounts with more decimal places than the configured
+    /// precision, unknown columns and inputs missing any of the expected column headers.
+    #[arg(long)]
+    pub strict: bool,
+
+    #[command(flatten)]
+    pub ledger: LedgerArgs,
+}
+
+impl InputArgs {
+    pub fn options(&self) -> InputOptions {
+        InputOptions {
+            format: self.input_format,
+            mode: match self.strict {
+                true => ParseMode::Strict,
+                false => ParseMode::Lenient,
+            },
+            precision: self.ledger.precision(),
+        }
+    }
+}
+
+/// Arguments configuring the ledger transactions are applied to.
+#[derive(Debug, Args)]
+pub struct LedgerArgs {
+    /// Client master data in CSV format, with `client`, `tier`, `status`, `country`, `risk_rating`
+    /// and `credit_limit` columns. Tiers and credit limits given separately take precedence.
+    #[arg(long, value_name = "PATH")]
+    pub clients: Option<PathBuf>,
+
+    /// Comma separated client master data fields to include in the accounts output, from `tier`,
+    /// `status`, `country` and `risk_rating`.
+    #[arg(
+        long,
+        value_name = "FIELDS",
+        value_delimiter = ',',
+        requires = "clients"
+    )]
+    pub client_columns: Vec<ClientField>,
+
+    /// Fee schedule in CSV format, with `type`, `flat` and `percent` columns.
+    #[arg(long, value_name = "PATH")]
+    pub fees: Option<PathBuf>,
+
+    /// Withdrawal limits in CSV format, with `tier`, `per_transaction`, `daily` and `monthly`
+    /// columns. Clients without an assigned tier are subject to the `default` tier's limits.
+    #[arg(long, value_name = "PATH")]
+    pub limits: Option<PathBuf>,
+
+    /// The tier each client belongs to in CSV format, with `client` and `tier` columns.
+    #[arg(long, value_name = "PATH", requires = "limits")]
+    pub tiers: Option<PathBuf>,
+
+    /// Fraud and velocity rules in CSV format, with `rule`, `action`, `count`, `window`, `ratio`
+    /// and `minimum` columns. Each rule flags, rejects or locks the account of transactions which
+    /// trigger it.
+    #[arg(long, value_name = "PATH")]
+    pub rules: Option<PathBuf>,
+
+    /// Credit limits in CSV format, with `client` and `credit_limit` columns. Clients with a credit
+    /// limit may withdraw beyond their available funds up to that limit.
+    #[arg(long, value_name = "PATH")]
+    pub credit_limits: Option<PathBuf>,
+
+    /// Rolling reserves in CSV format, with `client`, `percent` and `days` columns. The percentage
+    /// of each deposit made by a listed client is withheld for the given number of days.
+    #[arg(long, value_name = "PATH")]
+    pub reserves: Option<PathBuf>,
+
+    /// Hold deposits as pending until they are settled by a `settle` transaction, rather than
+    /// making them available immediately.
+    #[arg(long)]
+    pub pending_deposits: bool,
+
+    /// Settle pending deposits automatically once the given number of days have passed.
+    #[arg(long, value_name = "DAYS", requires = "pending_deposits")]
+    pub settlement_delay: Option<u64>,
+
+    /// Park disputes, resolves and chargebacks arriving before the deposit they reference for up
+    /// to the given number of rows, retrying them once the deposit arrives.
+    #[arg(long, value_name = "ROWS")]
+    pub reorder_window: Option<u64>,
+
+    /// The number of decimal places amounts are rounded to as they are applied and output.
+    #[arg(
+        long,
+        value_name = "PLACES",
+        default_value_t = DEFAULT_DECIMAL_PLACES,
+        value_parser = clap::value_parser!(u32).range(0..=i64::from(MAX_DECIMAL_PLACES)),
+    )]
+    pub precision: u32,
+
+    /// How amounts are rounded to the precision: `bankers`, `half_up` or `truncate`.
+    #[arg(long, value_name = "MODE", default_value = "bankers")]
+    pub rounding: Rounding,
+}
+
+impl LedgerArgs {
+    pub fn precision(&self) -> Precision {
+        Precision {
+            decimal_places: self.precision,
+            rounding: self.rounding,
+        }
+    }
+
+    /// Load the ledger configuration from the files given.
+    pub fn config(&self) -> Result<LedgerConfig, Error> {
+        let mut config = LedgerConfig {
+            pending_deposits: self.pending_deposits,
+            settlement_delay: self.settlement_delay,
+            reorder_window: self.reorder_window,
+            precision: self.precision(),
+            client_columns: self.client_columns.clone(),
+            ..LedgerConfig::default()
+        };
+        if let Some(path) = &self.clients {
+            config.load_clients(read_config(path, read_client_master)?);
+        }
+        if let Some(path) = &self.fees {
+            config.fees = read_config(path, read_fee_schedule)?;
+        }
+        if let Some(path) = &self.limits {
+            read_config(path, |rdr| read_limits(rdr, &mut config.limits))?;
+        }
+        if let Some(path) = &self.tiers {
+            read_config(path, |rdr| read_client_tiers(rdr, &mut config.limits))?;
+        }
+        if let Some(path) = &self.rules {
+            config.rules = read_config(path, read_rules)?;
+        }
+        if let Some(path) = &self.credit_limits {
+            config
+                .credit_limits
+                .extend(read_config(path, read_credit_limits)?);
+        }
+        if let Some(path) = &self.reserves {
+            config.reserves = read_config(path, read_reserves)?;
+        }
+        Ok(config)
+    }
+}
+
+/// Read a configuration file, reporting which file was invalid on failure. A file which cannot be
+/// parsed fails with `ErrorKind::InvalidInput`.
+fn read_config<T>(
+    path: &Path,
+    read: impl FnOnce(fs::File) -> Result<T, ParseError>,
+) -> Result<T, Error> {
+    let file = fs::File::open(path)
+        .map_err(|e| Error::new(e.kind(), format!("failed to open {}: {e}", path.display())))?;
+    read(file).map_err(|e| Error::new(ErrorKind::InvalidInput, format!("{}: {e}", path.display())))
+}
+
+#[derive(Debug, Args)]
+pub struct IoArgs {
+    #[command(flatten)]
+    pub input: InputArgs,
+
+    /// Output file, or `-` to write to stdout.
+    #[arg(short, long, value_name = "PATH", default_value = "-")]
+    pub output: String,
+
+    /// Format of the output file.
+    #[arg(long, value_name = "FORMAT", default_value = "csv")]
+    pub output_format: Format,
+
+    /// Write a row for each of a client's wallets, rather than one row per client.
+    #[arg(long)]
+    pub wallets: bool,
+}
+
+/// Expand the input arguments into the ordered list of files to process. Directories expand to
+/// the files they contain and glob patterns to the files they match, each sorted by name, and
+/// either failing to expand to any file is an error. Standard input, `-`, may only be given once
+/// since it can only be read once. Invalid inputs fail with `ErrorKind::InvalidInput`.
+pub fn expand_inputs(inputs: &[String]) -> Result<Vec<PathBuf>, Error> {
+    if inputs.iter().filter(|input| *input == "-").count() > 1 {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            "standard input `-` may only be given once",
+        ));
+    }
+    let mut paths = Vec::new();
+    for input in inputs {
+        let path = PathBuf::from(input);
+        if path.is_dir() {
+            let mut files = fs::read_dir(&path)?
+                .map(|entry| entry.map(|entry| entry.path()))
+                .collect::<Result<Vec<_>, _>>()?;
+            files.retain(|file| file.is_file());
+            if files.is_empty() {
+                return Err(Error::new(
+                    ErrorKind::InvalidInput,
+                    format!("no files in {input}"),
+                ));
+            }
+            files.sort();
+            paths.extend(files);
+        } else if input != "-" && !path.exists() && input.contains(['*', '?', '[']) {
+            let pattern = glob::glob(input).map_err(|e| Error::new(ErrorKind::InvalidInput, e))?;
+            let mut files = pattern
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|e| Error::other(e.to_string()))?;
+            files.retain(|file| file.is_file());
+            if files.is_empty() {
+                return Err(Error::new(
+                    ErrorKind::InvalidInput,
+                    format!("no files match {input}"),
+                ));
+            }
+            paths.extend(files);
+        } else {
+            paths.push(path);
+        }
+    }
+    Ok(paths)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_to_process() {
+        let cli = Cli::try_parse_args(["payments-engine", "transactions.csv", "--strict"]).unwrap();
+        let Command::Process(io) = cli.command else {
+            panic!("expected process command");
+        };
+        assert_eq!(io.input.inputs, ["transactions.csv"]);
+        assert_eq!(io.input.options().mode, ParseMode::Strict);
+        assert_eq!(io.output, "-");
+        assert_eq!(io.output_format, Format::Csv);
+
+        let cli = Cli::try_parse_args(["payments-engine", "--fees", "fees.csv", "-"]).unwrap();
+        assert!(matches!(cli.command, Command::Process(_)));
+    }
+
+    #[test]
+    fn statement() {
+        let cli = Cli::try_parse_args([
+            "payments-engine",
+            "statement",
+            "-",
+            "--client",
+            "7",
+            "--input-format",
+            "tsv",
+            "--fees",
+            "fees.csv",
+        ])
+        .unwrap();
+        let Command::Statement { io, client } = cli.command else {
+            panic!("expected statement command");
+        };
+        assert_eq!(client, 7);
+        assert_eq!(io.input.inputs, ["-"]);
+        assert_eq!(io.input.input_format, Format::Tsv);
+        assert_eq!(io.input.ledger.fees, Some(PathBuf::from("fees.csv")));
+    }
+
+    #[test]
+    fn client_columns() {
+        let cli = Cli::try_parse_args([
+            "payments-engine",
+            "a.csv",
+            "--clients",
+            "clients.csv",
+            "--client-columns",
+            "tier,risk_rating",
+        ])
+        .unwrap();
+        let Command::Process(io) = cli.command else {
+            panic!("expected process command");
+        };
+        assert_eq!(
+            io.input.ledger.client_columns,
+            [ClientField::Tier, ClientField::RiskRating]
+        );
+
+        assert!(Cli::try_parse_args([
+            "payments-engine",
+            "a.csv",
+            "--clients",
+            "clients.csv",
+            "--client-columns",
+            "balance",
+        ])
+        .is_err());
+    }
+
+    #[test]
+    fn multiple_inputs() {
+        let cli = Cli::try_parse_args(["payments-engine", "process", "b.csv", "a.csv"]).unwrap();
+        let Command::Process(io) = cli.command else {
+            panic!("expected process command");
+        };
+        assert_eq!(io.input.inputs, ["b.csv", "a.csv"]);
+    }
+
+    #[test]
+    fn expand_directories_and_patterns() {
+        let dir = std::env::temp_dir().join(format!("payments-engine-{}", std::process::id()));
+        fs::create_dir_all(dir.join("shards")).unwrap();
+        for name in [
+            "shards/02.csv",
+            "shards/01.csv",
+            "shards/10.csv",
+            "extra.csv",
+        ] {
+            fs::write(dir.join(name), "type,client,tx,amount\n").unwrap();
+        }
+
+        let inputs = [
+            dir.join("extra.csv").display().to_string(),
+            dir.join("shards").display().to_string(),
+            dir.join("shards/0*.csv").display().to_string(),
+            dir.join("*").display().to_string(),
+        ];
+        let got = expand_inputs(&inputs);
+        let missing = expand_inputs(&[dir.join("*.tsv").display().to_string()]);
+        let directories = expand_inputs(&[dir.join("shard*").display().to_string()]);
+        fs::remove_dir_all(&dir).unwrap();
+
+        let want = [
+            "extra.csv",
+            "shards/01.csv",
+            "shards/02.csv",
+            "shards/10.csv",
+            "shards/01.csv",
+            "shards/02.csv",
+            "extra.csv",
+        ]
+        .map(|name| dir.join(name));
+        assert_eq!(got.unwrap(), want);
+        assert!(missing.is_err());
+        assert!(directories.is_err());
+        assert!(expand_inputs(&["-".to_string(), "-".to_string()]).is_err());
+    }
+
+    #[test]
+    fn expand_empty_directory() {
+        let dir =
+            std::env::temp_dir().join(format!("payments-engine-empty-{}", std::process::id()));
+        fs::create_dir_all(dir.join("nested")).unwrap();
+        let got = expand_inputs(&[dir.display().to_string()]);
+        fs::remove_dir_all(&dir).unwrap();
+
+        // Assert that a directory without any files fails like a pattern matching none.
+        assert_eq!(got.unwrap_err().kind(), ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn usage_errors() {
+        assert!(Cli::try_parse_args(["payments-engine"]).is_err());
+        assert!(Cli::try_parse_args(["payments-engine", "replay", "a.csv"]).is_err());
+        assert!(
+            Cli::try_parse_args(["payments-engine", "a.csv", "--settlement-delay", "2"]).is_err()
+        );
+        assert!(
+            Cli::try_parse_args(["payments-engine", "a.csv", "--output-format", "xml"]).is_err()
+        );
+    }
+}
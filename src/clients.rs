@@ -0,0 +1,143 @@
+use crate::types::{Amount, ClientId};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+
+/// Whether a client is permitted to transact.
+#[derive(Debug, Default, Deserialize, Clone, Copy, Eq, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ClientStatus {
+    #[default]
+    Active,
+    Suspended,
+}
+
+/// The risk a client has been assessed as posing.
+#[derive(Debug, Deserialize, Clone, Copy, Eq, Ord, PartialEq, PartialOrd)]
+#[serde(rename_all = "lowercase")]
+pub enum RiskRating {
+    Low,
+    Medium,
+    High,
+}
+
+/// The master data held for a client, loaded before any transactions are applied.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ClientRecord {
+    /// The withdrawal limits tier the client belongs to.
+    pub tier: Option<String>,
+    pub status: ClientStatus,
+    pub country: Option<String>,
+    pub risk_rating: Option<RiskRating>,
+    pub credit_limit: Option<Amount>,
+}
+
+/// The master data of every known client.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ClientMaster {
+    records: HashMap<ClientId, ClientRecord>,
+}
+
+impl ClientMaster {
+    /// Set a client's master data, replacing any existing record.
+    pub fn insert(&mut self, client_id: ClientId, record: ClientRecord) {
+        self.records.insert(client_id, record);
+    }
+
+    pub fn get(&self, client_id: ClientId) -> Option<&ClientRecord> {
+        self.records.get(&client_id)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&ClientId, &ClientRecord)> {
+        self.records.iter()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+}
+
+/// A field of the client master data which can be included in the accounts output.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum ClientField {
+    Tier,
+    Status,
+    Country,
+    RiskRating,
+}
+
+impl ClientField {
+    /// The value of the field for a client, which is empty when the client has no record or the
+    /// record does not set it.
+    pub fn value(self, record: Option<&ClientRecord>) -> String {
+        let Some(record) = record else {
+            return String::new();
+        };
+        match self {
+            ClientField::Tier => record.tier.clone().unwrap_or_default(),
+            ClientField::Status => format!("{}", record.status),
+            ClientField::Country => record.country.clone().unwrap_or_default(),
+            ClientField::RiskRating => record
+                .risk_rating
+                .map_or_else(String::new, |rating| rating.to_string()),
+        }
+    }
+}
+
+impl FromStr for ClientField {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "tier" => Ok(ClientField::Tier),
+            "status" => Ok(ClientField::Status),
+            "country" => Ok(ClientField::Country),
+            "risk_rating" => Ok(ClientField::RiskRating),
+            _ => Err(format!(
+                "unknown client field {s:?}, expected one of: tier, status, country, risk_rating"
+            )),
+        }
+    }
+}
+
+impl fmt::Display for ClientStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ClientStatus::Active => write!(f, "active"),
+            ClientStatus::Suspended => write!(f, "suspended"),
+        }
+    }
+}
+
+impl fmt::Display for RiskRating {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RiskRating::Low => write!(f, "low"),
+            RiskRating::Medium => write!(f, "medium"),
+            RiskRating::High => write!(f, "high"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn field_values() {
+        let record = ClientRecord {
+            tier: Some("premium".to_string()),
+            risk_rating: Some(RiskRating::High),
+            ..ClientRecord::default()
+        };
+        assert_eq!(ClientField::Tier.value(Some(&record)), "premium");
+        assert_eq!(ClientField::Status.value(Some(&record)), "active");
+        assert_eq!(ClientField::Country.value(Some(&record)), "");
+        assert_eq!(ClientField::RiskRating.value(Some(&record)), "high");
+        assert_eq!(ClientField::Tier.value(None), "");
+
+        assert_eq!("risk_rating".parse(), Ok(ClientField::RiskRating));
+        assert!("balance".parse::<ClientField>().is_err());
+    }
+}
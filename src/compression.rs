@@ -0,0 +1,141 @@
+use flate2::bufread::MultiGzDecoder;
+use std::io::{self, BufReader, Read};
+
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+
+/// A reader which transparently decompresses gzip and zstd input, detected by the magic bytes at
+/// the start of the stream. Any other input is passed through unchanged. Decompression is
+/// streamed, so the input is never fully decompressed in memory or to disk.
+pub struct Decompressor<R: Read> {
+    state: State<R>,
+}
+
+/// The input, with the bytes read to detect its format put back in front of the rest.
+type Source<R> = BufReader<io::Chain<io::Cursor<Vec<u8>>, R>>;
+
+enum State<R: Read> {
+    /// The format has not yet been detected as nothing has been read.
+    Pending(Option<R>),
+    Plain(Source<R>),
+    Gzip(MultiGzDecoder<Source<R>>),
+    Zstd(zstd::Decoder<'static, Source<R>>),
+}
+
+impl<R: Read> Decompressor<R> {
+    pub fn new(rdr: R) -> Self {
+        Decompressor {
+            state: State::Pending(Some(rdr)),
+        }
+    }
+
+    /// Read the start of the input to determine its format. Reading continues until the longest
+    /// magic number is available or the input ends, since a pipe may deliver as little as one
+    /// byte at a time.
+    fn detect(mut rdr: R) -> io::Result<State<R>> {
+        let mut magic = Vec::with_capacity(ZSTD_MAGIC.len());
+        (&mut rdr)
+            .take(ZSTD_MAGIC.len() as u64)
+            .read_to_end(&mut magic)?;
+        let (gzip, zstd) = (
+            magic.starts_with(&GZIP_MAGIC),
+            magic.starts_with(&ZSTD_MAGIC),
+        );
+        let rdr = BufReader::new(io::Cursor::new(magic).chain(rdr));
+        if gzip {
+            Ok(State::Gzip(MultiGzDecoder::new(rdr)))
+        } else if zstd {
+            Ok(State::Zstd(zstd::Decoder::with_buffer(rdr)?))
+        } else {
+            Ok(State::Plain(rdr))
+        }
+    }
+}
+
+impl<R: Read> Read for Decompressor<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if let State::Pending(rdr) = &mut self.state {
+            let rdr = rdr
+                .take()
+                .ok_or_else(|| io::Error::other("input format detection previously failed"))?;
+            self.state = Self::detect(rdr)?;
+        }
+        match &mut self.state {
+            State::Pending(_) => unreachable!("input format has been detected"),
+            State::Plain(rdr) => rdr.read(buf),
+            State::Gzip(rdr) => rdr.read(buf),
+            State::Zstd(rdr) => rdr.read(buf),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::{write::GzEncoder, Compression};
+    use std::io::Write;
+
+    const CSV: &str = "type, client, tx, amount\ndeposit, 1, 1, 1.5\n";
+
+    fn decompress(input: &[u8]) -> String {
+        let mut got = String::new();
+        Decompressor::new(input).read_to_string(&mut got).unwrap();
+        got
+    }
+
+    #[test]
+    fn plain() {
+        assert_eq!(decompress(CSV.as_bytes()), CSV);
+        assert_eq!(decompress(b""), "");
+    }
+
+    #[test]
+    fn gzip() {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(CSV.as_bytes()).unwrap();
+        let compressed = encoder.finish().unwrap();
+
+        assert_eq!(decompress(&compressed), CSV);
+    }
+
+    #[test]
+    fn zstd() {
+        let compressed = zstd::encode_all(CSV.as_bytes(), 0).unwrap();
+
+        assert_eq!(decompress(&compressed), CSV);
+    }
+
+    /// A reader delivering one byte per read, like a slow pipe.
+    struct Trickle<'a>(&'a [u8]);
+
+    impl Read for Trickle<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let Some((first, rest)) = self.0.split_first() else {
+                return Ok(0);
+            };
+            buf[0] = *first;
+            self.0 = rest;
+            Ok(1)
+        }
+    }
+
+    #[test]
+    fn trickle() {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(CSV.as_bytes()).unwrap();
+        let compressed = encoder.finish().unwrap();
+
+        for input in [compressed, zstd::encode_all(CSV.as_bytes(), 0).unwrap()] {
+            let mut got = String::new();
+            Decompressor::new(Trickle(&input))
+                .read_to_string(&mut got)
+                .unwrap();
+            assert_eq!(got, CSV);
+        }
+        let mut got = String::new();
+        Decompressor::new(Trickle(b"a"))
+            .read_to_string(&mut got)
+            .unwrap();
+        assert_eq!(got, "a");
+    }
+}
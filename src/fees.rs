@@ -0,0 +1,103 @@
+use crate::types::{Amount, TransactionType};
+use rust_decimal::Decimal;
+use std::collections::HashMap;
+
+/// A fee charged for a single transaction, made up of a flat amount and a percentage of the
+/// transaction amount.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Fee {
+    pub flat: Amount,
+    pub percent: Decimal,
+}
+
+impl Fee {
+    /// The fee charged for a transaction of the given amount, before it is rounded to the ledger's
+    /// precision, or `None` if it is too large to represent. Transactions which carry no amount of
+    /// their own are only charged the flat fee. The percentage is of the amount's size, so that a
+    /// negative amount is never paid a fee.
+    pub fn charge(&self, amount: Option<Amount>) -> Option<Amount> {
+        let percentage = match amount {
+            Some(amount) => amount.0.abs().checked_mul(self.percent)? / Decimal::ONE_HUNDRED,
+            None => Decimal::ZERO,
+        };
+        self.flat.checked_add(Amount(percentage))
+    }
+}
+
+/// The fees charged for each `TransactionType`. Transaction types without a fee are free.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct FeeSchedule {
+    fees: HashMap<TransactionType, Fee>,
+}
+
+impl FeeSchedule {
+    /// Set the fee charged for a transaction type, replacing any existing fee.
+    pub fn insert(&mut self, transaction_type: TransactionType, fee: Fee) {
+        self.fees.insert(transaction_type, fee);
+    }
+
+    /// The fee charged for a transaction of the given type and amount, before it is rounded, or
+    /// `None` if it is too large to represent.
+    pub fn fee(&self, transaction_type: TransactionType, amount: Option<Amount>) -> Option<Amount> {
+        self.fees
+            .get(&transaction_type)
+            .map_or(Some(Amount::from(0)), |fee| fee.charge(amount))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.fees.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn charge() {
+        let fee = Fee {
+            flat: Amount::from(Decimal::new(5, 1)),
+            percent: Decimal::new(15, 1),
+        };
+
+        // 0.5 + 1.5% of 12.3456 = 0.685184
+        assert_eq!(
+            fee.charge(Some(Amount::from(Decimal::new(123456, 4)))),
+            Some(Amount::from(Decimal::new(685184, 6)))
+        );
+        assert_eq!(fee.charge(None), Some(Amount::from(Decimal::new(5, 1))));
+        assert_eq!(
+            fee.charge(Some(Amount::from(-10))),
+            Some(Amount::from(Decimal::new(65, 2)))
+        );
+
+        // Assert that fees too large to represent are not charged.
+        assert_eq!(fee.charge(Some(Amount::from(Decimal::MAX))), None);
+        let fee = Fee {
+            flat: Amount::from(Decimal::MAX),
+            percent: Decimal::ONE,
+        };
+        assert_eq!(fee.charge(Some(Amount::from(100))), None);
+    }
+
+    #[test]
+    fn schedule() {
+        let mut schedule = FeeSchedule::default();
+        schedule.insert(
+            TransactionType::Chargeback,
+            Fee {
+                flat: Amount::from(15),
+                percent: Decimal::ZERO,
+            },
+        );
+
+        assert_eq!(
+            schedule.fee(TransactionType::Chargeback, Some(Amount::from(100))),
+            Some(Amount::from(15))
+        );
+        assert_eq!(
+            schedule.fee(TransactionType::Deposit, Some(Amount::from(100))),
+            Some(Amount::from(0))
+        );
+    }
+}
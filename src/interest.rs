@@ -0,0 +1,197 @@
+use crate::precision::{Precision, Rounding};
+use crate::types::{Amount, Timestamp};
+use rust_decimal::Decimal;
+use std::collections::BTreeMap;
+use std::ops::Range;
+use thiserror::Error;
+
+/// The number of days an annual interest rate is spread over.
+const DAYS_PER_YEAR: u32 = 365;
+
+#[derive(Error, Debug, PartialEq)]
+pub enum InterestError {
+    #[error("interest period must end after it starts")]
+    EmptyPeriod,
+    #[error("interest has already been accrued until {accrued_until:?}")]
+    PeriodAccrued { accrued_until: Timestamp },
+    #[error("interest accrued would overflow")]
+    Overflow,
+}
+
+/// Annual interest rates, as percentages, banded by balance. A day's balance earns the rate of
+/// the highest band whose minimum it meets, and balances which are not positive earn nothing.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct InterestRates {
+    bands: BTreeMap<Decimal, Decimal>,
+}
+
+impl InterestRates {
+    /// Set the annual rate earned by balances of at least `minimum`, replacing any existing rate.
+    pub fn insert(&mut self, minimum: Amount, percent: Decimal) {
+        self.bands.insert(minimum.0, percent);
+    }
+
+    /// The annual rate earned by a balance.
+    pub fn rate(&self, balance: Amount) -> Decimal {
+        if balance.0 <= Decimal::ZERO {
+            return Decimal::ZERO;
+        }
+        self.bands
+            .range(..=balance.0)
+            .next_back()
+            .map_or(Decimal::ZERO, |(_, percent)| *percent)
+    }
+}
+
+/// The end of day balances of an account, used to accrue interest by the daily balance method,
+/// along with the rounding residue carried between accruals.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct InterestAccrual {
+    balances: BTreeMap<u64, Amount>,
+    residue: Decimal,
+}
+
+impl InterestAccrual {
+    /// Record the balance of the account at the given time, which stands until the end of the day
+    /// unless a later balance is recorded.
+    pub fn record(&mut self, now: Timestamp, balance: Amount) {
+        self.balances.insert(now.day(), balance);
+    }
+
+    /// The interest earned but not yet credited because it was lost to rounding.
+    pub fn residue(&self) -> Decimal {
+        self.residue
+    }
+
+    /// Accrue interest on each day's closing balance in the period, returning the amount to be
+    /// credited truncated to the ledger's precision, whatever its rounding. The remainder is
+    /// carried forward as residue into the next accrual, so no interest is lost to rounding.
+    /// Balances recorded before the period, other than the one standing at its start, are
+    /// discarded. Fails without changing anything if the interest is too large to represent.
+    pub fn accrue(
+        &mut self,
+        rates: &InterestRates,
+        period: Range<Timestamp>,
+        precision: &Precision,
+    ) -> Result<Amount, InterestError> {
+        let (start, end) = (period.start.day(), period.end.day());
+        let opening = self
+            .balances
+            .range(..=start)
+            .next_back()
+            .map(|(_, balance)| (start, *balance));
+        let changes = self
+            .balances
+            .range(start + 1..end)
+            .map(|(day, balance)| (*day, *balance));
+
+        // Each balance stands from the day it was recorded until the next change, or the end of
+        // the period.
+        let mut segments = opening.into_iter().chain(changes).peekable();
+        let mut earned = self.residue;
+        while let Some((day, balance)) = segments.next() {
+            let until = segments.peek().map_or(end, |(next, _)| *next);
+            let interest = balance
+                .0
+                .checked_mul(rates.rate(balance))
+                .and_then(|interest| interest.checked_mul(Decimal::from(until - day)))
+                .ok_or(InterestError::Overflow)?;
+            earned = earned
+                .checked_add(interest / Decimal::ONE_HUNDRED / Decimal::from(DAYS_PER_YEAR))
+                .ok_or(InterestError::Overflow)?;
+        }
+
+        let truncate = Precision {
+            rounding: Rounding::Truncate,
+            ..*precision
+        };
+        let credit = truncate.round(Amount(earned)).0;
+        self.residue = earned - credit;
+        if let Some((day, _)) = self.balances.range(..=start).next_back() {
+            let day = *day;
+            self.balances = self.balances.split_off(&day);
+        }
+        Ok(Amount(credit))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DAY: u64 = Timestamp::SECONDS_PER_DAY;
+
+    #[test]
+    fn rate_bands() {
+        let mut rates = InterestRates::default();
+        rates.insert(Amount::from(0), Decimal::ONE);
+        rates.insert(Amount::from(1000), Decimal::TWO);
+
+        assert_eq!(rates.rate(Amount::from(-5)), Decimal::ZERO);
+        assert_eq!(rates.rate(Amount::from(0)), Decimal::ZERO);
+        assert_eq!(rates.rate(Amount::from(999)), Decimal::ONE);
+        assert_eq!(rates.rate(Amount::from(1000)), Decimal::TWO);
+    }
+
+    #[test]
+    fn accrue_daily_balances() {
+        let mut rates = InterestRates::default();
+        rates.insert(Amount::from(0), Decimal::from(DAYS_PER_YEAR));
+
+        // 100 for the first two days, then 200 for the remaining three, at 1% a day.
+        let precision = Precision::default();
+        let mut accrual = InterestAccrual::default();
+        accrual.record(Timestamp(DAY / 2), Amount::from(50));
+        accrual.record(Timestamp(DAY - 1), Amount::from(100));
+        accrual.record(Timestamp(3 * DAY), Amount::from(200));
+        let credit = accrual.accrue(&rates, Timestamp(DAY)..Timestamp(6 * DAY), &precision);
+        assert_eq!(credit, Ok(Amount::from(8)));
+
+        // Only the balance standing at the start of the period is kept.
+        assert_eq!(accrual.balances.len(), 2);
+        let credit = accrual.accrue(&rates, Timestamp(6 * DAY)..Timestamp(7 * DAY), &precision);
+        assert_eq!(credit, Ok(Amount::from(2)));
+    }
+
+    #[test]
+    fn accrue_overflow() {
+        let mut rates = InterestRates::default();
+        rates.insert(Amount::from(0), Decimal::from(10_i64.pow(12)));
+
+        // Assert that interest too large to represent fails without changing the accrual.
+        let precision = Precision::default();
+        let mut accrual = InterestAccrual::default();
+        accrual.record(Timestamp(0), Amount::MAX);
+        let unchanged = accrual.clone();
+        let period = Timestamp(DAY)..Timestamp(100_000 * DAY);
+        assert_eq!(
+            accrual.accrue(&rates, period, &precision),
+            Err(InterestError::Overflow)
+        );
+        assert_eq!(accrual, unchanged);
+    }
+
+    #[test]
+    fn accrue_residue() {
+        let mut rates = InterestRates::default();
+        rates.insert(Amount::from(0), Decimal::new(146, 2));
+
+        // A day's interest on 1 at 1.46% is 0.00004, less than the smallest amount credited.
+        let precision = Precision::default();
+        let mut accrual = InterestAccrual::default();
+        accrual.record(Timestamp(0), Amount::from(1));
+        let day = |day: u64| Timestamp(day * DAY)..Timestamp((day + 1) * DAY);
+        assert_eq!(
+            accrual.accrue(&rates, day(0), &precision),
+            Ok(Amount::from(0))
+        );
+        assert_eq!(accrual.residue(), Decimal::new(4, 5));
+
+        // The residue is credited once enough has accrued.
+        let credited = (1..10)
+            .map(|n| accrual.accrue(&rates, day(n), &precision).unwrap().0)
+            .sum::<Decimal>();
+        assert_eq!(credited, Decimal::new(4, 4));
+        assert_eq!(accrual.residue(), Decimal::ZERO);
+    }
+}
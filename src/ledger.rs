@@ -1,15 +1,27 @@
-use crate::parser::{self, read_input, LedgerEntry};
+use crate::clients::{ClientField, ClientMaster};
+use crate::fees::FeeSchedule;
+use crate::interest::{InterestAccrual, InterestError, InterestRates};
+use crate::limits::{Limit, LimitSchedule, WithdrawalUsage};
+use crate::parser::{self, read_input, LedgerEntry, ParseError, Position, Row};
+use crate::precision::Precision;
+use crate::reserves::{Reserve, ReservePolicy};
+use crate::rules::{Action, RuleSet, Verdict};
 use crate::types::{
-    Amount, Chargeback, ClientId, Deposit, Dispute, Resolve, Transaction, TransactionId, Withdrawal,
+    AccountId, Amount, Arbitrate, Authorize, Capture, Chargeback, ClientId, Close, CreditLimit,
+    Deposit, Dispute, Hold, HoldReason, Interest, Refund, Release, Represent, Resolve, Reversal,
+    Settle, Timestamp, Transaction, TransactionId, Transfer, Void, WalletId, Withdrawal,
 };
+use rust_decimal::Decimal;
+use std::borrow::Cow;
+use std::collections::{BTreeMap, VecDeque};
 use std::io::Error;
-use std::{
-    collections::{HashMap, HashSet},
-    io,
-};
+use std::iter;
+use std::ops::{AddAssign, Range};
+use std::{collections::HashMap, io};
+use std::{fmt, mem};
 use thiserror::Error;
 
-#[derive(Error, Debug, PartialEq)]
+#[derive(Error, Debug, Clone, PartialEq)]
 pub enum TransactionError {
     #[error("client {client_id:?} does not exist, transaction failed")]
     ClientDoesNotExist { client_id: ClientId },
@@ -24,6 +36,20 @@ pub enum TransactionError {
         client_id: ClientId,
         transaction_id: TransactionId,
     },
+    #[error("failed to dispute {requested:?}, transaction id {transaction_id:?} has {disputable:?} remaining disputable for client {client_id:?}")]
+    DisputeExceedsBalance {
+        client_id: ClientId,
+        transaction_id: TransactionId,
+        requested: Amount,
+        disputable: Amount,
+    },
+    #[error("failed to settle {requested:?}, transaction id {transaction_id:?} has {disputed:?} under dispute for client {client_id:?}")]
+    ExceedsDisputedAmount {
+        client_id: ClientId,
+        transaction_id: TransactionId,
+        requested: Amount,
+        disputed: Amount,
+    },
     #[error("failed to resolve dispute, transaction id {transaction_id:?} is not under dispute for client {client_id:?})")]
     ResolveFailed {
         client_id: ClientId,
@@ -34,79 +60,561 @@ pub enum TransactionError {
         client_id: ClientId,
         transaction_id: TransactionId,
     },
+    #[error("transaction id {transaction_id:?} for client {client_id:?} has not yet settled")]
+    DepositPending {
+        client_id: ClientId,
+        transaction_id: TransactionId,
+    },
+    #[error("failed to settle, transaction id {transaction_id:?} is not a pending deposit for client {client_id:?}")]
+    SettleFailed {
+        client_id: ClientId,
+        transaction_id: TransactionId,
+    },
+    #[error("failed to refund, transaction id {transaction_id:?} is not a deposit for client {client_id:?}")]
+    RefundFailed {
+        client_id: ClientId,
+        transaction_id: TransactionId,
+    },
+    #[error("failed to refund {requested:?}, transaction id {transaction_id:?} has {refundable:?} remaining refundable for client {client_id:?}")]
+    RefundExceedsDeposit {
+        client_id: ClientId,
+        transaction_id: TransactionId,
+        requested: Amount,
+        refundable: Amount,
+    },
+    #[error("client {client_id:?} has insufficient funds to refund {requested:?} (available {available:?})")]
+    RefundInsufficientFunds {
+        client_id: ClientId,
+        available: Amount,
+        requested: Amount,
+    },
+    #[error("credit limit {requested:?} for client {client_id:?} must not be negative")]
+    NegativeCreditLimit {
+        client_id: ClientId,
+        requested: Amount,
+    },
+    #[error("failed to reverse, transaction id {transaction_id:?} is not a deposit, withdrawal or authorization for client {client_id:?}")]
+    ReversalFailed {
+        client_id: ClientId,
+        transaction_id: TransactionId,
+    },
+    #[error("cannot reverse transaction id {transaction_id:?} for client {client_id:?}, {reason}")]
+    ReversalInconsistent {
+        client_id: ClientId,
+        transaction_id: TransactionId,
+        reason: &'static str,
+    },
+    #[error("failed to represent, transaction id {transaction_id:?} has not been charged back for client {client_id:?}")]
+    RepresentFailed {
+        client_id: ClientId,
+        transaction_id: TransactionId,
+    },
+    #[error("failed to represent {requested:?}, transaction id {transaction_id:?} has {charged_back:?} charged back for client {client_id:?}")]
+    RepresentExceedsChargeback {
+        client_id: ClientId,
+        transaction_id: TransactionId,
+        requested: Amount,
+        charged_back: Amount,
+    },
+    #[error("failed to arbitrate, transaction id {transaction_id:?} has not been represented for client {client_id:?}")]
+    ArbitrateFailed {
+        client_id: ClientId,
+        transaction_id: TransactionId,
+    },
+    #[error("failed to arbitrate {requested:?}, transaction id {transaction_id:?} has {represented:?} represented for client {client_id:?}")]
+    ArbitrateExceedsRepresentment {
+        client_id: ClientId,
+        transaction_id: TransactionId,
+        requested: Amount,
+        represented: Amount,
+    },
+    #[error("client {client_id:?} has insufficient funds to authorize {requested:?} (available {available:?})")]
+    AuthorizeInsufficientFunds {
+        client_id: ClientId,
+        available: Amount,
+        requested: Amount,
+    },
+    #[error("failed to authorize, transaction id {transaction_id:?} is already authorized for client {client_id:?}")]
+    AuthorizeDuplicate {
+        client_id: ClientId,
+        transaction_id: TransactionId,
+    },
+    #[error("client {client_id:?} has insufficient funds to hold {requested:?} (available {available:?})")]
+    HoldInsufficientFunds {
+        client_id: ClientId,
+        available: Amount,
+        requested: Amount,
+    },
+    #[error("client {client_id:?} has insufficient funds in {wallet:?} to transfer {requested:?} (available {available:?})")]
+    TransferInsufficientFunds {
+        client_id: ClientId,
+        wallet: WalletId,
+        available: Amount,
+        requested: Amount,
+    },
+    #[error("failed to hold, transaction id {transaction_id:?} is already held for client {client_id:?}")]
+    HoldDuplicate {
+        client_id: ClientId,
+        transaction_id: TransactionId,
+    },
+    #[error(
+        "failed to release, transaction id {transaction_id:?} is not held for client {client_id:?}"
+    )]
+    ReleaseFailed {
+        client_id: ClientId,
+        transaction_id: TransactionId,
+    },
+    #[error("failed to release {requested:?}, transaction id {transaction_id:?} has {held:?} held for client {client_id:?}")]
+    ReleaseExceedsHold {
+        client_id: ClientId,
+        transaction_id: TransactionId,
+        requested: Amount,
+        held: Amount,
+    },
+    #[error("failed to capture, transaction id {transaction_id:?} is not authorized for client {client_id:?}")]
+    CaptureFailed {
+        client_id: ClientId,
+        transaction_id: TransactionId,
+    },
+    #[error("failed to capture {requested:?}, transaction id {transaction_id:?} has {remaining:?} authorized for client {client_id:?}")]
+    CaptureExceedsAuthorization {
+        client_id: ClientId,
+        transaction_id: TransactionId,
+        requested: Amount,
+        remaining: Amount,
+    },
+    #[error("failed to void, transaction id {transaction_id:?} is not authorized for client {client_id:?}")]
+    VoidFailed {
+        client_id: ClientId,
+        transaction_id: TransactionId,
+    },
     #[error("account has been locked for client {client_id:?}, operation failed)")]
     ClientAccountLocked { client_id: ClientId },
+    #[error("account has been closed for client {client_id:?}, operation failed")]
+    ClientAccountClosed { client_id: ClientId },
+    #[error("cannot close the account of client {client_id:?}, {reason}")]
+    CloseFailed {
+        client_id: ClientId,
+        reason: &'static str,
+    },
+    #[error("client {client_id:?} withdrawal of {requested:?} exceeds the {limit} withdrawal limit (remaining {remaining:?})")]
+    LimitExceeded {
+        client_id: ClientId,
+        limit: Limit,
+        requested: Amount,
+        remaining: Amount,
+    },
+    #[error(
+        "client {client_id:?} transaction would overflow their balances or the house accounts"
+    )]
+    AmountOverflow { client_id: ClientId },
+    #[error("client {client_id:?} transaction rejected by {verdict}")]
+    RuleRejected {
+        client_id: ClientId,
+        verdict: Verdict,
+    },
+    #[error("account has been locked for client {client_id:?} by {verdict}")]
+    RuleLocked {
+        client_id: ClientId,
+        verdict: Verdict,
+    },
+    #[error("client {client_id:?} has insufficient funds for a fee of {fee:?} (available {available:?})")]
+    FeeInsufficientFunds {
+        client_id: ClientId,
+        available: Amount,
+        fee: Amount,
+    },
+    #[error("client {client_id:?} transaction amount {requested:?} must be greater than zero")]
+    NonPositiveAmount {
+        client_id: ClientId,
+        requested: Amount,
+    },
+}
+
+/// Ensure that an amount given by a transaction is greater than zero. Amounts are only checked
+/// when parsed strictly, so the ledger checks those which would otherwise reverse the transaction.
+fn positive(client: ClientId, amount: Amount) -> Result<Amount, TransactionError> {
+    if amount.0 <= Decimal::ZERO {
+        return Err(TransactionError::NonPositiveAmount {
+            client_id: client,
+            requested: amount,
+        });
+    }
+    Ok(amount)
+}
+
+/// An error which caused an input row to be rejected, either because it could not be parsed or
+/// because the resulting transaction could not be applied to the ledger.
+#[derive(Error, Debug)]
+pub enum LoadError {
+    #[error(transparent)]
+    Parse(#[from] ParseError),
+    #[error(transparent)]
+    Transaction(#[from] TransactionError),
+}
+
+/// An input row which was rejected, along with the `Position` it was read from.
+#[derive(Error, Debug)]
+#[error("{position}: {error}")]
+pub struct Reject {
+    pub position: Position,
+    pub error: LoadError,
+}
+
+/// An input row which was applied, but flagged for review by a rule, along with the `Position` it
+/// was read from.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Alert {
+    pub position: Position,
+    pub verdict: Verdict,
+}
+
+impl fmt::Display for Alert {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: alert: {}", self.position, self.verdict)
+    }
+}
+
+/// An input row reported while loading, either because it was rejected or because it raised an
+/// alert.
+#[derive(Debug)]
+pub enum Report {
+    Reject(Reject),
+    Alert(Alert),
+}
+
+impl Report {
+    /// Where the reported row was read from.
+    pub fn position(&self) -> Position {
+        match self {
+            Report::Reject(reject) => reject.position,
+            Report::Alert(alert) => alert.position,
+        }
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Report::Reject(reject) => reject.fmt(f),
+            Report::Alert(alert) => alert.fmt(f),
+        }
+    }
+}
+
+/// How an input row which was not rejected was handled.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Applied {
+    /// The row's transaction was applied to the ledger.
+    Applied,
+    /// The row's transaction references a deposit which has not yet arrived, so it was parked
+    /// until the deposit is applied.
+    Parked,
+}
+
+/// Counts of the input rows which were applied to, or rejected by, the ledger.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct LoadSummary {
+    pub applied: usize,
+    pub rejected: usize,
+}
+
+impl AddAssign for LoadSummary {
+    fn add_assign(&mut self, other: Self) {
+        self.applied += other.applied;
+        self.rejected += other.rejected;
+    }
+}
+
+impl LoadSummary {
+    /// Record the outcome of a single input row. Parked rows are recorded once they are finally
+    /// applied or rejected.
+    pub fn record<E>(&mut self, result: &Result<Applied, E>) {
+        match result {
+            Ok(Applied::Applied) => self.applied += 1,
+            Ok(Applied::Parked) => {}
+            Err(_) => self.rejected += 1,
+        }
+    }
 }
 
 /// Represents a client's account in the ledger, including funds and whether the account is locked.
+#[derive(Clone)]
 pub struct Account {
     pub client_id: ClientId,
     pub available: Amount,
     pub held: Amount,
     pub locked: bool,
+    pub fees: Amount,
+    pub withdrawals: WithdrawalUsage,
+    pub credit_limit: Amount,
+    pub accrual: InterestAccrual,
+    pub reserve: Reserve,
+    pub pending: Amount,
+    pub closed: bool,
+    pub(crate) wallets: BTreeMap<WalletId, Wallet>,
+    /// How many of the client's deposits have funds charged back which are not yet represented.
+    /// Each keeps the account locked until it is represented.
+    pub(crate) chargeback_locks: usize,
+    /// Whether the account has been locked by an arbitration or a rule, which no representment
+    /// lifts.
+    pub(crate) permanently_locked: bool,
+}
+
+/// The funds in one of a client's wallets.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Wallet {
+    pub available: Amount,
+    pub held: Amount,
+}
+
+impl Wallet {
+    /// Add to the available and held funds, returning `None` if either overflows.
+    fn checked_add(self, available: Amount, held: Amount) -> Option<Wallet> {
+        Some(Wallet {
+            available: self.available.checked_add(available)?,
+            held: self.held.checked_add(held)?,
+        })
+    }
 }
 
-impl From<&Account> for LedgerEntry {
-    fn from(account: &Account) -> Self {
-        const DP: u32 = 4;
+impl Account {
+    /// The account as an output entry, with its balances rounded to the given precision.
+    pub fn entry(&self, precision: &Precision) -> LedgerEntry {
         LedgerEntry {
-            client: account.client_id,
-            available: Amount(account.available.0.round_dp(DP)),
-            held: Amount(account.held.0.round_dp(DP)),
-            total: Amount((account.available.0 + account.held.0).round_dp(DP)),
-            locked: account.locked,
+            client: AccountId::Client(self.client_id),
+            wallet: None,
+            available: precision.round(self.available),
+            held: precision.round(self.held),
+            total: precision.round(self.total()),
+            locked: self.locked,
+            fees: None,
+            credit_limit: None,
+            used_credit: None,
+            reserve: None,
+            pending: None,
+            closed: None,
+            tier: None,
+            status: None,
+            country: None,
+            risk_rating: None,
+            held_disputes: None,
+            held_authorizations: None,
+            held_aml: None,
+            held_fraud: None,
+            held_legal: None,
+            held_other: None,
         }
     }
 }
 
 impl Account {
-    fn new(client_id: ClientId) -> Self {
+    pub(crate) fn new(client_id: ClientId) -> Self {
         Account {
             client_id,
             available: Amount::from(0),
             held: Amount::from(0),
             locked: false,
+            fees: Amount::from(0),
+            withdrawals: WithdrawalUsage::default(),
+            credit_limit: Amount::from(0),
+            accrual: InterestAccrual::default(),
+            reserve: Reserve::default(),
+            pending: Amount::from(0),
+            closed: false,
+            wallets: BTreeMap::new(),
+            chargeback_locks: 0,
+            permanently_locked: false,
+        }
+    }
+
+    /// The funds in one of the client's wallets. The default wallet holds whatever funds are not
+    /// in any other wallet.
+    pub fn wallet(&self, wallet: WalletId) -> Wallet {
+        if wallet != WalletId::default() {
+            return self.wallets.get(&wallet).copied().unwrap_or_default();
+        }
+        let mut default = Wallet {
+            available: self.available,
+            held: self.held,
+        };
+        for other in self.wallets.values() {
+            default.available.0 = default.available.0.saturating_sub(other.available.0);
+            default.held.0 = default.held.0.saturating_sub(other.held.0);
+        }
+        default
+    }
+
+    /// The client's default wallet followed by every other wallet they have used, in order.
+    pub fn wallets(&self) -> impl Iterator<Item = (WalletId, Wallet)> + '_ {
+        let others = self.wallets.keys().copied();
+        std::iter::once(WalletId::default())
+            .chain(others)
+            .map(|wallet| (wallet, self.wallet(wallet)))
+    }
+
+    /// Track a change to the funds in one of the client's wallets, made alongside the same change
+    /// to their account. Changes to the default wallet need no tracking. Nothing changes if the
+    /// wallet's funds would overflow.
+    fn track(
+        &mut self,
+        wallet: WalletId,
+        available: Amount,
+        held: Amount,
+    ) -> Result<(), TransactionError> {
+        if wallet == WalletId::default() {
+            return Ok(());
+        }
+        let funds = self
+            .wallet(wallet)
+            .checked_add(available, held)
+            .ok_or(self.overflow())?;
+        self.wallets.insert(wallet, funds);
+        Ok(())
+    }
+
+    /// Change the client's available and held funds, along with those of one of their wallets.
+    /// Nothing changes if any of the funds would overflow.
+    fn adjust(
+        &mut self,
+        wallet: WalletId,
+        available: Amount,
+        held: Amount,
+    ) -> Result<(), TransactionError> {
+        let funds = Wallet {
+            available: self.available,
+            held: self.held,
+        }
+        .checked_add(available, held)
+        .ok_or(self.overflow())?;
+        self.track(wallet, available, held)?;
+        self.available = funds.available;
+        self.held = funds.held;
+        Ok(())
+    }
+
+    /// The error for a change which would overflow the client's funds.
+    fn overflow(&self) -> TransactionError {
+        TransactionError::AmountOverflow {
+            client_id: self.client_id,
+        }
+    }
+
+    /// The client's total funds, saturating at the largest amount which can be represented,
+    /// though credits are checked so that the total stays within range.
+    fn total(&self) -> Amount {
+        Amount(
+            self.available
+                .0
+                .saturating_add(self.held.0)
+                .saturating_add(self.reserve.balance().0)
+                .saturating_add(self.pending.0),
+        )
+    }
+
+    /// The client's total funds, or `None` if they are too large to represent.
+    fn checked_total(&self) -> Option<Amount> {
+        self.available
+            .checked_add(self.held)?
+            .checked_add(self.reserve.balance())?
+            .checked_add(self.pending)
+    }
+
+    /// Ensure that crediting an amount to the given balances, and so to the client's total funds,
+    /// cannot overflow. Funds moved within the account are bounded by its total, so only funds
+    /// credited from outside it need checking.
+    fn check_credit(&self, balances: &[Amount], amount: Amount) -> Result<(), TransactionError> {
+        let fits = |balance: Amount| balance.checked_add(amount).is_some();
+        if self.checked_total().is_some_and(fits) && balances.iter().copied().all(fits) {
+            Ok(())
+        } else {
+            Err(self.overflow())
         }
     }
 
+    /// How much of the client's overdraft is drawn, which is zero while available funds are
+    /// positive.
+    pub fn used_credit(&self) -> Amount {
+        Amount((-self.available.0).max(Default::default()))
+    }
+
     /// A deposit is a credit to the client's asset account, meaning it should increase the available and
     /// total funds of the client account.
-    fn deposit(&mut self, amount: Amount) -> Result<(), TransactionError> {
+    fn deposit(&mut self, wallet: WalletId, amount: Amount) -> Result<(), TransactionError> {
         if self.locked {
             return Err(TransactionError::ClientAccountLocked {
                 client_id: self.client_id,
             });
         }
-        self.available.0 += amount.0;
+        self.check_credit(&[self.available, self.wallet(wallet).available], amount)?;
+        self.adjust(wallet, amount, Amount::from(0))
+    }
+
+    /// A pending deposit is a credit to the client's asset account which has not yet settled,
+    /// meaning it should increase the pending and total funds of the client account, but not
+    /// their available funds.
+    fn deposit_pending(&mut self, amount: Amount) -> Result<(), TransactionError> {
+        if self.locked {
+            return Err(TransactionError::ClientAccountLocked {
+                client_id: self.client_id,
+            });
+        }
+        self.check_credit(&[self.pending], amount)?;
+        self.pending = self.pending.checked_add(amount).ok_or(self.overflow())?;
+        Ok(())
+    }
+
+    /// A settlement makes the funds of a pending deposit available, meaning the client's pending
+    /// funds should decrease by the amount settled and their available funds increase by the same
+    /// amount. Deposits settle even if the account has since been locked, since the funds have
+    /// already been received.
+    fn settle(&mut self, wallet: WalletId, amount: Amount) -> Result<(), TransactionError> {
+        let pending = self.pending.checked_sub(amount).ok_or(self.overflow())?;
+        self.adjust(wallet, amount, Amount::from(0))?;
+        self.pending = pending;
         Ok(())
     }
 
     /// A withdraw is a debit to the client's asset account, meaning it should decrease the available and
     /// total funds of the client account. If a client does not have sufficient available funds the withdrawal
-    /// should fail and the total amount of funds should not change.
-    fn withdrawal(&mut self, amount: Amount) -> Result<(), TransactionError> {
+    /// should fail and the total amount of funds should not change. Clients with an overdraft may draw
+    /// the available funds of their default wallet down to minus their credit limit. A negative
+    /// withdrawal, which lenient parsing lets through, credits the account like a deposit.
+    fn withdrawal(&mut self, wallet: WalletId, amount: Amount) -> Result<(), TransactionError> {
         if self.locked {
             return Err(TransactionError::ClientAccountLocked {
                 client_id: self.client_id,
             });
         }
-        if self.available.0 >= amount.0 {
-            self.available.0 -= amount.0;
-            Ok(())
+        let available = self.wallet(wallet).available;
+        if available.0.saturating_add(self.credit(wallet).0) >= amount.0 {
+            let debit = Amount(-amount.0);
+            self.check_credit(&[self.available, available], debit)?;
+            self.adjust(wallet, debit, Amount::from(0))
         } else {
             Err(TransactionError::WithdrawalInsufficientFunds {
                 client_id: self.client_id,
-                available: self.available,
+                available,
                 requested: amount,
             })
         }
     }
 
+    /// The credit a wallet's available funds may be drawn down by, which only the default wallet
+    /// is given.
+    fn credit(&self, wallet: WalletId) -> Amount {
+        if wallet == WalletId::default() {
+            self.credit_limit
+        } else {
+            Amount::from(0)
+        }
+    }
+
     /// A dispute represents a client's claim that a transaction was erroneous and should be reversed.
     /// The transaction shouldn't be reversed yet but the associated funds should be held. This means
     /// that the clients available funds should decrease by the amount disputed, their held funds should
     /// increase by the amount disputed, while their total funds should remain the same.
-    fn dispute(&mut self, amount: Amount) -> Result<(), TransactionError> {
+    fn dispute(&mut self, wallet: WalletId, amount: Amount) -> Result<(), TransactionError> {
         if self.locked {
             return Err(TransactionError::ClientAccountLocked {
                 client_id: self.client_id,
@@ -115,16 +623,14 @@ impl Account {
 
         // It is assumed that a client always has a sufficient available funds for the amount disputed
         // to be held.
-        self.available.0 -= amount.0;
-        self.held.0 += amount.0;
-        Ok(())
+        self.adjust(wallet, Amount(-amount.0), amount)
     }
 
     /// A resolve represents a resolution to a dispute, releasing the associated held funds. Funds that
     /// were previously disputed are no longer disputed. This means that the clients held funds should
     /// decrease by the amount no longer disputed, their available funds should increase by the amount
     /// no longer disputed, and their total funds should remain the same.
-    fn resolve(&mut self, amount: Amount) -> Result<(), TransactionError> {
+    fn resolve(&mut self, wallet: WalletId, amount: Amount) -> Result<(), TransactionError> {
         if self.locked {
             return Err(TransactionError::ClientAccountLocked {
                 client_id: self.client_id,
@@ -133,16 +639,16 @@ impl Account {
 
         // It is assumed that a client always has sufficient held funds for the amount to be made
         // available.
-        self.available.0 += amount.0;
-        self.held.0 -= amount.0;
-        Ok(())
+        self.adjust(wallet, amount, Amount(-amount.0))
     }
 
     /// A chargeback is the final state of a dispute and represents the client reversing a transaction.
     /// Funds that were held have now been withdrawn. This means that the clients held funds and total
     /// funds should decrease by the amount previously disputed. If a chargeback occurs the client's
-    /// account should be immediately frozen.
-    fn chargeback(&mut self, amount: Amount) -> Result<(), TransactionError> {
+    /// account should be immediately frozen, until the deposit is represented. If the client's
+    /// available funds are negative, the shortfall is covered from their reserve as far as it
+    /// allows.
+    fn chargeback(&mut self, wallet: WalletId, amount: Amount) -> Result<(), TransactionError> {
         if self.locked {
             return Err(TransactionError::ClientAccountLocked {
                 client_id: self.client_id,
@@ -150,597 +656,4803 @@ impl Account {
         }
 
         // It is assumed that a client always has sufficient held funds for the amount to be charged
-        // back.
-        self.held.0 -= amount.0;
-        self.locked = true;
-        Ok(())
-    }
-}
-
-#[derive(Default)]
-pub struct Ledger {
-    clients: HashMap<ClientId, Account>,
-    deposits: HashMap<(ClientId, TransactionId), Amount>,
-    disputes: HashSet<(ClientId, TransactionId)>,
-}
-
-impl Ledger {
-    /// Updates the ledger by applying a `Transaction`.
-    fn update(&mut self, transaction: Transaction) -> Result<(), TransactionError> {
-        match transaction {
-            Transaction::Deposit(deposit) => self.deposit(deposit)?,
-            Transaction::Withdrawal(withdrawal) => self.withdrawal(withdrawal)?,
-            Transaction::Dispute(dispute) => self.dispute(dispute)?,
-            Transaction::Resolve(resolve) => self.resolve(resolve)?,
-            Transaction::Chargeback(chargeback) => self.chargeback(chargeback)?,
+        // back. The reserve is drawn on a copy of the account, so that nothing changes if
+        // returning the funds drawn would overflow.
+        let mut account = self.clone();
+        account.adjust(wallet, Amount::from(0), Amount(-amount.0))?;
+        if account.available.0.is_sign_negative() {
+            for (wallet, covered) in account.reserve.draw(Amount(-account.available.0)) {
+                account.adjust(wallet, covered, Amount::from(0))?;
+            }
         }
+        account.locked = true;
+        account.chargeback_locks += 1;
+        *self = account;
         Ok(())
     }
 
-    /// Load transactions in CSV format from a given reader and update the ledger.
-    pub fn load(&mut self, rdr: impl io::Read) {
-        for entry in read_input(rdr) {
-            match entry {
-                Ok(transaction) => {
-                    if let Err(e) = self.update(transaction) {
-                        eprintln!("{}", e);
-                    }
-                }
-                Err(e) => eprintln!("{}", e),
-            }
+    /// Release the reserve tranches due by the given time, returning each to the available funds
+    /// of the wallet it was withheld from. Nothing is released if returning the funds would
+    /// overflow.
+    fn release_reserve(&mut self, now: Timestamp) -> Result<(), TransactionError> {
+        let mut account = self.clone();
+        for (wallet, released) in account.reserve.release(now) {
+            account.adjust(wallet, released, Amount::from(0))?;
         }
+        *self = account;
+        Ok(())
     }
 
-    /// Print the client accounts to a given writer in CSV format.
-    pub fn print(&self, wrt: impl io::Write) -> Result<(), Error> {
-        parser::write_output(wrt, self.clients.values().map(LedgerEntry::from))?;
+    /// Withhold part of a deposit into the client's reserve until it is released, meaning the
+    /// client's available funds should decrease by the amount withheld while their total funds
+    /// remain the same.
+    fn withhold(
+        &mut self,
+        wallet: WalletId,
+        tx: TransactionId,
+        release_at: Timestamp,
+        amount: Amount,
+    ) -> Result<(), TransactionError> {
+        self.adjust(wallet, Amount(-amount.0), Amount::from(0))?;
+        self.reserve.withhold(tx, wallet, release_at, amount);
         Ok(())
     }
 
-    /// Deposit an amount into a client's account.
-    fn deposit(&mut self, Deposit { client, tx, amount }: Deposit) -> Result<(), TransactionError> {
-        // Increase the client's available funds, creating a new client if it does not already exist.
-        self.clients
-            .entry(client)
-            .or_insert(Account::new(client))
-            .deposit(amount)?;
+    /// A refund returns part of a deposit to the client's source, meaning it should decrease the
+    /// available and total funds of the client account. If a client does not have sufficient
+    /// available funds the refund should fail.
+    fn refund(&mut self, wallet: WalletId, amount: Amount) -> Result<(), TransactionError> {
+        if self.locked {
+            return Err(TransactionError::ClientAccountLocked {
+                client_id: self.client_id,
+            });
+        }
+        let available = self.wallet(wallet).available;
+        if available.0 < amount.0 {
+            return Err(TransactionError::RefundInsufficientFunds {
+                client_id: self.client_id,
+                available,
+                requested: amount,
+            });
+        }
+        self.adjust(wallet, Amount(-amount.0), Amount::from(0))
+    }
 
-        // Track this deposit so that it can later be disputed.
-        self.deposits.insert((client, tx), amount);
+    /// Set the client's credit limit. Lowering the limit below the credit already used is allowed,
+    /// and prevents further withdrawals until the account is back within its limit.
+    fn set_credit_limit(&mut self, amount: Amount) -> Result<(), TransactionError> {
+        if self.locked {
+            return Err(TransactionError::ClientAccountLocked {
+                client_id: self.client_id,
+            });
+        }
+        if amount.0.is_sign_negative() {
+            return Err(TransactionError::NegativeCreditLimit {
+                client_id: self.client_id,
+                requested: amount,
+            });
+        }
+        self.credit_limit = amount;
         Ok(())
     }
 
-    /// Withdraw an amount from a client's account. Fails if the client does not exist.
-    fn withdrawal(
+    /// A representment contests a chargeback, restoring funds which were charged back. This means
+    /// that the client's available and total funds should increase by the amount represented. A
+    /// representment applies to an account locked by the chargeback it contests. Once the
+    /// deposit is fully represented its lock is lifted, and the account is unlocked when no other
+    /// chargeback, arbitration or rule keeps it locked.
+    fn represent(
         &mut self,
-        Withdrawal {
-            client,
-            tx: _,
-            amount,
-        }: Withdrawal,
+        wallet: WalletId,
+        amount: Amount,
+        fully_represented: bool,
     ) -> Result<(), TransactionError> {
-        // Reduce the client's available funds, failing if the client does not exist.
-        self.clients
-            .get_mut(&client)
-            .ok_or(TransactionError::ClientDoesNotExist { client_id: client })?
-            .withdrawal(amount)?;
+        self.adjust(wallet, amount, Amount::from(0))?;
+        if fully_represented {
+            self.chargeback_locks = self.chargeback_locks.saturating_sub(1);
+            if self.chargeback_locks == 0 && !self.permanently_locked {
+                self.locked = false;
+            }
+        }
         Ok(())
     }
 
-    /// Dispute a client's deposit transaction. Fails if the client does not exist.
-    fn dispute(&mut self, Dispute { client, tx }: Dispute) -> Result<(), TransactionError> {
-        // Find the transaction amount
-        let amount = match self.deposits.get(&(client, tx)) {
-            Some(deposit) => deposit,
-            None => {
-                return Err(TransactionError::DisputeFailed {
-                    client_id: client,
-                    transaction_id: tx,
-                })
-            }
+    /// Lock the account for good, as no representment can lift the lock.
+    fn lock_permanently(&mut self) {
+        self.locked = true;
+        self.permanently_locked = true;
+    }
+
+    /// An arbitration upholds a second dispute of a represented deposit, debiting the funds which
+    /// were restored again. This means that the client's available and total funds should
+    /// decrease by the amount arbitrated, and the account should be locked once more.
+    fn arbitrate(&mut self, wallet: WalletId, amount: Amount) -> Result<(), TransactionError> {
+        self.adjust(wallet, Amount(-amount.0), Amount::from(0))?;
+        self.lock_permanently();
+        Ok(())
+    }
+
+    /// An authorization reserves funds for a later capture. This means that the client's available
+    /// funds should decrease by the amount authorized and their held funds increase by the same
+    /// amount, while their total funds remain the same. If a client does not have sufficient
+    /// available funds the authorization should fail.
+    fn authorize(&mut self, amount: Amount) -> Result<(), TransactionError> {
+        if self.locked {
+            return Err(TransactionError::ClientAccountLocked {
+                client_id: self.client_id,
+            });
+        }
+        if self.available.0 < amount.0 {
+            return Err(TransactionError::AuthorizeInsufficientFunds {
+                client_id: self.client_id,
+                available: self.available,
+                requested: amount,
+            });
+        }
+        self.adjust(WalletId::default(), Amount(-amount.0), amount)
+    }
+
+    /// A hold reserves funds for a compliance investigation. This means that the client's available
+    /// funds should decrease by the amount held and their held funds increase by the same amount,
+    /// while their total funds remain the same. If a client does not have sufficient available
+    /// funds the hold should fail.
+    fn hold(&mut self, amount: Amount) -> Result<(), TransactionError> {
+        if self.locked {
+            return Err(TransactionError::ClientAccountLocked {
+                client_id: self.client_id,
+            });
+        }
+        if self.available.0 < amount.0 {
+            return Err(TransactionError::HoldInsufficientFunds {
+                client_id: self.client_id,
+                available: self.available,
+                requested: amount,
+            });
+        }
+        self.adjust(WalletId::default(), Amount(-amount.0), amount)
+    }
+
+    /// A release lifts some or all of a compliance hold, meaning the client's held funds should
+    /// decrease by the amount released and their available funds increase by the same amount.
+    /// Holds are released even if the account has since been locked.
+    fn release(&mut self, amount: Amount) -> Result<(), TransactionError> {
+        self.adjust(WalletId::default(), amount, Amount(-amount.0))
+    }
+
+    /// A capture settles some or all of an authorization. Funds that were held have now been
+    /// withdrawn, meaning the client's held funds and total funds should decrease by the amount
+    /// captured.
+    fn capture(&mut self, amount: Amount) -> Result<(), TransactionError> {
+        if self.locked {
+            return Err(TransactionError::ClientAccountLocked {
+                client_id: self.client_id,
+            });
+        }
+        self.adjust(WalletId::default(), Amount::from(0), Amount(-amount.0))
+    }
+
+    /// A void cancels the remainder of an authorization, releasing the associated held funds. This
+    /// means that the client's held funds should decrease by the amount released, their available
+    /// funds increase by the same amount, and their total funds remain the same.
+    fn void(&mut self, amount: Amount) -> Result<(), TransactionError> {
+        if self.locked {
+            return Err(TransactionError::ClientAccountLocked {
+                client_id: self.client_id,
+            });
+        }
+        self.adjust(WalletId::default(), amount, Amount(-amount.0))
+    }
+
+    /// Interest is a credit to the client's asset account, meaning it should increase the available
+    /// and total funds of the client account. Interest is owed on funds held before the account
+    /// was locked, so it is credited even if the account has since been locked.
+    fn interest(&mut self, amount: Amount) -> Result<(), TransactionError> {
+        self.adjust(WalletId::default(), amount, Amount::from(0))
+    }
+
+    /// Closing an account pays out its remaining available funds, less the closing fee, as a
+    /// final withdrawal and marks the account closed. The ledger ensures nothing remains held,
+    /// pending or reserved.
+    fn close(&mut self, fee: Amount) -> Result<Amount, TransactionError> {
+        if self.locked {
+            return Err(TransactionError::ClientAccountLocked {
+                client_id: self.client_id,
+            });
+        }
+        let payout = self.available.checked_sub(fee).ok_or(self.overflow())?;
+        if payout.0.is_sign_negative() {
+            return Err(TransactionError::CloseFailed {
+                client_id: self.client_id,
+                reason: "its available funds do not cover the closing fee",
+            });
+        }
+        self.available = fee;
+        self.wallets.clear();
+        self.closed = true;
+        Ok(payout)
+    }
+
+    /// A transfer moves funds from one of the client's wallets to another, meaning the available
+    /// funds of the first wallet should decrease by the amount moved and those of the second
+    /// increase by the same amount, while the client's available and total funds remain the same.
+    /// If the first wallet does not have sufficient available funds the transfer should fail.
+    fn transfer(
+        &mut self,
+        wallet: WalletId,
+        to_wallet: WalletId,
+        amount: Amount,
+    ) -> Result<(), TransactionError> {
+        if self.locked {
+            return Err(TransactionError::ClientAccountLocked {
+                client_id: self.client_id,
+            });
+        }
+        let available = self.wallet(wallet).available;
+        if available.0 < amount.0 {
+            return Err(TransactionError::TransferInsufficientFunds {
+                client_id: self.client_id,
+                wallet,
+                available,
+                requested: amount,
+            });
+        }
+        // The funds are credited first, since debiting funds a wallet holds cannot overflow.
+        self.track(to_wallet, amount, Amount::from(0))?;
+        self.track(wallet, Amount(-amount.0), Amount::from(0))
+    }
+
+    /// A fee is debited from the available funds of the wallet a transaction targets once the
+    /// transaction has been applied. Fees are charged even if the account has since been locked,
+    /// for example by a chargeback, and may leave the client's available funds negative.
+    fn charge(&mut self, wallet: WalletId, fee: Amount) -> Result<(), TransactionError> {
+        let fees = self.fees.checked_add(fee).ok_or(self.overflow())?;
+        self.adjust(wallet, Amount(-fee.0), Amount::from(0))?;
+        self.fees = fees;
+        Ok(())
+    }
+}
+
+/// A deposit, withdrawal or authorization applied to the ledger, along with the fee charged for it
+/// and when it was applied, kept so that it can later be reversed.
+#[derive(Debug, Clone, Copy)]
+struct Posting {
+    transaction: Transaction,
+    fee: Amount,
+    timestamp: Timestamp,
+}
+
+/// A dispute, resolve or chargeback parked until the deposit it references arrives, along with
+/// the error it first failed with and the last row it waits for.
+#[derive(Debug, Clone)]
+struct Parked {
+    position: Position,
+    transaction: Transaction,
+    error: TransactionError,
+    expires_after: u64,
+}
+
+/// A parked row leaving the reorder window.
+#[derive(Debug, Clone)]
+enum Unparking {
+    /// Waiting to be retried now that its deposit has arrived.
+    Retry(Parked),
+    /// Retried, along with the fee charged for it and the outcome.
+    Retried {
+        parked: Parked,
+        fee: Amount,
+        outcome: Result<(), TransactionError>,
+    },
+    /// Expired before its deposit arrived.
+    Expired(Parked),
+}
+
+/// A parked row which has left the reorder window, either applied or rejected once its deposit
+/// arrived, or rejected once it expired.
+#[derive(Debug)]
+pub struct Unparked {
+    pub transaction: Transaction,
+    /// The fee charged for the transaction, which is zero unless it was applied.
+    pub fee: Amount,
+    pub result: Result<Applied, Reject>,
+}
+
+/// A compliance hold placed on a client's funds, along with the amount which remains held.
+#[derive(Debug, Clone, Copy)]
+struct OpenHold {
+    reason: HoldReason,
+    amount: Amount,
+}
+
+/// A client's held funds broken down by why they are held.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct HeldFunds {
+    pub disputes: Amount,
+    pub authorizations: Amount,
+    pub aml: Amount,
+    pub fraud: Amount,
+    pub legal: Amount,
+    pub other: Amount,
+}
+
+impl HeldFunds {
+    fn hold(&mut self, reason: HoldReason, amount: Amount) {
+        let held = match reason {
+            HoldReason::Aml => &mut self.aml,
+            HoldReason::Fraud => &mut self.fraud,
+            HoldReason::Legal => &mut self.legal,
+            HoldReason::Other => &mut self.other,
         };
+        *held = Amount(held.0.saturating_add(amount.0));
+    }
+}
 
-        // Update the client's account, moving funds from available to held.
-        self.clients
-            .get_mut(&client)
-            .ok_or(TransactionError::ClientDoesNotExist { client_id: client })?
-            .dispute(*amount)?;
+/// Configuration applied by the ledger to every transaction.
+#[derive(Debug, Default, Clone)]
+pub struct LedgerConfig {
+    pub fees: FeeSchedule,
+    pub limits: LimitSchedule,
+    pub rules: RuleSet,
+    /// The initial credit limit of each client with an overdraft.
+    pub credit_limits: HashMap<ClientId, Amount>,
+    /// The rolling reserve applied to each high risk client.
+    pub reserves: HashMap<ClientId, ReservePolicy>,
+    /// Whether deposits are held as pending until they settle, rather than being available
+    /// immediately.
+    pub pending_deposits: bool,
+    /// The number of days after which pending deposits settle without an explicit `settle`.
+    pub settlement_delay: Option<u64>,
+    /// The master data of each known client, consulted by rules.
+    pub clients: ClientMaster,
+    /// The master data fields included in the accounts output.
+    pub client_columns: Vec<ClientField>,
+    /// The number of rows a dispute, resolve or chargeback arriving before the deposit it
+    /// references waits for the deposit before it is rejected.
+    pub reorder_window: Option<u64>,
+    /// The precision amounts are rounded to, both as they are applied and when they are output.
+    pub precision: Precision,
+}
 
-        // Track the dispute
-        self.disputes.insert((client, tx));
+impl LedgerConfig {
+    /// Load the client master data, assigning each client to their tier's withdrawal limits and
+    /// giving them their credit limit. Tiers and credit limits loaded afterwards take precedence.
+    pub fn load_clients(&mut self, clients: ClientMaster) {
+        for (client_id, record) in clients.iter() {
+            if let Some(tier) = &record.tier {
+                self.limits.assign(*client_id, tier.clone());
+            }
+            if let Some(credit_limit) = record.credit_limit {
+                self.credit_limits.insert(*client_id, credit_limit);
+            }
+        }
+        self.clients = clients;
+    }
+}
+
+#[derive(Default, Clone)]
+pub struct Ledger {
+    config: LedgerConfig,
+    clients: HashMap<ClientId, Account>,
+    deposits: HashMap<(ClientId, TransactionId), Amount>,
+    disputes: HashMap<(ClientId, TransactionId), Amount>,
+    chargebacks: HashMap<(ClientId, TransactionId), Amount>,
+    representments: HashMap<(ClientId, TransactionId), Amount>,
+    authorizations: HashMap<(ClientId, TransactionId), Amount>,
+    postings: HashMap<(ClientId, TransactionId), Posting>,
+    revenue: Amount,
+    residue: Amount,
+    now: Timestamp,
+    alerts: Vec<Verdict>,
+    interest_accrued_until: Option<Timestamp>,
+    reserve_releases: BTreeMap<Timestamp, Vec<ClientId>>,
+    pending: HashMap<(ClientId, TransactionId), Amount>,
+    settlements: BTreeMap<Timestamp, Vec<(ClientId, TransactionId)>>,
+    holds: HashMap<(ClientId, TransactionId), OpenHold>,
+    holds_placed: bool,
+    rows: u64,
+    /// Parked transactions by the row they arrived at, and the rows parked waiting for each
+    /// deposit.
+    parked: BTreeMap<u64, Parked>,
+    waiting: HashMap<(ClientId, TransactionId), Vec<u64>>,
+    unparked: VecDeque<Unparking>,
+}
+
+impl Ledger {
+    pub fn new(config: LedgerConfig) -> Self {
+        Ledger {
+            config,
+            ..Ledger::default()
+        }
+    }
+
+    pub fn config(&self) -> &LedgerConfig {
+        &self.config
+    }
+
+    /// Updates the ledger by applying a `Transaction`, then charging any fee due for it. The
+    /// configured rules are evaluated first, and may reject the transaction, lock the client's
+    /// account or raise an alert, which is held until taken with `take_alerts`.
+    pub fn update(&mut self, transaction: Transaction) -> Result<(), TransactionError> {
+        let (transaction, rounded) = self.round(transaction);
+        let client = transaction.client();
+        if self
+            .clients
+            .get(&client)
+            .is_some_and(|account| account.closed)
+        {
+            return Err(TransactionError::ClientAccountClosed { client_id: client });
+        }
+        let flagged = self.evaluate_rules(&transaction)?;
+
+        let unrounded_fee = self
+            .unrounded_fee(&transaction)
+            .ok_or(TransactionError::AmountOverflow { client_id: client })?;
+        let fee = self.config.precision.round(unrounded_fee);
+        self.check_fee(&transaction, fee)?;
+        self.check_fee_covered(&transaction, fee)?;
+        match transaction {
+            Transaction::Deposit(deposit) => self.deposit(deposit)?,
+            Transaction::Withdrawal(withdrawal) => self.withdrawal(withdrawal, fee)?,
+            Transaction::Dispute(dispute) => self.dispute(dispute)?,
+            Transaction::Resolve(resolve) => self.resolve(resolve)?,
+            Transaction::Chargeback(chargeback) => self.chargeback(chargeback)?,
+            Transaction::Refund(refund) => self.refund(refund)?,
+            Transaction::Represent(represent) => self.represent(represent)?,
+            Transaction::Arbitrate(arbitrate) => self.arbitrate(arbitrate)?,
+            Transaction::Authorize(authorize) => self.authorize(authorize)?,
+            Transaction::Capture(capture) => self.capture(capture)?,
+            Transaction::Void(void) => self.void(void)?,
+            Transaction::Reversal(reversal) => self.reverse(reversal)?,
+            Transaction::CreditLimit(credit_limit) => self.set_credit_limit(credit_limit)?,
+            Transaction::Interest(interest) => self.interest(interest)?,
+            Transaction::Settle(settle) => self.settle(settle)?,
+            Transaction::Hold(hold) => self.hold(hold)?,
+            Transaction::Release(release) => self.release(release)?,
+            Transaction::Close(close) => self.close(close, fee)?,
+            Transaction::Transfer(transfer) => self.transfer(transfer, fee)?,
+        }
+        self.charge(client, transaction.wallet(), fee)?;
+        self.residue.0 = self
+            .residue
+            .0
+            .saturating_add(rounded.0)
+            .saturating_add(unrounded_fee.0 - fee.0);
+        self.config.rules.observe(&transaction);
+        self.record_balance(client);
+
+        // Keep the transactions which own their transaction ID so that they can be reversed.
+        if let Transaction::Deposit(_) | Transaction::Withdrawal(_) | Transaction::Authorize(_) =
+            transaction
+        {
+            let posting = Posting {
+                transaction,
+                fee,
+                timestamp: self.now,
+            };
+            self.postings.insert((client, transaction.tx()), posting);
+        }
+        self.alerts.extend(flagged);
         Ok(())
     }
 
-    /// Resolve a client's disputed deposit. Fails if the client does not exist.
-    fn resolve(&mut self, Resolve { client, tx }: Resolve) -> Result<(), TransactionError> {
-        // Confirm a dispute exists
-        self.disputes
-            .get(&(client, tx))
-            .ok_or(TransactionError::ResolveFailed {
+    /// Evaluate the configured rules against a transaction. The strictest verdict applies: a
+    /// rejection or lock fails the transaction, otherwise the verdicts of any rules which flagged
+    /// the transaction are returned. A locked account remains locked even though the transaction
+    /// fails.
+    fn evaluate_rules(
+        &mut self,
+        transaction: &Transaction,
+    ) -> Result<Vec<Verdict>, TransactionError> {
+        let client = self.config.clients.get(transaction.client());
+        let verdicts = self.config.rules.evaluate(transaction, client);
+        let Some(verdict) = verdicts.iter().max_by_key(|verdict| verdict.action) else {
+            return Ok(verdicts);
+        };
+        let client_id = transaction.client();
+        match verdict.action {
+            Action::Flag => Ok(verdicts),
+            Action::Reject => Err(TransactionError::RuleRejected {
+                client_id,
+                verdict: verdict.clone(),
+            }),
+            Action::Lock => {
+                if let Some(account) = self.clients.get_mut(&client_id) {
+                    account.lock_permanently();
+                }
+                Err(TransactionError::RuleLocked {
+                    client_id,
+                    verdict: verdict.clone(),
+                })
+            }
+        }
+    }
+
+    /// Take the alerts raised by rules which flagged transactions since alerts were last taken.
+    pub fn take_alerts(&mut self) -> Vec<Verdict> {
+        mem::take(&mut self.alerts)
+    }
+
+    /// The fee due for a transaction under the configured fee schedule. Percentage fees for
+    /// transactions which settle a disputed deposit or an authorization in full are charged on the
+    /// amount they settle: the disputable balance of the deposit, the amount under dispute, charged
+    /// back or represented, or the amount remaining authorized. Full refunds are charged on the
+    /// deposit's refundable balance, and reversals on the amount of the transaction reversed.
+    /// Returns `None` if the fee is too large to represent, in which case the transaction is
+    /// rejected.
+    pub fn fee(&self, transaction: &Transaction) -> Option<Amount> {
+        let (transaction, _) = self.round(*transaction);
+        let fee = self.unrounded_fee(&transaction)?;
+        Some(self.config.precision.round(fee))
+    }
+
+    /// The fee due for a transaction before it is rounded to the ledger's precision, or `None` if
+    /// it is too large to represent.
+    fn unrounded_fee(&self, transaction: &Transaction) -> Option<Amount> {
+        let amount = self.resolved_amount(transaction);
+        self.config.fees.fee(transaction.transaction_type(), amount)
+    }
+
+    /// The amount a transaction applies to: its own amount when given, otherwise the whole
+    /// remaining amount of the transaction it references.
+    fn resolved_amount(&self, transaction: &Transaction) -> Option<Amount> {
+        let key = (transaction.client(), transaction.tx());
+        transaction.amount().or_else(|| match transaction {
+            Transaction::Dispute(_) | Transaction::Refund(_) => self.deposits.get(&key).copied(),
+            Transaction::Settle(_) => self.pending.get(&key).copied(),
+            Transaction::Release(_) => self.holds.get(&key).map(|hold| hold.amount),
+            Transaction::Resolve(_) | Transaction::Chargeback(_) => {
+                self.disputes.get(&key).copied()
+            }
+            Transaction::Represent(_) => self.chargebacks.get(&key).copied(),
+            Transaction::Arbitrate(_) => self.representments.get(&key).copied(),
+            Transaction::Capture(_) | Transaction::Void(_) => {
+                self.authorizations.get(&key).copied()
+            }
+            Transaction::Reversal(_) => self
+                .postings
+                .get(&key)
+                .and_then(|posting| posting.transaction.amount()),
+            Transaction::Deposit(_)
+            | Transaction::Withdrawal(_)
+            | Transaction::Authorize(_)
+            | Transaction::CreditLimit(_)
+            | Transaction::Interest(_)
+            | Transaction::Hold(_)
+            | Transaction::Close(_)
+            | Transaction::Transfer(_) => None,
+        })
+    }
+
+    /// Round a transaction's amount to the ledger's precision, returning the rounded transaction
+    /// along with the amount lost to rounding.
+    fn round(&self, mut transaction: Transaction) -> (Transaction, Amount) {
+        let mut lost = Amount::from(0);
+        if let Some(amount) = transaction.amount_mut() {
+            let rounded = self.config.precision.round(*amount);
+            lost.0 = amount.0 - rounded.0;
+            *amount = rounded;
+        }
+        (transaction, lost)
+    }
+
+    /// The balance of the rounding residue account: the total lost when transaction amounts and
+    /// fees were rounded to the ledger's precision, which is negative when more was gained by
+    /// rounding up than was lost by rounding down.
+    pub fn residue(&self) -> Amount {
+        self.residue
+    }
+
+    /// Ensure that charging a fee cannot overflow the client's funds or fees, or the house revenue
+    /// account. The fee is charged once the transaction has been applied, so this is checked
+    /// beforehand, allowing for the transaction to debit up to its whole amount.
+    fn check_fee(&self, transaction: &Transaction, fee: Amount) -> Result<(), TransactionError> {
+        let client = transaction.client();
+        let amount = Amount(
+            self.resolved_amount(transaction)
+                .unwrap_or_default()
+                .0
+                .abs(),
+        );
+        let (fees, available) = match self.clients.get(&client) {
+            Some(account) => (
+                account.fees,
+                [
+                    account.available,
+                    account.wallet(transaction.wallet()).available,
+                ],
+            ),
+            None => (Amount::from(0), [Amount::from(0); 2]),
+        };
+        let fits = |available: Amount| {
+            available
+                .checked_sub(amount)
+                .and_then(|available| available.checked_sub(fee))
+                .is_some()
+        };
+        match (fees.checked_add(fee), self.revenue.checked_add(fee)) {
+            (Some(_), Some(_)) if available.into_iter().all(fits) => Ok(()),
+            _ => Err(TransactionError::AmountOverflow { client_id: client }),
+        }
+    }
+
+    /// Ensure that a client's available funds, with any credit they have, will cover the fee due
+    /// for a transaction once the transaction has been applied. Withdrawals, transfers and closes
+    /// check their fee along with their own amount, while chargebacks and reversals are imposed
+    /// whatever the client's funds, so their fees may still leave available funds negative.
+    fn check_fee_covered(
+        &self,
+        transaction: &Transaction,
+        fee: Amount,
+    ) -> Result<(), TransactionError> {
+        if fee.0.is_zero() {
+            return Ok(());
+        }
+        let client = transaction.client();
+        let wallet = transaction.wallet();
+        let deposit_wallet = self.deposit_wallet(client, transaction.tx());
+        let amount = self.resolved_amount(transaction).unwrap_or_default().0;
+        let (affected, change) = match transaction {
+            Transaction::Withdrawal(_)
+            | Transaction::Transfer(_)
+            | Transaction::Close(_)
+            | Transaction::Chargeback(_)
+            | Transaction::Reversal(_) => return Ok(()),
+            Transaction::Deposit(_) if self.config.pending_deposits => (wallet, Decimal::ZERO),
+            Transaction::Deposit(_) => {
+                // A deposit too large to withhold from fails once applied, so needs no fee check.
+                let Ok(withheld) = self.reserve_withheld(client, Amount(amount)) else {
+                    return Ok(());
+                };
+                (wallet, amount - withheld.0)
+            }
+            Transaction::Resolve(_) | Transaction::Represent(_) | Transaction::Settle(_) => {
+                (deposit_wallet, amount)
+            }
+            Transaction::Dispute(_) | Transaction::Refund(_) | Transaction::Arbitrate(_) => {
+                (deposit_wallet, -amount)
+            }
+            Transaction::Void(_) | Transaction::Release(_) | Transaction::Interest(_) => {
+                (wallet, amount)
+            }
+            Transaction::Authorize(_) | Transaction::Hold(_) => (wallet, -amount),
+            Transaction::Capture(_) | Transaction::CreditLimit(_) => (wallet, Decimal::ZERO),
+        };
+        let (available, credit) = match self.clients.get(&client) {
+            Some(account) => (account.wallet(wallet).available, account.credit(wallet)),
+            None => (
+                Amount::from(0),
+                self.config
+                    .credit_limits
+                    .get(&client)
+                    .copied()
+                    .unwrap_or_default(),
+            ),
+        };
+        let credit = match transaction {
+            Transaction::CreditLimit(credit_limit) => credit_limit.amount,
+            _ => credit,
+        };
+        let available = match affected == wallet {
+            true => Amount(available.0.saturating_add(change)),
+            false => available,
+        };
+        if available.0.saturating_add(credit.0) < fee.0 {
+            return Err(TransactionError::FeeInsufficientFunds {
                 client_id: client,
-                transaction_id: tx,
-            })?;
+                available,
+                fee,
+            });
+        }
+        Ok(())
+    }
 
-        // Find the transaction amount
-        let amount = match self.deposits.get(&(client, tx)) {
-            Some(deposit) => deposit,
-            None => {
-                return Err(TransactionError::ResolveFailed {
-                    client_id: client,
-                    transaction_id: tx,
-                })
+    /// Debit a fee from one of a client's wallets and credit it to the house revenue account.
+    fn charge(
+        &mut self,
+        client: ClientId,
+        wallet: WalletId,
+        fee: Amount,
+    ) -> Result<(), TransactionError> {
+        if fee.0.is_zero() {
+            return Ok(());
+        }
+        if let Some(account) = self.clients.get_mut(&client) {
+            let revenue = self
+                .revenue
+                .checked_add(fee)
+                .ok_or(TransactionError::AmountOverflow { client_id: client })?;
+            account.charge(wallet, fee)?;
+            self.revenue = revenue;
+        }
+        Ok(())
+    }
+
+    /// The house revenue account as an account holding the revenue as its available funds, once
+    /// there is a fee schedule or any revenue. Its client ID is never output, as its entries are
+    /// for `AccountId::House`.
+    fn house_account(&self) -> Option<Account> {
+        (!self.config.fees.is_empty() || !self.revenue.0.is_zero()).then(|| Account {
+            available: self.revenue,
+            ..Account::new(ClientId(0))
+        })
+    }
+
+    /// The balance of the house revenue account: the total fees credited to it, less any interest
+    /// paid from it.
+    pub fn revenue(&self) -> Amount {
+        self.revenue
+    }
+
+    /// Applies a single parsed input row to the ledger, first advancing the ledger's clock to the
+    /// row's timestamp. When a reorder window is configured, a dispute, resolve or chargeback
+    /// referencing a deposit which has not yet arrived is parked rather than rejected, and retried
+    /// once the deposit is applied. The outcomes of parked rows are taken with `take_unparked`.
+    pub fn apply(
+        &mut self,
+        Row {
+            position,
+            timestamp,
+            entry,
+        }: Row,
+    ) -> Result<Applied, Reject> {
+        // Retry any rows whose deposit has arrived which were not taken, before applying the next.
+        for index in 0..self.unparked.len() {
+            if let Unparking::Retry(parked) = &self.unparked[index] {
+                let parked = parked.clone();
+                let (fee, outcome) = self.retry(&parked);
+                self.unparked[index] = Unparking::Retried {
+                    parked,
+                    fee,
+                    outcome,
+                };
+            }
+        }
+        if let Some(timestamp) = timestamp {
+            self.advance_clock(timestamp);
+        }
+        self.rows += 1;
+        while let Some(entry) = self.parked.first_entry() {
+            if entry.get().expires_after >= self.rows {
+                break;
+            }
+            let (row, parked) = entry.remove_entry();
+            self.expire(row, parked);
+        }
+
+        let transaction = entry.map_err(|error| Reject {
+            position,
+            error: error.into(),
+        })?;
+        match self.update(transaction) {
+            Ok(()) => {
+                self.retry_parked(transaction);
+                Ok(Applied::Applied)
+            }
+            Err(error) => match self.config.reorder_window {
+                Some(window) if self.awaits_deposit(transaction, &error) => {
+                    self.parked.insert(
+                        self.rows,
+                        Parked {
+                            position,
+                            transaction,
+                            error,
+                            expires_after: self.rows.saturating_add(window),
+                        },
+                    );
+                    self.waiting
+                        .entry((transaction.client(), transaction.tx()))
+                        .or_default()
+                        .push(self.rows);
+                    Ok(Applied::Parked)
+                }
+                _ => Err(Reject {
+                    position,
+                    error: error.into(),
+                }),
+            },
+        }
+    }
+
+    /// Whether a transaction failed only because the deposit it references has not yet arrived.
+    fn awaits_deposit(&self, transaction: Transaction, error: &TransactionError) -> bool {
+        matches!(
+            error,
+            TransactionError::DisputeFailed { .. }
+                | TransactionError::ResolveFailed { .. }
+                | TransactionError::ChargebackFailed { .. }
+        ) && !self
+            .deposits
+            .contains_key(&(transaction.client(), transaction.tx()))
+    }
+
+    /// Queue the parked transactions waiting for a deposit which has just been applied to be
+    /// retried, in the order they arrived.
+    fn retry_parked(&mut self, transaction: Transaction) {
+        let Transaction::Deposit(Deposit { client, tx, .. }) = transaction else {
+            return;
+        };
+        for row in self.waiting.remove(&(client, tx)).unwrap_or_default() {
+            if let Some(parked) = self.parked.remove(&row) {
+                self.unparked.push_back(Unparking::Retry(parked));
+            }
+        }
+    }
+
+    /// Retry a parked transaction, returning the fee charged for it along with the outcome.
+    fn retry(&mut self, parked: &Parked) -> (Amount, Result<(), TransactionError>) {
+        let fee = self.fee(&parked.transaction).unwrap_or_default();
+        match self.update(parked.transaction) {
+            Ok(()) => (fee, Ok(())),
+            Err(error) => (Amount::from(0), Err(error)),
+        }
+    }
+
+    /// Reject a transaction parked at the given row with the error it first failed with.
+    fn expire(&mut self, row: u64, parked: Parked) {
+        let key = (parked.transaction.client(), parked.transaction.tx());
+        if let Some(rows) = self.waiting.get_mut(&key) {
+            rows.retain(|waiting| *waiting != row);
+            if rows.is_empty() {
+                self.waiting.remove(&key);
+            }
+        }
+        self.unparked.push_back(Unparking::Expired(parked));
+    }
+
+    /// Reject every parked transaction, as none of the deposits they wait for can still arrive.
+    pub fn expire_parked(&mut self) {
+        while let Some((row, parked)) = self.parked.pop_first() {
+            self.expire(row, parked);
+        }
+    }
+
+    /// Take the next parked row to leave the reorder window since it was last called: one
+    /// applied once its deposit arrived, one which failed when retried, or one rejected once it
+    /// expired. Rows are retried as they are taken, so the ledger reflects each row up to and
+    /// including the one taken. Rows which are not taken are retried before the next row is
+    /// applied.
+    pub fn next_unparked(&mut self) -> Option<Unparked> {
+        let (parked, fee, outcome) = match self.unparked.pop_front()? {
+            Unparking::Retry(parked) => {
+                let (fee, outcome) = self.retry(&parked);
+                (parked, fee, outcome)
+            }
+            Unparking::Retried {
+                parked,
+                fee,
+                outcome,
+            } => (parked, fee, outcome),
+            Unparking::Expired(parked) => {
+                let error = parked.error.clone();
+                (parked, Amount::from(0), Err(error))
+            }
+        };
+        Some(Unparked {
+            transaction: parked.transaction,
+            fee,
+            result: outcome.map(|()| Applied::Applied).map_err(|error| Reject {
+                position: parked.position,
+                error: error.into(),
+            }),
+        })
+    }
+
+    /// Take the outcomes of every parked row to leave the reorder window since they were last
+    /// taken.
+    pub fn take_unparked(&mut self) -> Vec<Result<Applied, Reject>> {
+        iter::from_fn(|| self.next_unparked())
+            .map(|unparked| unparked.result)
+            .collect()
+    }
+
+    /// The ledger's clock: the latest timestamp seen, or the Unix epoch when no row has had one.
+    pub fn now(&self) -> Timestamp {
+        self.now
+    }
+
+    /// Advance the ledger's clock, which is used to apply time based rules such as withdrawal
+    /// limits. The clock never moves backwards, so rows which are out of order are treated as
+    /// occurring at the latest time seen. Pending deposits and reserves due by the new time are
+    /// settled and released to the clients' available funds.
+    pub fn advance_clock(&mut self, timestamp: Timestamp) {
+        self.now = self.now.max(timestamp);
+        let next = Timestamp(self.now.0.saturating_add(1));
+
+        let waiting = self.settlements.split_off(&next);
+        let due = mem::replace(&mut self.settlements, waiting);
+        for (client, tx) in due.into_values().flatten() {
+            // Deposits which were settled explicitly or reversed in the meantime are skipped.
+            if self.settle(Settle { client, tx }).is_ok() {
+                self.record_balance(client);
+            }
+        }
+
+        let waiting = self.reserve_releases.split_off(&next);
+        let due = mem::replace(&mut self.reserve_releases, waiting);
+        for client in due.into_values().flatten() {
+            // A release which would overflow the client's funds is left withheld until their next
+            // release.
+            if let Some(account) = self.clients.get_mut(&client) {
+                let _ = account.release_reserve(self.now);
+            }
+            self.record_balance(client);
+        }
+    }
+
+    /// Load transactions in CSV format from a given reader and update the ledger.
+    pub fn load(&mut self, rdr: impl io::Read) -> LoadSummary {
+        self.load_from(read_input(rdr))
+    }
+
+    /// Update the ledger from a sequence of parsed input rows, reporting rejected rows and alerts
+    /// to stderr.
+    pub fn load_from(&mut self, rows: impl IntoIterator<Item = Row>) -> LoadSummary {
+        self.load_with(rows, |report| eprintln!("{}", report))
+    }
+
+    /// Update the ledger from a sequence of parsed input rows, passing each rejected row and each
+    /// alert raised to `on_report`. Rows still parked at the end of the sequence are rejected.
+    pub fn load_with(
+        &mut self,
+        rows: impl IntoIterator<Item = Row>,
+        mut on_report: impl FnMut(Report),
+    ) -> LoadSummary {
+        let mut summary = LoadSummary::default();
+        for row in rows {
+            let position = row.position;
+            let result = self.apply(row);
+            for result in iter::once(result).chain(self.take_unparked()) {
+                summary.record(&result);
+                if let Err(reject) = result {
+                    on_report(Report::Reject(reject));
+                }
+            }
+            for verdict in self.take_alerts() {
+                on_report(Report::Alert(Alert { position, verdict }));
+            }
+        }
+        self.expire_parked();
+        for result in self.take_unparked() {
+            summary.record(&result);
+            if let Err(reject) = result {
+                on_report(Report::Reject(reject));
+            }
+        }
+        summary
+    }
+
+    /// Apply a sequence of parsed input rows to a scratch copy of the ledger, reporting the rows
+    /// which would be rejected or raise alerts without modifying this ledger.
+    pub fn dry_run(
+        &self,
+        rows: impl IntoIterator<Item = Row>,
+        on_report: impl FnMut(Report),
+    ) -> LoadSummary {
+        self.clone().load_with(rows, on_report)
+    }
+
+    /// Print the client accounts to a given writer in CSV format.
+    pub fn print(&self, wrt: impl io::Write) -> Result<(), Error> {
+        parser::write_output(wrt, self.entries())?;
+        Ok(())
+    }
+
+    /// The client accounts as output entries, including the optional columns relevant to the
+    /// ledger's configuration. Once fees are charged or interest paid, the house revenue account
+    /// follows the clients as `AccountId::House`.
+    pub fn entries(&self) -> impl Iterator<Item = LedgerEntry> + '_ {
+        let credit = !self.config.credit_limits.is_empty()
+            || self
+                .clients
+                .values()
+                .any(|account| !account.credit_limit.0.is_zero());
+        let held = self.holds_placed.then(|| self.held_funds());
+        let closed = self.clients.values().any(|account| account.closed);
+        let house = self
+            .house_account()
+            .map(|account| (AccountId::House, Cow::Owned(account)));
+        let accounts = self
+            .clients
+            .values()
+            .map(|account| (AccountId::Client(account.client_id), Cow::Borrowed(account)))
+            .chain(house);
+        accounts.map(move |(id, account)| {
+            let client = id.client();
+            let breakdown = held.as_ref().map(|held| {
+                client
+                    .and_then(|client| held.get(&client))
+                    .copied()
+                    .unwrap_or_default()
+            });
+            LedgerEntry {
+                client: id,
+                fees: (!self.config.fees.is_empty()).then_some(account.fees),
+                credit_limit: credit.then_some(account.credit_limit),
+                used_credit: credit.then(|| account.used_credit()),
+                reserve: (!self.config.reserves.is_empty()).then(|| account.reserve.balance()),
+                pending: self.config.pending_deposits.then_some(account.pending),
+                closed: closed.then_some(account.closed),
+                tier: self.client_column(ClientField::Tier, client),
+                status: self.client_column(ClientField::Status, client),
+                country: self.client_column(ClientField::Country, client),
+                risk_rating: self.client_column(ClientField::RiskRating, client),
+                held_disputes: breakdown.map(|held| held.disputes),
+                held_authorizations: breakdown.map(|held| held.authorizations),
+                held_aml: breakdown.map(|held| held.aml),
+                held_fraud: breakdown.map(|held| held.fraud),
+                held_legal: breakdown.map(|held| held.legal),
+                held_other: breakdown.map(|held| held.other),
+                ..account.entry(&self.config.precision)
             }
+        })
+    }
+
+    /// The balances of each of the client accounts' wallets as output entries, with a row for each
+    /// wallet a client has used. A client's pending funds count towards the total of their default
+    /// wallet, and reserved funds towards the wallet they were deposited to. The house revenue
+    /// account follows the clients, as in `entries`.
+    pub fn wallet_entries(&self) -> impl Iterator<Item = LedgerEntry> + '_ {
+        let house = self.house_account().map(|account| LedgerEntry {
+            client: AccountId::House,
+            wallet: Some(WalletId::default()),
+            ..account.entry(&self.config.precision)
+        });
+        let clients = self.clients.values().flat_map(|account| {
+            let precision = &self.config.precision;
+            let entry = account.entry(precision);
+            account.wallets().map(move |(wallet, funds)| {
+                let mut total = funds
+                    .available
+                    .0
+                    .saturating_add(funds.held.0)
+                    .saturating_add(account.reserve.wallet_balance(wallet).0);
+                if wallet == WalletId::default() {
+                    total = total.saturating_add(account.pending.0);
+                }
+                LedgerEntry {
+                    wallet: Some(wallet),
+                    available: precision.round(funds.available),
+                    held: precision.round(funds.held),
+                    total: precision.round(Amount(total)),
+                    ..entry.clone()
+                }
+            })
+        });
+        clients.chain(house)
+    }
+
+    /// A client's master data field, when it is selected for output.
+    fn client_column(&self, field: ClientField, client_id: Option<ClientId>) -> Option<String> {
+        self.config.client_columns.contains(&field).then(|| {
+            let record = client_id.and_then(|client_id| self.config.clients.get(client_id));
+            field.value(record)
+        })
+    }
+
+    /// Each client's held funds broken down by why they are held, for clients with funds held.
+    pub fn held_funds(&self) -> HashMap<ClientId, HeldFunds> {
+        let mut held = HashMap::<ClientId, HeldFunds>::new();
+        for ((client, _), amount) in &self.disputes {
+            let disputes = &mut held.entry(*client).or_default().disputes;
+            *disputes = Amount(disputes.0.saturating_add(amount.0));
+        }
+        for ((client, _), amount) in &self.authorizations {
+            let authorizations = &mut held.entry(*client).or_default().authorizations;
+            *authorizations = Amount(authorizations.0.saturating_add(amount.0));
+        }
+        for ((client, _), hold) in &self.holds {
+            held.entry(*client)
+                .or_default()
+                .hold(hold.reason, hold.amount);
+        }
+        held
+    }
+
+    /// The client accounts held by the ledger, in no particular order.
+    pub fn accounts(&self) -> impl Iterator<Item = &Account> {
+        self.clients.values()
+    }
+
+    /// Look up a single client's account.
+    pub fn account(&self, client_id: ClientId) -> Option<&Account> {
+        self.clients.get(&client_id)
+    }
+
+    /// Deposit an amount into a client's account.
+    fn deposit(
+        &mut self,
+        Deposit {
+            client,
+            tx,
+            amount,
+            wallet,
+        }: Deposit,
+    ) -> Result<(), TransactionError> {
+        // Increase the client's available funds, or their pending funds if deposits settle later,
+        // creating a new client if it does not already exist. Deposits which settle later are
+        // withheld from once they settle.
+        let withheld = match self.config.pending_deposits {
+            true => Amount::from(0),
+            false => self.reserve_withheld(client, amount)?,
+        };
+        let credit_limit = self.config.credit_limits.get(&client).copied();
+        let account = self.clients.entry(client).or_insert_with(|| Account {
+            credit_limit: credit_limit.unwrap_or_default(),
+            ..Account::new(client)
+        });
+        if self.config.pending_deposits {
+            account.deposit_pending(amount)?;
+            self.pending.insert((client, tx), amount);
+            if let Some(days) = self.config.settlement_delay {
+                let settle_at = self
+                    .now
+                    .0
+                    .saturating_add(days.saturating_mul(Timestamp::SECONDS_PER_DAY));
+                self.settlements
+                    .entry(Timestamp(settle_at))
+                    .or_default()
+                    .push((client, tx));
+            }
+        } else {
+            account.deposit(wallet, amount)?;
+            self.withhold_reserve(client, wallet, tx, withheld)?;
+        }
+
+        // Track this deposit's disputable balance so that it can later be disputed.
+        self.deposits.insert((client, tx), amount);
+        Ok(())
+    }
+
+    /// Settle a pending deposit, making its funds available to the client.
+    fn settle(&mut self, Settle { client, tx }: Settle) -> Result<(), TransactionError> {
+        let amount =
+            self.pending
+                .get(&(client, tx))
+                .copied()
+                .ok_or(TransactionError::SettleFailed {
+                    client_id: client,
+                    transaction_id: tx,
+                })?;
+        let withheld = self.reserve_withheld(client, amount)?;
+        let wallet = self.deposit_wallet(client, tx);
+        self.clients
+            .get_mut(&client)
+            .ok_or(TransactionError::ClientDoesNotExist { client_id: client })?
+            .settle(wallet, amount)?;
+        self.pending.remove(&(client, tx));
+        self.withhold_reserve(client, wallet, tx, withheld)
+    }
+
+    /// The wallet a deposit was made to.
+    fn deposit_wallet(&self, client: ClientId, tx: TransactionId) -> WalletId {
+        match self.postings.get(&(client, tx)) {
+            Some(Posting {
+                transaction: Transaction::Deposit(Deposit { wallet, .. }),
+                ..
+            }) => *wallet,
+            _ => WalletId::default(),
+        }
+    }
+
+    /// The part of a deposit withheld from a high risk client until their reserve is released.
+    fn reserve_withheld(
+        &self,
+        client: ClientId,
+        amount: Amount,
+    ) -> Result<Amount, TransactionError> {
+        match self.config.reserves.get(&client) {
+            Some(policy) => policy
+                .withhold(amount, &self.config.precision)
+                .ok_or(TransactionError::AmountOverflow { client_id: client }),
+            None => Ok(Amount::from(0)),
+        }
+    }
+
+    /// Withhold part of a deposit from a high risk client until its reserve is released.
+    fn withhold_reserve(
+        &mut self,
+        client: ClientId,
+        wallet: WalletId,
+        tx: TransactionId,
+        withheld: Amount,
+    ) -> Result<(), TransactionError> {
+        let Some(policy) = self.config.reserves.get(&client) else {
+            return Ok(());
+        };
+        if withheld.0.is_zero() {
+            return Ok(());
+        }
+        let release_at = policy.release_at(self.now);
+        if let Some(account) = self.clients.get_mut(&client) {
+            account.withhold(wallet, tx, release_at, withheld)?;
+        }
+        self.reserve_releases
+            .entry(release_at)
+            .or_default()
+            .push(client);
+        Ok(())
+    }
+
+    /// Credit interest to a client's account, paid from the house revenue account.
+    fn interest(
+        &mut self,
+        Interest {
+            client,
+            tx: _,
+            amount,
+        }: Interest,
+    ) -> Result<(), TransactionError> {
+        let amount = positive(client, amount)?;
+        let account = self
+            .clients
+            .get_mut(&client)
+            .ok_or(TransactionError::ClientDoesNotExist { client_id: client })?;
+        let revenue = self
+            .revenue
+            .checked_sub(amount)
+            .ok_or(TransactionError::AmountOverflow { client_id: client })?;
+        account.check_credit(&[account.available], amount)?;
+        account.interest(amount)?;
+        self.revenue = revenue;
+        Ok(())
+    }
+
+    /// Record a client's available funds as their balance at the ledger's current time, so that
+    /// interest can be accrued on the balance standing at the end of each day.
+    fn record_balance(&mut self, client: ClientId) {
+        if let Some(account) = self.clients.get_mut(&client) {
+            account.accrual.record(self.now, account.available);
+        }
+    }
+
+    /// Accrue interest on every client's available funds over a period, by the daily balance
+    /// method, and credit it to their accounts as `Interest` transactions with the given `tx`.
+    /// Interest is credited at the ledger's current time, and any fraction of the smallest amount
+    /// credited is carried forward to the client's next accrual. Returns the transactions credited,
+    /// which excludes clients who earned nothing. Periods must be accrued in order and may not
+    /// overlap.
+    pub fn accrue_interest(
+        &mut self,
+        rates: &InterestRates,
+        period: Range<Timestamp>,
+        tx: TransactionId,
+    ) -> Result<Vec<Interest>, InterestError> {
+        if period.end.day() <= period.start.day() {
+            return Err(InterestError::EmptyPeriod);
+        }
+        if let Some(accrued_until) = self.interest_accrued_until {
+            if period.start.day() < accrued_until.day() {
+                return Err(InterestError::PeriodAccrued { accrued_until });
+            }
+        }
+
+        let mut credited = Vec::new();
+        for account in self.clients.values_mut().filter(|account| !account.closed) {
+            // Interest which would overflow, or overflow the client's funds or the house revenue
+            // account, is not credited. It is accrued on a copy of the client's accrual, so that
+            // its residue is left unchanged when nothing is credited.
+            let mut accrual = account.accrual.clone();
+            let Ok(amount) = accrual.accrue(rates, period.clone(), &self.config.precision) else {
+                continue;
+            };
+            if amount.0.is_zero() {
+                account.accrual = accrual;
+                continue;
+            }
+            let Some(revenue) = self.revenue.checked_sub(amount) else {
+                continue;
+            };
+            let paid = account
+                .check_credit(&[], amount)
+                .and_then(|()| account.interest(amount));
+            if paid.is_err() {
+                continue;
+            }
+            account.accrual = accrual;
+            account.accrual.record(self.now, account.available);
+            self.revenue = revenue;
+            credited.push(Interest {
+                client: account.client_id,
+                tx,
+                amount,
+            });
+        }
+        self.interest_accrued_until = Some(period.end);
+        Ok(credited)
+    }
+
+    /// Close a client's account, paying out their remaining available funds. Fails if the client
+    /// has any open disputes, holds or authorizations, pending deposits, funds in reserve or drawn
+    /// credit, since these could not be settled once the account is closed.
+    fn close(
+        &mut self,
+        Close { client, tx: _ }: Close,
+        fee: Amount,
+    ) -> Result<(), TransactionError> {
+        let account = self
+            .clients
+            .get_mut(&client)
+            .ok_or(TransactionError::ClientDoesNotExist { client_id: client })?;
+        let reason = if self.disputes.keys().any(|(id, _)| *id == client) {
+            Some("it has open disputes")
+        } else if !account.held.0.is_zero() {
+            Some("it has funds held")
+        } else if !account.pending.0.is_zero() {
+            Some("it has deposits pending settlement")
+        } else if !account.reserve.balance().0.is_zero() {
+            Some("it has funds in reserve")
+        } else if account.available.0.is_sign_negative() {
+            Some("it has drawn on its credit limit")
+        } else {
+            None
+        };
+        if let Some(reason) = reason {
+            return Err(TransactionError::CloseFailed {
+                client_id: client,
+                reason,
+            });
+        }
+
+        // Pay out the client's remaining funds as a final withdrawal.
+        let payout = account.close(fee)?;
+        account.withdrawals.record(self.now, payout);
+        Ok(())
+    }
+
+    /// Place a compliance hold on an amount of a client's available funds.
+    fn hold(
+        &mut self,
+        Hold {
+            client,
+            tx,
+            amount,
+            reason,
+        }: Hold,
+    ) -> Result<(), TransactionError> {
+        if self.holds.contains_key(&(client, tx)) {
+            return Err(TransactionError::HoldDuplicate {
+                client_id: client,
+                transaction_id: tx,
+            });
+        }
+
+        // Update the client's account, moving funds from available to held.
+        let amount = positive(client, amount)?;
+        self.clients
+            .get_mut(&client)
+            .ok_or(TransactionError::ClientDoesNotExist { client_id: client })?
+            .hold(amount)?;
+
+        // Track the hold so that it can later be released.
+        self.holds.insert((client, tx), OpenHold { reason, amount });
+        self.holds_placed = true;
+        Ok(())
+    }
+
+    /// Release some or all of a compliance hold, clearing it once fully released.
+    fn release(&mut self, Release { client, tx, amount }: Release) -> Result<(), TransactionError> {
+        let hold = *self
+            .holds
+            .get(&(client, tx))
+            .ok_or(TransactionError::ReleaseFailed {
+                client_id: client,
+                transaction_id: tx,
+            })?;
+        let amount = match amount {
+            Some(amount) => positive(client, amount)?,
+            None => hold.amount,
+        };
+        if amount.0 > hold.amount.0 {
+            return Err(TransactionError::ReleaseExceedsHold {
+                client_id: client,
+                transaction_id: tx,
+                requested: amount,
+                held: hold.amount,
+            });
+        }
+
+        // Update the client's account
+        self.clients
+            .get_mut(&client)
+            .ok_or(TransactionError::ClientDoesNotExist { client_id: client })?
+            .release(amount)?;
+
+        let remaining = Amount(hold.amount.0 - amount.0);
+        if remaining.0.is_zero() {
+            self.holds.remove(&(client, tx));
+        } else {
+            let hold = OpenHold {
+                amount: remaining,
+                ..hold
+            };
+            self.holds.insert((client, tx), hold);
+        }
+        Ok(())
+    }
+
+    /// Withdraw an amount from a client's account. Fails if the client does not exist, if the
+    /// client's available funds do not cover both the amount and the withdrawal fee, or if the
+    /// withdrawal would exceed any of the limits of the client's tier.
+    fn withdrawal(
+        &mut self,
+        Withdrawal {
+            client,
+            tx: _,
+            amount,
+            wallet,
+        }: Withdrawal,
+        fee: Amount,
+    ) -> Result<(), TransactionError> {
+        let account = self
+            .clients
+            .get_mut(&client)
+            .ok_or(TransactionError::ClientDoesNotExist { client_id: client })?;
+        if account.locked {
+            return Err(TransactionError::ClientAccountLocked { client_id: client });
+        }
+
+        // The fee is charged once the withdrawal has been applied, so check it is covered first.
+        let required = Amount(amount.0.saturating_add(fee.0));
+        let available = account.wallet(wallet).available;
+        if available.0.saturating_add(account.credit(wallet).0) < required.0 {
+            return Err(TransactionError::WithdrawalInsufficientFunds {
+                client_id: client,
+                available,
+                requested: required,
+            });
+        }
+
+        // Check the withdrawal is within the limits of the client's tier.
+        if let Some(limits) = self.config.limits.limits(client) {
+            account
+                .withdrawals
+                .check(limits, self.now, amount)
+                .map_err(|(limit, remaining)| TransactionError::LimitExceeded {
+                    client_id: client,
+                    limit,
+                    requested: amount,
+                    remaining,
+                })?;
+        }
+
+        // Reduce the client's available funds.
+        account.withdrawal(wallet, amount)?;
+        account.withdrawals.record(self.now, amount);
+        Ok(())
+    }
+
+    /// Dispute a client's deposit transaction, in full or in part. Without an amount, the
+    /// deposit's whole remaining disputable balance is disputed. Fails if the client does not
+    /// exist, or if the amount exceeds the deposit's remaining disputable balance.
+    fn dispute(&mut self, Dispute { client, tx, amount }: Dispute) -> Result<(), TransactionError> {
+        // Find the deposit's remaining disputable balance, which only settled deposits have.
+        if self.pending.contains_key(&(client, tx)) {
+            return Err(TransactionError::DepositPending {
+                client_id: client,
+                transaction_id: tx,
+            });
+        }
+        let disputable =
+            *self
+                .deposits
+                .get(&(client, tx))
+                .ok_or(TransactionError::DisputeFailed {
+                    client_id: client,
+                    transaction_id: tx,
+                })?;
+        let wallet = self.deposit_wallet(client, tx);
+        let account = self
+            .clients
+            .get_mut(&client)
+            .ok_or(TransactionError::ClientDoesNotExist { client_id: client })?;
+        if account.locked {
+            return Err(TransactionError::ClientAccountLocked { client_id: client });
+        }
+        let amount = match amount {
+            Some(amount) => positive(client, amount)?,
+            None => disputable,
+        };
+        if amount.0 > disputable.0 || disputable.0.is_zero() {
+            return Err(TransactionError::DisputeExceedsBalance {
+                client_id: client,
+                transaction_id: tx,
+                requested: amount,
+                disputable,
+            });
+        }
+
+        // Update the client's account, moving funds from available to held.
+        account.dispute(wallet, amount)?;
+
+        // Track the dispute, which is no longer disputable while it is open.
+        self.deposits
+            .insert((client, tx), Amount(disputable.0 - amount.0));
+        let disputed = self.disputes.entry((client, tx)).or_default();
+        *disputed = Amount(disputed.0.saturating_add(amount.0));
+        Ok(())
+    }
+
+    /// Resolve a client's disputed deposit, in full or in part. Without an amount, everything under
+    /// dispute is resolved. Fails if the client does not exist, or if the amount exceeds the amount
+    /// under dispute.
+    fn resolve(&mut self, Resolve { client, tx, amount }: Resolve) -> Result<(), TransactionError> {
+        // Confirm a dispute exists, and find the amount under dispute
+        let disputed =
+            *self
+                .disputes
+                .get(&(client, tx))
+                .ok_or(TransactionError::ResolveFailed {
+                    client_id: client,
+                    transaction_id: tx,
+                })?;
+        let amount = match amount {
+            Some(amount) => positive(client, amount)?,
+            None => disputed,
+        };
+        if amount.0 > disputed.0 {
+            return Err(TransactionError::ExceedsDisputedAmount {
+                client_id: client,
+                transaction_id: tx,
+                requested: amount,
+                disputed,
+            });
+        }
+
+        // Update the client's account
+        let wallet = self.deposit_wallet(client, tx);
+        self.clients
+            .get_mut(&client)
+            .ok_or(TransactionError::ClientDoesNotExist { client_id: client })?
+            .resolve(wallet, amount)?;
+
+        // Reduce the dispute, making the resolved amount disputable again.
+        self.reduce_dispute(client, tx, disputed, amount);
+        if let Some(disputable) = self.deposits.get_mut(&(client, tx)) {
+            *disputable = Amount(disputable.0.saturating_add(amount.0));
+        }
+        Ok(())
+    }
+
+    /// Chargeback a client's disputed deposit, in full or in part. Without an amount, everything
+    /// under dispute is charged back. Fails if the client does not exist, or if the amount exceeds
+    /// the amount under dispute.
+    fn chargeback(
+        &mut self,
+        Chargeback { client, tx, amount }: Chargeback,
+    ) -> Result<(), TransactionError> {
+        // Confirm a dispute exists, and find the amount under dispute
+        let disputed =
+            *self
+                .disputes
+                .get(&(client, tx))
+                .ok_or(TransactionError::ChargebackFailed {
+                    client_id: client,
+                    transaction_id: tx,
+                })?;
+        let amount = match amount {
+            Some(amount) => positive(client, amount)?,
+            None => disputed,
+        };
+        if amount.0 > disputed.0 {
+            return Err(TransactionError::ExceedsDisputedAmount {
+                client_id: client,
+                transaction_id: tx,
+                requested: amount,
+                disputed,
+            });
+        }
+
+        // Update the client's account
+        let wallet = self.deposit_wallet(client, tx);
+        self.clients
+            .get_mut(&client)
+            .ok_or(TransactionError::ClientDoesNotExist { client_id: client })?
+            .chargeback(wallet, amount)?;
+
+        // Reduce the dispute. The amount charged back has been reversed so is no longer disputable,
+        // but may be represented.
+        self.reduce_dispute(client, tx, disputed, amount);
+        let charged_back = self.chargebacks.entry((client, tx)).or_default();
+        *charged_back = Amount(charged_back.0.saturating_add(amount.0));
+        Ok(())
+    }
+
+    /// Represent a client's charged back deposit, in full or in part, restoring the funds charged
+    /// back. Without an amount, everything charged back is represented. The client's account is
+    /// unlocked once none of its deposits remain charged back, unless it has been locked by an
+    /// arbitration or a rule. Fails if the client does not exist, if the deposit has not been
+    /// charged back, or if the amount exceeds the amount charged back.
+    fn represent(
+        &mut self,
+        Represent { client, tx, amount }: Represent,
+    ) -> Result<(), TransactionError> {
+        // Find the amount charged back
+        let charged_back =
+            *self
+                .chargebacks
+                .get(&(client, tx))
+                .ok_or(TransactionError::RepresentFailed {
+                    client_id: client,
+                    transaction_id: tx,
+                })?;
+        let amount = match amount {
+            Some(amount) => positive(client, amount)?,
+            None => charged_back,
+        };
+        if amount.0 > charged_back.0 {
+            return Err(TransactionError::RepresentExceedsChargeback {
+                client_id: client,
+                transaction_id: tx,
+                requested: amount,
+                charged_back,
+            });
+        }
+
+        // Move the amount from charged back to represented.
+        let remaining = Amount(charged_back.0 - amount.0);
+        if remaining.0.is_zero() {
+            self.chargebacks.remove(&(client, tx));
+        } else {
+            self.chargebacks.insert((client, tx), remaining);
+        }
+
+        // Update the client's account
+        let wallet = self.deposit_wallet(client, tx);
+        self.clients
+            .get_mut(&client)
+            .ok_or(TransactionError::ClientDoesNotExist { client_id: client })?
+            .represent(wallet, amount, remaining.0.is_zero())?;
+        let represented = self.representments.entry((client, tx)).or_default();
+        *represented = Amount(represented.0.saturating_add(amount.0));
+        Ok(())
+    }
+
+    /// Arbitrate a client's represented deposit, in full or in part, debiting the funds restored
+    /// by the representment and locking the client's account again. Without an amount, everything
+    /// represented is arbitrated. Fails if the client does not exist, if the deposit has not been
+    /// represented, or if the amount exceeds the amount represented.
+    fn arbitrate(
+        &mut self,
+        Arbitrate { client, tx, amount }: Arbitrate,
+    ) -> Result<(), TransactionError> {
+        // Find the amount represented
+        let represented =
+            *self
+                .representments
+                .get(&(client, tx))
+                .ok_or(TransactionError::ArbitrateFailed {
+                    client_id: client,
+                    transaction_id: tx,
+                })?;
+        let amount = match amount {
+            Some(amount) => positive(client, amount)?,
+            None => represented,
+        };
+        if amount.0 > represented.0 {
+            return Err(TransactionError::ArbitrateExceedsRepresentment {
+                client_id: client,
+                transaction_id: tx,
+                requested: amount,
+                represented,
+            });
+        }
+
+        // Update the client's account
+        let wallet = self.deposit_wallet(client, tx);
+        self.clients
+            .get_mut(&client)
+            .ok_or(TransactionError::ClientDoesNotExist { client_id: client })?
+            .arbitrate(wallet, amount)?;
+
+        // Reduce the representment. An arbitration is final, so the amount cannot be represented
+        // again.
+        let remaining = Amount(represented.0 - amount.0);
+        if remaining.0.is_zero() {
+            self.representments.remove(&(client, tx));
+        } else {
+            self.representments.insert((client, tx), remaining);
+        }
+        Ok(())
+    }
+
+    /// Refund part of a client's deposit, in full when no amount is given. Only the part of the
+    /// deposit which has not already been refunded, charged back, or is under dispute can be
+    /// refunded, and the refunded part can no longer be disputed. Fails if the client does not
+    /// exist, if the transaction is not a deposit, if the amount exceeds the refundable balance, or
+    /// if the client's available funds do not cover the amount.
+    fn refund(&mut self, Refund { client, tx, amount }: Refund) -> Result<(), TransactionError> {
+        // Find the deposit's refundable balance, which is the same as its disputable balance.
+        if self.pending.contains_key(&(client, tx)) {
+            return Err(TransactionError::DepositPending {
+                client_id: client,
+                transaction_id: tx,
+            });
+        }
+        let refundable =
+            *self
+                .deposits
+                .get(&(client, tx))
+                .ok_or(TransactionError::RefundFailed {
+                    client_id: client,
+                    transaction_id: tx,
+                })?;
+        let amount = match amount {
+            Some(amount) => positive(client, amount)?,
+            None => refundable,
+        };
+        if amount.0 > refundable.0 || refundable.0.is_zero() {
+            return Err(TransactionError::RefundExceedsDeposit {
+                client_id: client,
+                transaction_id: tx,
+                requested: amount,
+                refundable,
+            });
+        }
+
+        // Update the client's account
+        let wallet = self.deposit_wallet(client, tx);
+        self.clients
+            .get_mut(&client)
+            .ok_or(TransactionError::ClientDoesNotExist { client_id: client })?
+            .refund(wallet, amount)?;
+
+        // The refunded part of the deposit can no longer be disputed or refunded.
+        self.deposits
+            .insert((client, tx), Amount(refundable.0 - amount.0));
+        Ok(())
+    }
+
+    /// Set a client's credit limit, replacing any limit the client was configured with. Fails if
+    /// the client does not exist or the limit is negative.
+    fn set_credit_limit(
+        &mut self,
+        CreditLimit { client, amount, .. }: CreditLimit,
+    ) -> Result<(), TransactionError> {
+        self.clients
+            .get_mut(&client)
+            .ok_or(TransactionError::ClientDoesNotExist { client_id: client })?
+            .set_credit_limit(amount)
+    }
+
+    /// Reverse a deposit, withdrawal or authorization loaded in error, undoing its effect on the
+    /// client's account and the ledger, and refunding the fee charged for it. Deposits can only be
+    /// reversed while their funds are still available and they have not been disputed or
+    /// refunded, and authorizations only before they have been captured or voided. Fails if the
+    /// client does not exist or the transaction has not been applied.
+    fn reverse(&mut self, Reversal { client, tx }: Reversal) -> Result<(), TransactionError> {
+        let key = (client, tx);
+        let Posting {
+            transaction,
+            fee,
+            timestamp,
+        } = *self
+            .postings
+            .get(&key)
+            .ok_or(TransactionError::ReversalFailed {
+                client_id: client,
+                transaction_id: tx,
+            })?;
+        let inconsistent = |reason| TransactionError::ReversalInconsistent {
+            client_id: client,
+            transaction_id: tx,
+            reason,
+        };
+        // The reversal is applied to a copy of the account, so that nothing changes if any of the
+        // client's funds would overflow.
+        let mut account = self
+            .clients
+            .get(&client)
+            .ok_or(TransactionError::ClientDoesNotExist { client_id: client })?
+            .clone();
+        if account.locked {
+            return Err(TransactionError::ClientAccountLocked { client_id: client });
+        }
+        let revenue = self
+            .revenue
+            .checked_sub(fee)
+            .ok_or(TransactionError::AmountOverflow { client_id: client })?;
+
+        match transaction {
+            Transaction::Deposit(Deposit { amount, .. }) => {
+                // The whole deposit must still be disputable, and never have been charged back.
+                let untouched = self.deposits.get(&key) == Some(&amount)
+                    && !self.disputes.contains_key(&key)
+                    && !self.chargebacks.contains_key(&key)
+                    && !self.representments.contains_key(&key);
+                if !untouched {
+                    return Err(inconsistent(
+                        "the deposit has since been disputed or refunded",
+                    ));
+                }
+                if self.pending.contains_key(&key) {
+                    // A deposit which has not yet settled is taken back from pending funds.
+                    account.pending = account
+                        .pending
+                        .checked_sub(amount)
+                        .ok_or(account.overflow())?;
+                } else {
+                    // Whatever remains withheld from the deposit is taken from the reserve.
+                    let withheld = account.reserve.withheld(tx);
+                    let available = account.wallet(transaction.wallet()).available;
+                    let covered = available.0.saturating_add(withheld.0).saturating_add(fee.0);
+                    if covered < amount.0 {
+                        return Err(inconsistent("the deposited funds are no longer available"));
+                    }
+                    account.reserve.remove(tx);
+                    let debit = Amount(amount.0 - withheld.0);
+                    account.adjust(transaction.wallet(), Amount(-debit.0), Amount::from(0))?;
+                }
+            }
+            Transaction::Withdrawal(Withdrawal { amount, wallet, .. }) => {
+                // The withdrawn funds are credited back to the account.
+                account.check_credit(&[], amount)?;
+                account.adjust(wallet, amount, Amount::from(0))?;
+                account.withdrawals.release(timestamp, amount);
+            }
+            Transaction::Authorize(Authorize { amount, .. }) => {
+                if self.authorizations.get(&key) != Some(&amount) {
+                    return Err(inconsistent(
+                        "the authorization has since been captured or voided",
+                    ));
+                }
+                account.adjust(WalletId::default(), amount, Amount(-amount.0))?;
+            }
+            _ => {
+                return Err(TransactionError::ReversalFailed {
+                    client_id: client,
+                    transaction_id: tx,
+                })
+            }
+        }
+
+        // Refund the fee charged for the reversed transaction.
+        account.check_credit(&[], fee)?;
+        account.adjust(transaction.wallet(), fee, Amount::from(0))?;
+        account.fees = account.fees.checked_sub(fee).ok_or(account.overflow())?;
+
+        match transaction {
+            Transaction::Deposit(_) => {
+                self.pending.remove(&key);
+                self.deposits.remove(&key);
+            }
+            Transaction::Authorize(_) => {
+                self.authorizations.remove(&key);
+            }
+            _ => {}
+        }
+        self.clients.insert(client, account);
+        self.revenue = revenue;
+        self.postings.remove(&key);
+        Ok(())
+    }
+
+    /// Move an amount of a client's available funds between two of their wallets. Fails if the
+    /// client does not exist, or if the available funds of the wallet moved from do not cover
+    /// both the amount and the transfer fee.
+    fn transfer(
+        &mut self,
+        Transfer {
+            client,
+            tx: _,
+            amount,
+            wallet,
+            to_wallet,
+        }: Transfer,
+        fee: Amount,
+    ) -> Result<(), TransactionError> {
+        let account = self
+            .clients
+            .get_mut(&client)
+            .ok_or(TransactionError::ClientDoesNotExist { client_id: client })?;
+
+        // The fee is charged once the transfer has been applied, so check it is covered first.
+        let amount = positive(client, amount)?;
+        let required = Amount(amount.0.saturating_add(fee.0));
+        let available = account.wallet(wallet).available;
+        if !account.locked && available.0 < required.0 {
+            return Err(TransactionError::TransferInsufficientFunds {
+                client_id: client,
+                wallet,
+                available,
+                requested: required,
+            });
+        }
+        account.transfer(wallet, to_wallet, amount)
+    }
+
+    /// Reduce the amount under dispute for a deposit, clearing the dispute once nothing remains.
+    fn reduce_dispute(
+        &mut self,
+        client: ClientId,
+        tx: TransactionId,
+        disputed: Amount,
+        amount: Amount,
+    ) {
+        let remaining = Amount(disputed.0 - amount.0);
+        if remaining.0.is_zero() {
+            self.disputes.remove(&(client, tx));
+        } else {
+            self.disputes.insert((client, tx), remaining);
+        }
+    }
+
+    /// Authorize an amount from a client's account, holding the funds until the authorization is
+    /// captured or voided. Fails if the client does not exist, if the client's available funds do
+    /// not cover the amount, or if the transaction ID is already authorized.
+    fn authorize(
+        &mut self,
+        Authorize { client, tx, amount }: Authorize,
+    ) -> Result<(), TransactionError> {
+        if self.authorizations.contains_key(&(client, tx)) {
+            return Err(TransactionError::AuthorizeDuplicate {
+                client_id: client,
+                transaction_id: tx,
+            });
+        }
+
+        // Update the client's account, moving funds from available to held.
+        let amount = positive(client, amount)?;
+        self.clients
+            .get_mut(&client)
+            .ok_or(TransactionError::ClientDoesNotExist { client_id: client })?
+            .authorize(amount)?;
+
+        // Track the authorization so that it can later be captured or voided.
+        self.authorizations.insert((client, tx), amount);
+        Ok(())
+    }
+
+    /// Capture a client's authorization, in full or in part. The remainder of a partial capture
+    /// stays authorized until it is captured or voided. Fails if the client does not exist, if the
+    /// transaction is not authorized, or if the amount exceeds the amount remaining authorized.
+    fn capture(&mut self, Capture { client, tx, amount }: Capture) -> Result<(), TransactionError> {
+        // Find the amount remaining authorized
+        let remaining =
+            *self
+                .authorizations
+                .get(&(client, tx))
+                .ok_or(TransactionError::CaptureFailed {
+                    client_id: client,
+                    transaction_id: tx,
+                })?;
+        let amount = match amount {
+            Some(amount) => positive(client, amount)?,
+            None => remaining,
+        };
+        if amount.0 > remaining.0 {
+            return Err(TransactionError::CaptureExceedsAuthorization {
+                client_id: client,
+                transaction_id: tx,
+                requested: amount,
+                remaining,
+            });
+        }
+
+        // Update the client's account
+        self.clients
+            .get_mut(&client)
+            .ok_or(TransactionError::ClientDoesNotExist { client_id: client })?
+            .capture(amount)?;
+
+        // Reduce the authorization, clearing it once fully captured.
+        let remaining = Amount(remaining.0 - amount.0);
+        if remaining.0.is_zero() {
+            self.authorizations.remove(&(client, tx));
+        } else {
+            self.authorizations.insert((client, tx), remaining);
+        }
+        Ok(())
+    }
+
+    /// Void a client's authorization, releasing the amount remaining authorized. Fails if the
+    /// client does not exist or if the transaction is not authorized.
+    fn void(&mut self, Void { client, tx }: Void) -> Result<(), TransactionError> {
+        // Find the amount remaining authorized
+        let remaining =
+            *self
+                .authorizations
+                .get(&(client, tx))
+                .ok_or(TransactionError::VoidFailed {
+                    client_id: client,
+                    transaction_id: tx,
+                })?;
+
+        // Update the client's account, moving funds from held to available.
+        self.clients
+            .get_mut(&client)
+            .ok_or(TransactionError::ClientDoesNotExist { client_id: client })?
+            .void(remaining)?;
+
+        // Clear the authorization
+        self.authorizations.remove(&(client, tx));
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+
+    use super::*;
+    use crate::clients::{ClientRecord, RiskRating};
+    use crate::fees::Fee;
+    use crate::limits::{Limits, DEFAULT_TIER};
+    use crate::precision::Rounding;
+    use crate::rules::{DepositWithdrawDispute, HighRiskWithdrawal, WithdrawalVelocity};
+    use crate::types::{
+        Arbitrate, Authorize, Capture, Chargeback, Close, CreditLimit, Deposit, Dispute, Hold,
+        HoldReason, Interest, Refund, Release, Represent, Resolve, Reversal, Settle,
+        TransactionType, Transfer, Void, Withdrawal,
+    };
+
+    #[test]
+    fn deposit() {
+        let mut ledger = Ledger::default();
+        let client_id = ClientId(1);
+        let transaction_id = TransactionId(1);
+        let amount = Amount(7.into());
+
+        let transaction = Transaction::Deposit(Deposit {
+            client: client_id,
+            tx: transaction_id,
+            amount,
+            wallet: WalletId::default(),
+        });
+
+        ledger.update(transaction).unwrap();
+
+        // Assert client account has been created and the deposit added.
+        let Account {
+            available,
+            held,
+            locked,
+            ..
+        } = ledger
+            .clients
+            .get(&client_id)
+            .expect("client not found in ledger");
+
+        assert_eq!(
+            (available, held, locked),
+            (&amount, &Amount(0.into()), &false)
+        );
+
+        // Assert that the transaction has been stored.
+        assert!(ledger.deposits.contains_key(&(client_id, transaction_id)));
+
+        // Assert that there are no disputes
+        assert!(ledger.disputes.is_empty());
+    }
+
+    #[test]
+    fn withdrawal() {
+        let mut ledger = Ledger::default();
+        let client_id = ClientId(1);
+        let deposit_id = TransactionId(1);
+        let withdrawal_id = TransactionId(2);
+
+        let transactions = [
+            Transaction::Deposit(Deposit {
+                client: client_id,
+                tx: deposit_id,
+                amount: Amount::from(7),
+                wallet: WalletId::default(),
+            }),
+            Transaction::Withdrawal(Withdrawal {
+                client: client_id,
+                tx: withdrawal_id,
+                amount: Amount::from(3),
+                wallet: WalletId::default(),
+            }),
+        ];
+
+        transactions
+            .into_iter()
+            .for_each(|transaction| ledger.update(transaction).unwrap());
+
+        // Assert client account has been created, the deposit added, and withdrawal made.
+        let Account {
+            available,
+            held,
+            locked,
+            ..
+        } = ledger
+            .clients
+            .get(&client_id)
+            .expect("client not found in ledger");
+
+        assert_eq!(
+            (available, held, locked),
+            (&Amount::from(4), &Amount::from(0), &false)
+        );
+
+        // Assert that the deposit has been stored.
+        assert!(ledger.deposits.contains_key(&(client_id, deposit_id)));
+
+        // Assert that there are no disputes
+        assert!(ledger.disputes.is_empty());
+    }
+
+    #[test]
+    fn withdrawal_insufficient_funds() {
+        let mut ledger = Ledger::default();
+        let client_id = ClientId(1);
+        let deposit_id = TransactionId(1);
+        let deposit_amount = Amount::from(5);
+        let withdrawal_id = TransactionId(2);
+        let withdrawal_amount = Amount::from(7);
+
+        ledger
+            .update(Transaction::Deposit(Deposit {
+                client: client_id,
+                tx: deposit_id,
+                amount: deposit_amount,
+                wallet: WalletId::default(),
+            }))
+            .unwrap();
+
+        let withdrawal_result = ledger.update(Transaction::Withdrawal(Withdrawal {
+            client: client_id,
+            tx: withdrawal_id,
+            amount: withdrawal_amount,
+            wallet: WalletId::default(),
+        }));
+
+        // Assert that the withdrawal failed
+        assert_eq!(
+            withdrawal_result,
+            Err(TransactionError::WithdrawalInsufficientFunds {
+                client_id,
+                available: deposit_amount,
+                requested: withdrawal_amount
+            })
+        );
+
+        // Assert client account has been created, the deposit added, and that the withdrawal failed.
+        let Account {
+            available,
+            held,
+            locked,
+            ..
+        } = ledger
+            .clients
+            .get(&client_id)
+            .expect("client not found in ledger");
+
+        assert_eq!(
+            (available, held, locked),
+            (&deposit_amount, &Amount::from(0), &false)
+        );
+
+        // Assert that the deposit has been stored.
+        assert!(ledger.deposits.contains_key(&(client_id, deposit_id)));
+
+        // Assert that there are no disputes
+        assert!(ledger.disputes.is_empty());
+    }
+
+    #[test]
+    fn dispute() {
+        let mut ledger = Ledger::default();
+        let client_id = ClientId(1);
+        let deposit_id_1 = TransactionId(1);
+        let deposit_id_2 = TransactionId(2);
+        let amount_available = Amount::from(5);
+        let amount_held = Amount::from(3);
+
+        let transactions = [
+            Transaction::Deposit(Deposit {
+                client: client_id,
+                tx: deposit_id_1,
+                amount: amount_available,
+                wallet: WalletId::default(),
+            }),
+            Transaction::Deposit(Deposit {
+                client: client_id,
+                tx: deposit_id_2,
+                amount: amount_held,
+                wallet: WalletId::default(),
+            }),
+            Transaction::Dispute(Dispute {
+                client: client_id,
+                tx: deposit_id_2,
+                amount: None,
+            }),
+        ];
+
+        transactions
+            .into_iter()
+            .for_each(|transaction| ledger.update(transaction).unwrap());
+
+        // Assert that the client account has been created, the deposits added, and the correct amount is held in dispute.
+        let Account {
+            available,
+            held,
+            locked,
+            ..
+        } = ledger
+            .clients
+            .get(&client_id)
+            .expect("client not found in ledger");
+
+        assert_eq!(
+            (available, held, locked),
+            (&amount_available, &amount_held, &false)
+        );
+
+        // Assert that the dispute is being tracked
+        assert!(ledger.disputes.contains_key(&(client_id, deposit_id_2)));
+    }
+
+    #[test]
+    fn dispute_no_transaction() {
+        // If the tx specified by the dispute doesn't exist this is an error on the partner's side.
+        let mut ledger = Ledger::default();
+        let client_id = ClientId(1);
+        let deposit_id = TransactionId(1);
+        let deposit_amount = Amount::from(7);
+        let dispute_id = TransactionId(2);
+
+        ledger
+            .update(Transaction::Deposit(Deposit {
+                client: client_id,
+                tx: deposit_id,
+                amount: deposit_amount,
+                wallet: WalletId::default(),
+            }))
+            .unwrap();
+
+        let dispute_result = ledger.update(Transaction::Dispute(Dispute {
+            client: client_id,
+            tx: dispute_id,
+            amount: None,
+        }));
+
+        // Assert that the dispute failed
+        assert_eq!(
+            dispute_result,
+            Err(TransactionError::DisputeFailed {
+                client_id,
+                transaction_id: dispute_id
+            })
+        );
+
+        // Assert client account has been created, the deposit added, and that no funds are held.
+        let Account {
+            available,
+            held,
+            locked,
+            ..
+        } = ledger
+            .clients
+            .get(&client_id)
+            .expect("client not found in ledger");
+
+        assert_eq!(
+            (available, held, locked),
+            (&deposit_amount, &Amount::from(0), &false)
+        );
+
+        // Assert that there are no disputes
+        assert!(ledger.disputes.is_empty());
+    }
+
+    #[test]
+    fn resolve() {
+        let mut ledger = Ledger::default();
+        let client_id = ClientId(1);
+        let deposit_id_1 = TransactionId(1);
+        let deposit_id_2 = TransactionId(2);
+        let amount_1 = Amount::from(5);
+        let amount_2 = Amount::from(3);
+
+        let transactions = [
+            Transaction::Deposit(Deposit {
+                client: client_id,
+                tx: deposit_id_1,
+                amount: amount_1,
+                wallet: WalletId::default(),
+            }),
+            Transaction::Deposit(Deposit {
+                client: client_id,
+                tx: deposit_id_2,
+                amount: amount_2,
+                wallet: WalletId::default(),
+            }),
+            Transaction::Dispute(Dispute {
+                client: client_id,
+                tx: deposit_id_2,
+                amount: None,
+            }),
+            Transaction::Resolve(Resolve {
+                client: client_id,
+                tx: deposit_id_2,
+                amount: None,
+            }),
+        ];
+
+        transactions
+            .into_iter()
+            .for_each(|transaction| ledger.update(transaction).unwrap());
+
+        // Assert that the client account has been created, the deposits added, and that the dispute has been resolved.
+        let Account {
+            available,
+            held,
+            locked,
+            ..
+        } = ledger
+            .clients
+            .get(&client_id)
+            .expect("client not found in ledger");
+
+        assert_eq!(
+            (available, held, locked),
+            (
+                &Amount::from(amount_1.0 + amount_2.0),
+                &Amount::from(0),
+                &false
+            )
+        );
+
+        // Assert that there are no disputes
+        assert!(ledger.disputes.is_empty());
+    }
+
+    #[test]
+    fn resolve_transaction_not_disputed() {
+        // If the tx specified by the resolve isn't under dispute this is an error on the partner's side.
+        let mut ledger = Ledger::default();
+        let client_id = ClientId(1);
+        let deposit_id_1 = TransactionId(1);
+        let deposit_id_2 = TransactionId(2);
+        let amount_1 = Amount::from(5);
+
+        ledger
+            .update(Transaction::Deposit(Deposit {
+                client: client_id,
+                tx: deposit_id_1,
+                amount: amount_1,
+                wallet: WalletId::default(),
+            }))
+            .unwrap();
+
+        let resolve_result = ledger.update(Transaction::Resolve(Resolve {
+            client: client_id,
+            tx: deposit_id_2,
+            amount: None,
+        }));
+
+        // Assert that the resolve failed
+        assert_eq!(
+            resolve_result,
+            Err(TransactionError::ResolveFailed {
+                client_id,
+                transaction_id: deposit_id_2
+            })
+        );
+    }
+
+    #[test]
+    fn chargeback() {
+        let mut ledger = Ledger::default();
+        let client_id = ClientId(1);
+        let deposit_id_1 = TransactionId(1);
+        let deposit_id_2 = TransactionId(2);
+        let amount_1 = Amount::from(5);
+        let amount_2 = Amount::from(3);
+
+        let transactions = [
+            Transaction::Deposit(Deposit {
+                client: client_id,
+                tx: deposit_id_1,
+                amount: amount_1,
+                wallet: WalletId::default(),
+            }),
+            Transaction::Deposit(Deposit {
+                client: client_id,
+                tx: deposit_id_2,
+                amount: amount_2,
+                wallet: WalletId::default(),
+            }),
+            Transaction::Dispute(Dispute {
+                client: client_id,
+                tx: deposit_id_2,
+                amount: None,
+            }),
+            Transaction::Chargeback(Chargeback {
+                client: client_id,
+                tx: deposit_id_2,
+                amount: None,
+            }),
+        ];
+
+        transactions
+            .into_iter()
+            .for_each(|transaction| ledger.update(transaction).unwrap());
+
+        // Assert that the client account has been created, the deposits added, the dispute has been charged back,
+        // and that the client has been locked
+        let Account {
+            available,
+            held,
+            locked,
+            ..
+        } = ledger
+            .clients
+            .get(&client_id)
+            .expect("client not found in ledger");
+
+        assert_eq!(
+            (available, held, locked),
+            (&amount_1, &Amount::from(0), &true)
+        );
+
+        // Assert that there are no disputes
+        assert!(ledger.disputes.is_empty());
+    }
+
+    #[test]
+    fn chargeback_transaction_not_disputed() {
+        // If the tx specified by the chargeback isn't under dispute this is an error on the partner's side.
+        let mut ledger = Ledger::default();
+        let client_id = ClientId(1);
+        let deposit_id_1 = TransactionId(1);
+        let deposit_id_2 = TransactionId(2);
+        let amount_1 = Amount::from(5);
+
+        ledger
+            .update(Transaction::Deposit(Deposit {
+                client: client_id,
+                tx: deposit_id_1,
+                amount: amount_1,
+                wallet: WalletId::default(),
+            }))
+            .unwrap();
+
+        let resolve_result = ledger.update(Transaction::Chargeback(Chargeback {
+            client: client_id,
+            tx: deposit_id_2,
+            amount: None,
+        }));
+
+        // Assert that the resolve failed
+        assert_eq!(
+            resolve_result,
+            Err(TransactionError::ChargebackFailed {
+                client_id,
+                transaction_id: deposit_id_2
+            })
+        );
+    }
+
+    #[test]
+    fn load_summary() {
+        let mut ledger = Ledger::default();
+        let rdr = "type, client, tx, amount\n\
+                   deposit, 1, 1, 5\n\
+                   withdrawal, 1, 2, 9\n\
+                   dispute, 1, 9,\n\
+                   withdrawal, 1, 3, 2\n"
+            .as_bytes();
+
+        assert_eq!(
+            ledger.load(rdr),
+            LoadSummary {
+                applied: 2,
+                rejected: 2
+            }
+        );
+        assert_eq!(
+            ledger.account(ClientId(1)).map(|account| account.available),
+            Some(Amount::from(3))
+        );
+    }
+
+    #[test]
+    fn dry_run() {
+        let mut ledger = Ledger::default();
+        ledger.load("type, client, tx, amount\ndeposit, 1, 1, 5\n".as_bytes());
+
+        let rdr = "type, client, tx, amount\n\
+                   withdrawal, 1, 2, 4\n\
+                   withdrawal, 1, 3, 4\n\
+                   deposit, 2, 4,\n\
+                   dispute, 1, 1,\n"
+            .as_bytes();
+
+        let mut rejected = Vec::new();
+        let summary = ledger.dry_run(read_input(rdr), |report| {
+            if let Report::Reject(reject) = report {
+                rejected.push(reject.position)
+            }
+        });
+
+        // Assert that the second withdrawal and the malformed deposit would be rejected.
+        assert_eq!(
+            summary,
+            LoadSummary {
+                applied: 2,
+                rejected: 2
+            }
+        );
+        assert_eq!(
+            rejected,
+            [
+                Position {
+                    input: 0,
+                    line: 3,
+                    client: Some(ClientId(1)),
+                    tx: Some(TransactionId(3))
+                },
+                Position {
+                    input: 0,
+                    line: 4,
+                    client: Some(ClientId(2)),
+                    tx: Some(TransactionId(4))
+                }
+            ]
+        );
+
+        // Assert that the ledger itself is unchanged.
+        let Account {
+            available, held, ..
+        } = ledger
+            .account(ClientId(1))
+            .expect("client not found in ledger");
+        assert_eq!((available, held), (&Amount::from(5), &Amount::from(0)));
+        assert!(ledger.disputes.is_empty());
+    }
+
+    #[test]
+    fn fees() {
+        let mut fees = FeeSchedule::default();
+        fees.insert(
+            TransactionType::Withdrawal,
+            Fee {
+                flat: Amount::from(1),
+                percent: Decimal::from(10),
+            },
+        );
+        fees.insert(
+            TransactionType::Chargeback,
+            Fee {
+                flat: Amount::from(15),
+                percent: Decimal::ZERO,
+            },
+        );
+        let mut ledger = Ledger::new(LedgerConfig {
+            fees,
+            ..LedgerConfig::default()
+        });
+        let client_id = ClientId(1);
+
+        let transactions = [
+            Transaction::Deposit(Deposit {
+                client: client_id,
+                tx: TransactionId(1),
+                amount: Amount::from(50),
+                wallet: WalletId::default(),
+            }),
+            Transaction::Deposit(Deposit {
+                client: client_id,
+                tx: TransactionId(2),
+                amount: Amount::from(20),
+                wallet: WalletId::default(),
+            }),
+            Transaction::Withdrawal(Withdrawal {
+                client: client_id,
+                tx: TransactionId(3),
+                amount: Amount::from(10),
+                wallet: WalletId::default(),
+            }),
+            Transaction::Dispute(Dispute {
+                client: client_id,
+                tx: TransactionId(2),
+                amount: None,
+            }),
+            Transaction::Chargeback(Chargeback {
+                client: client_id,
+                tx: TransactionId(2),
+                amount: None,
+            }),
+        ];
+
+        transactions
+            .into_iter()
+            .for_each(|transaction| ledger.update(transaction).unwrap());
+
+        // Assert that the withdrawal fee of 1 + 10% and the chargeback fee of 15 were debited.
+        let Account {
+            available,
+            held,
+            locked,
+            fees,
+            ..
+        } = ledger
+            .clients
+            .get(&client_id)
+            .expect("client not found in ledger");
+
+        assert_eq!(
+            (available, held, locked, fees),
+            (
+                &Amount::from(23),
+                &Amount::from(0),
+                &true,
+                &Amount::from(17)
+            )
+        );
+
+        // Assert that the fees were credited to the house revenue account.
+        assert_eq!(ledger.revenue(), Amount::from(17));
+
+        // Assert that the house revenue account is output after the clients.
+        let house = ledger.entries().last().expect("house account not output");
+        assert_eq!(
+            (house.client, house.available, house.total, house.fees),
+            (
+                AccountId::House,
+                Amount::from(17),
+                Amount::from(17),
+                Some(Amount::from(0))
+            )
+        );
+        let house = ledger
+            .wallet_entries()
+            .last()
+            .expect("house account not output");
+        assert_eq!(
+            (house.client, house.available),
+            (AccountId::House, Amount::from(17))
+        );
+    }
+
+    #[test]
+    fn largest_client_id() {
+        // Assert that every client ID is free for input rows, with or without a fee schedule, as
+        // the house revenue account has none.
+        let client_id = ClientId(u16::MAX);
+        let deposit = Transaction::Deposit(Deposit {
+            client: client_id,
+            tx: TransactionId(1),
+            amount: Amount::from(5),
+            wallet: WalletId::default(),
+        });
+        let mut fees = FeeSchedule::default();
+        fees.insert(TransactionType::Withdrawal, Fee::default());
+
+        for config in [
+            LedgerConfig::default(),
+            LedgerConfig {
+                fees,
+                ..LedgerConfig::default()
+            },
+        ] {
+            let mut ledger = Ledger::new(config);
+            ledger.update(deposit).unwrap();
+            let entry = ledger.entries().next().expect("client not output");
+            assert_eq!(
+                (entry.client, entry.available),
+                (AccountId::Client(client_id), Amount::from(5))
+            );
+        }
+    }
+
+    #[test]
+    fn fee_negative_amounts() {
+        let mut fees = FeeSchedule::default();
+        let fee = Fee {
+            flat: Amount::from(0),
+            percent: Decimal::TEN,
+        };
+        fees.insert(TransactionType::Deposit, fee);
+        fees.insert(TransactionType::Withdrawal, fee);
+        let mut ledger = Ledger::new(LedgerConfig {
+            fees,
+            ..LedgerConfig::default()
+        });
+        let client_id = ClientId(1);
+        let deposit = |tx, amount| {
+            Transaction::Deposit(Deposit {
+                client: client_id,
+                tx: TransactionId(tx),
+                amount: Amount::from(amount),
+                wallet: WalletId::default(),
+            })
+        };
+
+        let transactions = [
+            deposit(1, 100),
+            Transaction::Withdrawal(Withdrawal {
+                client: client_id,
+                tx: TransactionId(2),
+                amount: Amount::from(-50),
+                wallet: WalletId::default(),
+            }),
+            deposit(3, -20),
+        ];
+        transactions
+            .into_iter()
+            .for_each(|transaction| ledger.update(transaction).unwrap());
+
+        // Assert that the fees on the negative amounts, which lenient parsing lets through, are
+        // charged on their size rather than paid to the client.
+        let account = ledger.account(client_id).unwrap();
+        assert_eq!(
+            (account.available, account.fees, ledger.revenue()),
+            (Amount::from(113), Amount::from(17), Amount::from(17))
+        );
+    }
+
+    #[test]
+    fn fee_insufficient_funds() {
+        let mut fees = FeeSchedule::default();
+        for transaction_type in [TransactionType::Deposit, TransactionType::Dispute] {
+            fees.insert(
+                transaction_type,
+                Fee {
+                    flat: Amount::from(5),
+                    percent: Decimal::ZERO,
+                },
+            );
+        }
+        let mut ledger = Ledger::new(LedgerConfig {
+            fees,
+            ..LedgerConfig::default()
+        });
+        let client_id = ClientId(1);
+        let deposit = |tx, amount| {
+            Transaction::Deposit(Deposit {
+                client: client_id,
+                tx: TransactionId(tx),
+                amount: Amount::from(amount),
+                wallet: WalletId::default(),
+            })
+        };
+
+        // Assert that a deposit smaller than its fee fails, without creating the account.
+        assert_eq!(
+            ledger.update(deposit(1, 3)),
+            Err(TransactionError::FeeInsufficientFunds {
+                client_id,
+                available: Amount::from(3),
+                fee: Amount::from(5),
+            })
+        );
+        assert!(ledger.account(client_id).is_none());
+
+        // Assert that a dispute fails when the funds left once it is held cannot cover its fee.
+        ledger.update(deposit(2, 12)).unwrap();
+        ledger.update(deposit(3, 10)).unwrap();
+        assert_eq!(
+            ledger.update(Transaction::Dispute(Dispute {
+                client: client_id,
+                tx: TransactionId(2),
+                amount: None,
+            })),
+            Err(TransactionError::FeeInsufficientFunds {
+                client_id,
+                available: Amount::from(0),
+                fee: Amount::from(5),
+            })
+        );
+        ledger
+            .update(Transaction::Dispute(Dispute {
+                client: client_id,
+                tx: TransactionId(3),
+                amount: Some(Amount::from(7)),
+            }))
+            .unwrap();
+
+        let account = ledger
+            .account(client_id)
+            .expect("client not found in ledger");
+        assert_eq!(
+            (account.available, account.held, account.fees),
+            (Amount::from(0), Amount::from(7), Amount::from(15))
+        );
+    }
+
+    #[test]
+    fn withdrawal_insufficient_funds_for_fee() {
+        let mut fees = FeeSchedule::default();
+        fees.insert(
+            TransactionType::Withdrawal,
+            Fee {
+                flat: Amount::from(1),
+                percent: Decimal::ZERO,
+            },
+        );
+        let mut ledger = Ledger::new(LedgerConfig {
+            fees,
+            ..LedgerConfig::default()
+        });
+        let client_id = ClientId(1);
+
+        ledger
+            .update(Transaction::Deposit(Deposit {
+                client: client_id,
+                tx: TransactionId(1),
+                amount: Amount::from(5),
+                wallet: WalletId::default(),
+            }))
+            .unwrap();
+
+        // Assert that the withdrawal fails as the fee is not covered.
+        assert_eq!(
+            ledger.update(Transaction::Withdrawal(Withdrawal {
+                client: client_id,
+                tx: TransactionId(2),
+                amount: Amount::from(5),
+                wallet: WalletId::default(),
+            })),
+            Err(TransactionError::WithdrawalInsufficientFunds {
+                client_id,
+                available: Amount::from(5),
+                requested: Amount::from(6),
+            })
+        );
+        assert_eq!(ledger.revenue(), Amount::from(0));
+    }
+
+    #[test]
+    fn withdrawal_limits() {
+        let mut limits = LimitSchedule::default();
+        limits.insert_tier(
+            DEFAULT_TIER,
+            Limits {
+                per_transaction: Some(Amount::from(50)),
+                daily: Some(Amount::from(80)),
+                monthly: None,
+            },
+        );
+        let mut ledger = Ledger::new(LedgerConfig {
+            limits,
+            ..LedgerConfig::default()
+        });
+        let client_id = ClientId(1);
+        let withdrawal = |tx, amount| {
+            Transaction::Withdrawal(Withdrawal {
+                client: client_id,
+                tx: TransactionId(tx),
+                amount: Amount::from(amount),
+                wallet: WalletId::default(),
+            })
+        };
+
+        ledger
+            .update(Transaction::Deposit(Deposit {
+                client: client_id,
+                tx: TransactionId(1),
+                amount: Amount::from(500),
+                wallet: WalletId::default(),
+            }))
+            .unwrap();
+
+        // Assert that a single withdrawal above the per transaction limit fails.
+        assert_eq!(
+            ledger.update(withdrawal(2, 60)),
+            Err(TransactionError::LimitExceeded {
+                client_id,
+                limit: Limit::PerTransaction,
+                requested: Amount::from(60),
+                remaining: Amount::from(50),
+            })
+        );
+
+        // Assert that withdrawals fail once the daily limit is reached.
+        ledger.update(withdrawal(3, 50)).unwrap();
+        assert_eq!(
+            ledger.update(withdrawal(4, 40)),
+            Err(TransactionError::LimitExceeded {
+                client_id,
+                limit: Limit::Daily,
+                requested: Amount::from(40),
+                remaining: Amount::from(30),
+            })
+        );
+
+        // Assert that the daily limit resets the following day.
+        ledger.advance_clock(Timestamp(Timestamp::SECONDS_PER_DAY));
+        ledger.update(withdrawal(5, 40)).unwrap();
+
+        let Account { available, .. } = ledger
+            .clients
+            .get(&client_id)
+            .expect("client not found in ledger");
+        assert_eq!(available, &Amount::from(410));
+    }
+
+    #[test]
+    fn partial_disputes() {
+        let mut ledger = Ledger::default();
+        let client_id = ClientId(1);
+        let deposit_id = TransactionId(1);
+        let amount = |amount: i32| Some(Amount::from(amount));
+        let balances = |ledger: &Ledger| {
+            let account = ledger.account(client_id).unwrap();
+            (account.available, account.held)
+        };
+
+        let transactions = [
+            Transaction::Deposit(Deposit {
+                client: client_id,
+                tx: deposit_id,
+                amount: Amount::from(10),
+                wallet: WalletId::default(),
+            }),
+            Transaction::Dispute(Dispute {
+                client: client_id,
+                tx: deposit_id,
+                amount: amount(4),
+            }),
+            Transaction::Dispute(Dispute {
+                client: client_id,
+                tx: deposit_id,
+                amount: amount(2),
+            }),
+        ];
+        transactions
+            .into_iter()
+            .for_each(|transaction| ledger.update(transaction).unwrap());
+
+        // Assert that only the disputed portions are held.
+        assert_eq!(balances(&ledger), (Amount::from(4), Amount::from(6)));
+        assert_eq!(
+            ledger.disputes.get(&(client_id, deposit_id)),
+            Some(&Amount::from(6))
+        );
+
+        // Assert that a dispute beyond the remaining disputable balance fails.
+        assert_eq!(
+            ledger.update(Transaction::Dispute(Dispute {
+                client: client_id,
+                tx: deposit_id,
+                amount: amount(5),
+            })),
+            Err(TransactionError::DisputeExceedsBalance {
+                client_id,
+                transaction_id: deposit_id,
+                requested: Amount::from(5),
+                disputable: Amount::from(4),
+            })
+        );
+
+        // Assert that a partial resolve releases only that portion, which is disputable again.
+        ledger
+            .update(Transaction::Resolve(Resolve {
+                client: client_id,
+                tx: deposit_id,
+                amount: amount(1),
+            }))
+            .unwrap();
+        assert_eq!(balances(&ledger), (Amount::from(5), Amount::from(5)));
+        assert_eq!(
+            ledger.deposits.get(&(client_id, deposit_id)),
+            Some(&Amount::from(5))
+        );
+
+        // Assert that a chargeback beyond the amount under dispute fails.
+        assert_eq!(
+            ledger.update(Transaction::Chargeback(Chargeback {
+                client: client_id,
+                tx: deposit_id,
+                amount: amount(6),
+            })),
+            Err(TransactionError::ExceedsDisputedAmount {
+                client_id,
+                transaction_id: deposit_id,
+                requested: Amount::from(6),
+                disputed: Amount::from(5),
+            })
+        );
+
+        // Assert that a partial chargeback reverses only that portion, leaving the rest disputed.
+        ledger
+            .update(Transaction::Chargeback(Chargeback {
+                client: client_id,
+                tx: deposit_id,
+                amount: amount(3),
+            }))
+            .unwrap();
+        assert_eq!(balances(&ledger), (Amount::from(5), Amount::from(2)));
+        assert_eq!(
+            ledger.disputes.get(&(client_id, deposit_id)),
+            Some(&Amount::from(2))
+        );
+        assert!(ledger.account(client_id).unwrap().locked);
+    }
+
+    /// A ledger in which 4 of a client's deposit of 10 is disputed.
+    fn partially_disputed() -> Ledger {
+        let mut ledger = Ledger::default();
+        ledger
+            .update(Transaction::Deposit(Deposit {
+                client: ClientId(1),
+                tx: TransactionId(1),
+                amount: Amount::from(10),
+                wallet: WalletId::default(),
+            }))
+            .unwrap();
+        ledger
+            .update(Transaction::Dispute(Dispute {
+                client: ClientId(1),
+                tx: TransactionId(1),
+                amount: Some(Amount::from(4)),
+            }))
+            .unwrap();
+        ledger
+    }
+
+    /// Assert that a transaction with each of the given amounts is rejected, leaving the partially
+    /// disputed ledger unchanged.
+    fn assert_non_positive_rejected(transaction: impl Fn(Option<Amount>) -> Transaction) {
+        let mut ledger = partially_disputed();
+        let client_id = ClientId(1);
+        for requested in [Amount::from(-3), Amount::from(0)] {
+            assert_eq!(
+                ledger.update(transaction(Some(requested))),
+                Err(TransactionError::NonPositiveAmount {
+                    client_id,
+                    requested,
+                })
+            );
+        }
+        let account = ledger.account(client_id).unwrap();
+        assert_eq!(
+            (account.available, account.held, account.locked),
+            (Amount::from(6), Amount::from(4), false)
+        );
+        assert_eq!(
+            ledger.disputes.get(&(client_id, TransactionId(1))),
+            Some(&Amount::from(4))
+        );
+        assert!(ledger.chargebacks.is_empty());
+    }
+
+    #[test]
+    fn dispute_non_positive() {
+        assert_non_positive_rejected(|amount| {
+            Transaction::Dispute(Dispute {
+                client: ClientId(1),
+                tx: TransactionId(1),
+                amount,
+            })
+        });
+    }
+
+    #[test]
+    fn resolve_non_positive() {
+        assert_non_positive_rejected(|amount| {
+            Transaction::Resolve(Resolve {
+                client: ClientId(1),
+                tx: TransactionId(1),
+                amount,
+            })
+        });
+    }
+
+    #[test]
+    fn chargeback_non_positive() {
+        assert_non_positive_rejected(|amount| {
+            Transaction::Chargeback(Chargeback {
+                client: ClientId(1),
+                tx: TransactionId(1),
+                amount,
+            })
+        });
+    }
+
+    #[test]
+    fn refund() {
+        let mut ledger = Ledger::default();
+        let client_id = ClientId(1);
+        let deposit_id = TransactionId(1);
+        let refund = |amount: Option<i32>| {
+            Transaction::Refund(Refund {
+                client: client_id,
+                tx: deposit_id,
+                amount: amount.map(Amount::from),
+            })
+        };
+
+        let transactions = [
+            Transaction::Deposit(Deposit {
+                client: client_id,
+                tx: deposit_id,
+                amount: Amount::from(10),
+                wallet: WalletId::default(),
+            }),
+            Transaction::Dispute(Dispute {
+                client: client_id,
+                tx: deposit_id,
+                amount: Some(Amount::from(3)),
+            }),
+        ];
+        transactions
+            .into_iter()
+            .for_each(|transaction| ledger.update(transaction).unwrap());
+
+        // Assert that funds under dispute cannot be refunded.
+        assert_eq!(
+            ledger.update(refund(Some(8))),
+            Err(TransactionError::RefundExceedsDeposit {
+                client_id,
+                transaction_id: deposit_id,
+                requested: Amount::from(8),
+                refundable: Amount::from(7),
+            })
+        );
+
+        // Assert that a refund of a negative amount fails rather than crediting funds.
+        assert_eq!(
+            ledger.update(refund(Some(-5))),
+            Err(TransactionError::NonPositiveAmount {
+                client_id,
+                requested: Amount::from(-5),
+            })
+        );
+
+        // Assert that a partial refund debits available funds and is no longer disputable.
+        ledger.update(refund(Some(5))).unwrap();
+        let account = ledger.account(client_id).unwrap();
+        assert_eq!(
+            (account.available, account.held),
+            (Amount::from(2), Amount::from(3))
+        );
+        assert_eq!(
+            ledger.update(Transaction::Dispute(Dispute {
+                client: client_id,
+                tx: deposit_id,
+                amount: Some(Amount::from(3)),
+            })),
+            Err(TransactionError::DisputeExceedsBalance {
+                client_id,
+                transaction_id: deposit_id,
+                requested: Amount::from(3),
+                disputable: Amount::from(2),
+            })
+        );
+
+        // Assert that the remainder is refunded by default, after which nothing is refundable.
+        ledger.update(refund(None)).unwrap();
+        assert_eq!(
+            ledger.account(client_id).unwrap().available,
+            Amount::from(0)
+        );
+        assert_eq!(
+            ledger.update(refund(None)),
+            Err(TransactionError::RefundExceedsDeposit {
+                client_id,
+                transaction_id: deposit_id,
+                requested: Amount::from(0),
+                refundable: Amount::from(0),
+            })
+        );
+        assert_eq!(
+            ledger.update(Transaction::Refund(Refund {
+                client: client_id,
+                tx: TransactionId(2),
+                amount: None,
+            })),
+            Err(TransactionError::RefundFailed {
+                client_id,
+                transaction_id: TransactionId(2),
+            })
+        );
+    }
+
+    #[test]
+    fn overdraft() {
+        let client_id = ClientId(1);
+        let mut ledger = Ledger::new(LedgerConfig {
+            credit_limits: HashMap::from([(client_id, Amount::from(50))]),
+            ..LedgerConfig::default()
+        });
+        let withdrawal = |tx, amount| {
+            Transaction::Withdrawal(Withdrawal {
+                client: client_id,
+                tx: TransactionId(tx),
+                amount: Amount::from(amount),
+                wallet: WalletId::default(),
+            })
+        };
+
+        ledger
+            .update(Transaction::Deposit(Deposit {
+                client: client_id,
+                tx: TransactionId(1),
+                amount: Amount::from(20),
+                wallet: WalletId::default(),
+            }))
+            .unwrap();
+
+        // Assert that the client may withdraw into their overdraft, but not beyond it.
+        ledger.update(withdrawal(2, 60)).unwrap();
+        assert_eq!(
+            ledger.update(withdrawal(3, 11)),
+            Err(TransactionError::WithdrawalInsufficientFunds {
+                client_id,
+                available: Amount::from(-40),
+                requested: Amount::from(11),
+            })
+        );
+
+        // Assert that the credit limit can be raised by an admin transaction.
+        ledger
+            .update(Transaction::CreditLimit(CreditLimit {
+                client: client_id,
+                tx: TransactionId(4),
+                amount: Amount::from(100),
+            }))
+            .unwrap();
+        ledger.update(withdrawal(5, 11)).unwrap();
+
+        let entry = ledger.entries().next().unwrap();
+        assert_eq!(entry.available, Amount::from(-51));
+        assert_eq!(entry.credit_limit, Some(Amount::from(100)));
+        assert_eq!(entry.used_credit, Some(Amount::from(51)));
+
+        assert_eq!(
+            ledger.update(Transaction::CreditLimit(CreditLimit {
+                client: client_id,
+                tx: TransactionId(6),
+                amount: Amount::from(-1),
+            })),
+            Err(TransactionError::NegativeCreditLimit {
+                client_id,
+                requested: Amount::from(-1),
+            })
+        );
+    }
+
+    #[test]
+    fn interest() {
+        const DAY: u64 = Timestamp::SECONDS_PER_DAY;
+        let mut ledger = Ledger::new(LedgerConfig::default());
+        let mut rates = InterestRates::default();
+        rates.insert(Amount::from(0), Decimal::from(365));
+        let deposit = |client, tx, amount| {
+            Transaction::Deposit(Deposit {
+                client: ClientId(client),
+                tx: TransactionId(tx),
+                amount: Amount::from(amount),
+                wallet: WalletId::default(),
+            })
+        };
+
+        ledger.update(deposit(1, 1, 100)).unwrap();
+        ledger.update(deposit(2, 2, 50)).unwrap();
+        ledger
+            .update(Transaction::Withdrawal(Withdrawal {
+                client: ClientId(2),
+                tx: TransactionId(3),
+                amount: Amount::from(50),
+                wallet: WalletId::default(),
+            }))
+            .unwrap();
+        ledger.advance_clock(Timestamp(2 * DAY));
+        ledger.update(deposit(1, 4, 100)).unwrap();
+
+        // Assert that 100 for two days then 200 for two days earns 6 at 1% a day, paid by the
+        // house, while the client without funds earns nothing.
+        let credited = ledger
+            .accrue_interest(&rates, Timestamp(0)..Timestamp(4 * DAY), TransactionId(5))
+            .unwrap();
+        assert_eq!(
+            credited,
+            [Interest {
+                client: ClientId(1),
+                tx: TransactionId(5),
+                amount: Amount::from(6),
+            }]
+        );
+        assert_eq!(
+            ledger.account(ClientId(1)).unwrap().available,
+            Amount::from(206)
+        );
+        assert_eq!(ledger.revenue(), Amount::from(-6));
+
+        assert_eq!(
+            ledger.accrue_interest(
+                &rates,
+                Timestamp(3 * DAY)..Timestamp(5 * DAY),
+                TransactionId(6)
+            ),
+            Err(InterestError::PeriodAccrued {
+                accrued_until: Timestamp(4 * DAY)
+            })
+        );
+        assert_eq!(
+            ledger.accrue_interest(
+                &rates,
+                Timestamp(4 * DAY)..Timestamp(4 * DAY),
+                TransactionId(6)
+            ),
+            Err(InterestError::EmptyPeriod)
+        );
+
+        // Assert that the interest credited earns interest in the next period.
+        let credited = ledger
+            .accrue_interest(
+                &rates,
+                Timestamp(4 * DAY)..Timestamp(5 * DAY),
+                TransactionId(6),
+            )
+            .unwrap();
+        assert_eq!(credited[0].amount, Amount::from(Decimal::new(206, 2)));
+
+        // Assert that an interest row of a negative or zero amount fails rather than debiting.
+        for amount in [-500, 0] {
+            assert_eq!(
+                ledger.update(Transaction::Interest(Interest {
+                    client: ClientId(1),
+                    tx: TransactionId(7),
+                    amount: Amount::from(amount),
+                })),
+                Err(TransactionError::NonPositiveAmount {
+                    client_id: ClientId(1),
+                    requested: Amount::from(amount),
+                })
+            );
+        }
+    }
+
+    #[test]
+    fn interest_overflow() {
+        const DAY: u64 = Timestamp::SECONDS_PER_DAY;
+        let client_id = ClientId(1);
+        let mut ledger = Ledger::new(LedgerConfig::default());
+        ledger
+            .update(Transaction::Deposit(Deposit {
+                client: client_id,
+                tx: TransactionId(1),
+                amount: Amount::from(Decimal::MAX),
+                wallet: WalletId::default(),
+            }))
+            .unwrap();
+
+        // Assert that interest which would overflow the client's funds, or which is too large to
+        // accrue at all, is not credited and leaves the residue carried forward unchanged.
+        for (rate, day) in [(Decimal::new(1, 10), 0), (Decimal::TWO, 1)] {
+            let mut rates = InterestRates::default();
+            rates.insert(Amount::from(0), rate);
+            let period = Timestamp(day * DAY)..Timestamp((day + 1) * DAY);
+            assert_eq!(
+                ledger.accrue_interest(&rates, period, TransactionId(2)),
+                Ok(vec![])
+            );
+            let account = ledger.account(client_id).unwrap();
+            assert_eq!(account.available, Amount::from(Decimal::MAX));
+            assert_eq!(account.accrual.residue(), Decimal::ZERO);
+        }
+        assert_eq!(ledger.revenue(), Amount::from(0));
+    }
+
+    #[test]
+    fn rolling_reserve() {
+        let client_id = ClientId(1);
+        let mut ledger = Ledger::new(LedgerConfig {
+            reserves: HashMap::from([(
+                client_id,
+                ReservePolicy {
+                    percent: Decimal::TEN,
+                    days: 2,
+                },
+            )]),
+            ..LedgerConfig::default()
+        });
+        let deposit = |tx, amount| {
+            Transaction::Deposit(Deposit {
+                client: client_id,
+                tx: TransactionId(tx),
+                amount: Amount::from(amount),
+                wallet: WalletId::default(),
+            })
+        };
+        let balances = |ledger: &Ledger| {
+            let account = ledger.account(client_id).unwrap();
+            (account.available, account.reserve.balance())
+        };
+
+        // Assert that part of a deposit is withheld, but still counts towards the total.
+        ledger.update(deposit(1, 100)).unwrap();
+        assert_eq!(balances(&ledger), (Amount::from(90), Amount::from(10)));
+        let entry = ledger.entries().next().unwrap();
+        assert_eq!(entry.total, Amount::from(100));
+        assert_eq!(entry.reserve, Some(Amount::from(10)));
+
+        // Assert that the reserve is released once the ledger's clock reaches its release.
+        ledger.advance_clock(Timestamp(2 * Timestamp::SECONDS_PER_DAY - 1));
+        assert_eq!(balances(&ledger), (Amount::from(90), Amount::from(10)));
+        ledger.advance_clock(Timestamp(2 * Timestamp::SECONDS_PER_DAY));
+        assert_eq!(balances(&ledger), (Amount::from(100), Amount::from(0)));
+
+        // Assert that reversing a deposit takes back what remains withheld from it.
+        ledger.update(deposit(2, 20)).unwrap();
+        assert_eq!(balances(&ledger), (Amount::from(118), Amount::from(2)));
+        ledger
+            .update(Transaction::Reversal(Reversal {
+                client: client_id,
+                tx: TransactionId(2),
+            }))
+            .unwrap();
+        assert_eq!(balances(&ledger), (Amount::from(100), Amount::from(0)));
+
+        // Assert that the reserve covers a chargeback which leaves available funds negative.
+        ledger.update(deposit(3, 50)).unwrap();
+        ledger
+            .update(Transaction::Withdrawal(Withdrawal {
+                client: client_id,
+                tx: TransactionId(4),
+                amount: Amount::from(145),
+                wallet: WalletId::default(),
+            }))
+            .unwrap();
+        for transaction in [
+            Transaction::Dispute(Dispute {
+                client: client_id,
+                tx: TransactionId(3),
+                amount: None,
+            }),
+            Transaction::Chargeback(Chargeback {
+                client: client_id,
+                tx: TransactionId(3),
+                amount: None,
+            }),
+        ] {
+            ledger.update(transaction).unwrap();
+        }
+        assert_eq!(balances(&ledger), (Amount::from(-45), Amount::from(0)));
+    }
+
+    #[test]
+    fn pending_deposits() {
+        let client_id = ClientId(1);
+        let mut ledger = Ledger::new(LedgerConfig {
+            pending_deposits: true,
+            settlement_delay: Some(1),
+            ..LedgerConfig::default()
+        });
+        let deposit = |tx, amount| {
+            Transaction::Deposit(Deposit {
+                client: client_id,
+                tx: TransactionId(tx),
+                amount: Amount::from(amount),
+                wallet: WalletId::default(),
+            })
+        };
+        let settle = |tx| {
+            Transaction::Settle(Settle {
+                client: client_id,
+                tx: TransactionId(tx),
+            })
+        };
+        let balances = |ledger: &Ledger| {
+            let account = ledger.account(client_id).unwrap();
+            (account.available, account.pending)
+        };
+
+        // Assert that deposited funds are pending, and cannot be withdrawn or disputed.
+        ledger.update(deposit(1, 10)).unwrap();
+        ledger.update(deposit(2, 5)).unwrap();
+        assert_eq!(balances(&ledger), (Amount::from(0), Amount::from(15)));
+        let entry = ledger.entries().next().unwrap();
+        assert_eq!(entry.total, Amount::from(15));
+        assert_eq!(entry.pending, Some(Amount::from(15)));
+        assert_eq!(
+            ledger.update(Transaction::Withdrawal(Withdrawal {
+                client: client_id,
+                tx: TransactionId(3),
+                amount: Amount::from(1),
+                wallet: WalletId::default(),
+            })),
+            Err(TransactionError::WithdrawalInsufficientFunds {
+                client_id,
+                available: Amount::from(0),
+                requested: Amount::from(1),
+            })
+        );
+        assert_eq!(
+            ledger.update(Transaction::Dispute(Dispute {
+                client: client_id,
+                tx: TransactionId(1),
+                amount: None,
+            })),
+            Err(TransactionError::DepositPending {
+                client_id,
+                transaction_id: TransactionId(1),
+            })
+        );
+
+        // Assert that a deposit settles explicitly, but only once.
+        ledger.update(settle(1)).unwrap();
+        assert_eq!(balances(&ledger), (Amount::from(10), Amount::from(5)));
+        assert_eq!(
+            ledger.update(settle(1)),
+            Err(TransactionError::SettleFailed {
+                client_id,
+                transaction_id: TransactionId(1),
+            })
+        );
+
+        // Assert that the remaining deposit settles once the settlement delay has passed.
+        ledger.advance_clock(Timestamp(Timestamp::SECONDS_PER_DAY));
+        assert_eq!(balances(&ledger), (Amount::from(15), Amount::from(0)));
+    }
+
+    #[test]
+    fn compliance_holds() {
+        let client_id = ClientId(1);
+        let mut ledger = Ledger::default();
+        let hold = |tx, amount, reason| {
+            Transaction::Hold(Hold {
+                client: client_id,
+                tx: TransactionId(tx),
+                amount: Amount::from(amount),
+                reason,
+            })
+        };
+        let release = |tx, amount: Option<i32>| {
+            Transaction::Release(Release {
+                client: client_id,
+                tx: TransactionId(tx),
+                amount: amount.map(Amount::from),
+            })
+        };
+
+        for transaction in [
+            Transaction::Deposit(Deposit {
+                client: client_id,
+                tx: TransactionId(1),
+                amount: Amount::from(100),
+                wallet: WalletId::default(),
+            }),
+            Transaction::Dispute(Dispute {
+                client: client_id,
+                tx: TransactionId(1),
+                amount: Some(Amount::from(10)),
+            }),
+            Transaction::Authorize(Authorize {
+                client: client_id,
+                tx: TransactionId(2),
+                amount: Amount::from(20),
+            }),
+        ] {
+            ledger.update(transaction).unwrap();
+        }
+
+        // Assert that held funds are only broken down once a compliance hold has been placed.
+        assert_eq!(ledger.entries().next().unwrap().held_disputes, None);
+        ledger.update(hold(3, 30, HoldReason::Aml)).unwrap();
+        ledger.update(hold(4, 5, HoldReason::Legal)).unwrap();
+
+        let entry = ledger.entries().next().unwrap();
+        assert_eq!(
+            (entry.available, entry.held, entry.total),
+            (Amount::from(35), Amount::from(65), Amount::from(100))
+        );
+        assert_eq!(entry.held_disputes, Some(Amount::from(10)));
+        assert_eq!(entry.held_authorizations, Some(Amount::from(20)));
+        assert_eq!(entry.held_aml, Some(Amount::from(30)));
+        assert_eq!(entry.held_fraud, Some(Amount::from(0)));
+        assert_eq!(entry.held_legal, Some(Amount::from(5)));
+
+        assert_eq!(
+            ledger.update(hold(5, 36, HoldReason::Fraud)),
+            Err(TransactionError::HoldInsufficientFunds {
+                client_id,
+                available: Amount::from(35),
+                requested: Amount::from(36),
+            })
+        );
+        assert_eq!(
+            ledger.update(hold(3, 1, HoldReason::Aml)),
+            Err(TransactionError::HoldDuplicate {
+                client_id,
+                transaction_id: TransactionId(3),
+            })
+        );
+
+        // Assert that holds and releases of negative or zero amounts fail.
+        for amount in [-50, 0] {
+            let rejected = Err(TransactionError::NonPositiveAmount {
+                client_id,
+                requested: Amount::from(amount),
+            });
+            assert_eq!(ledger.update(hold(5, amount, HoldReason::Fraud)), rejected);
+            assert_eq!(ledger.update(release(3, Some(amount))), rejected);
+        }
+
+        // Assert that a hold can be released in parts, but not beyond the amount held.
+        ledger.update(release(3, Some(10))).unwrap();
+        assert_eq!(
+            ledger.update(release(3, Some(21))),
+            Err(TransactionError::ReleaseExceedsHold {
+                client_id,
+                transaction_id: TransactionId(3),
+                requested: Amount::from(21),
+                held: Amount::from(20),
+            })
+        );
+        ledger.update(release(3, None)).unwrap();
+        assert_eq!(
+            ledger.update(release(3, None)),
+            Err(TransactionError::ReleaseFailed {
+                client_id,
+                transaction_id: TransactionId(3),
+            })
+        );
+
+        let entry = ledger.entries().next().unwrap();
+        assert_eq!(entry.available, Amount::from(65));
+        assert_eq!(entry.held_aml, Some(Amount::from(0)));
+    }
+
+    #[test]
+    fn close() {
+        let client_id = ClientId(1);
+        let mut fees = FeeSchedule::default();
+        fees.insert(
+            TransactionType::Close,
+            Fee {
+                flat: Amount::from(1),
+                percent: Decimal::ZERO,
+            },
+        );
+        let mut ledger = Ledger::new(LedgerConfig {
+            fees,
+            ..LedgerConfig::default()
+        });
+        let close = Transaction::Close(Close {
+            client: client_id,
+            tx: TransactionId(9),
+        });
+        let deposit = |tx| {
+            Transaction::Deposit(Deposit {
+                client: client_id,
+                tx: TransactionId(tx),
+                amount: Amount::from(100),
+                wallet: WalletId::default(),
+            })
+        };
+
+        // Assert that an account with open disputes or holds cannot be closed.
+        ledger.update(deposit(1)).unwrap();
+        ledger
+            .update(Transaction::Dispute(Dispute {
+                client: client_id,
+                tx: TransactionId(1),
+                amount: Some(Amount::from(10)),
+            }))
+            .unwrap();
+        assert_eq!(
+            ledger.update(close),
+            Err(TransactionError::CloseFailed {
+                client_id,
+                reason: "it has open disputes",
+            })
+        );
+        ledger
+            .update(Transaction::Resolve(Resolve {
+                client: client_id,
+                tx: TransactionId(1),
+                amount: None,
+            }))
+            .unwrap();
+        ledger
+            .update(Transaction::Hold(Hold {
+                client: client_id,
+                tx: TransactionId(2),
+                amount: Amount::from(5),
+                reason: HoldReason::Legal,
+            }))
+            .unwrap();
+        assert_eq!(
+            ledger.update(close),
+            Err(TransactionError::CloseFailed {
+                client_id,
+                reason: "it has funds held",
+            })
+        );
+        ledger
+            .update(Transaction::Release(Release {
+                client: client_id,
+                tx: TransactionId(2),
+                amount: None,
+            }))
+            .unwrap();
+
+        // Assert that closing pays out everything but the closing fee, and that the closed
+        // account rejects every later transaction.
+        ledger.update(close).unwrap();
+        let entry = ledger.entries().next().unwrap();
+        assert_eq!(entry.available, Amount::from(0));
+        assert_eq!(entry.fees, Some(Amount::from(1)));
+        assert_eq!(entry.closed, Some(true));
+        assert_eq!(
+            ledger.update(deposit(3)),
+            Err(TransactionError::ClientAccountClosed { client_id })
+        );
+    }
+
+    #[test]
+    fn client_master() {
+        let client_id = ClientId(1);
+        let mut clients = ClientMaster::default();
+        clients.insert(
+            client_id,
+            ClientRecord {
+                tier: Some("restricted".to_string()),
+                risk_rating: Some(RiskRating::High),
+                credit_limit: Some(Amount::from(10)),
+                country: Some("GB".to_string()),
+                ..ClientRecord::default()
+            },
+        );
+        let mut config = LedgerConfig {
+            client_columns: vec![ClientField::Country, ClientField::RiskRating],
+            ..LedgerConfig::default()
+        };
+        config.limits.insert_tier(
+            "restricted",
+            Limits {
+                per_transaction: Some(Amount::from(8)),
+                ..Limits::default()
+            },
+        );
+        config.rules.add(Action::Flag, Box::new(HighRiskWithdrawal));
+        config.load_clients(clients);
+        let mut ledger = Ledger::new(config);
+        let withdrawal = |client, tx, amount| {
+            Transaction::Withdrawal(Withdrawal {
+                client: ClientId(client),
+                tx: TransactionId(tx),
+                amount: Amount::from(amount),
+                wallet: WalletId::default(),
+            })
+        };
+
+        for client in [1, 2] {
+            ledger
+                .update(Transaction::Deposit(Deposit {
+                    client: ClientId(client),
+                    tx: TransactionId(u32::from(client)),
+                    amount: Amount::from(5),
+                    wallet: WalletId::default(),
+                }))
+                .unwrap();
+        }
+
+        // Assert that the client's tier limits withdrawals, their credit limit allows an overdraft,
+        // and the rules see their risk rating.
+        assert!(matches!(
+            ledger.update(withdrawal(1, 3, 9)),
+            Err(TransactionError::LimitExceeded { .. })
+        ));
+        ledger.update(withdrawal(1, 4, 8)).unwrap();
+        assert_eq!(ledger.take_alerts().len(), 1);
+        ledger.update(withdrawal(2, 5, 1)).unwrap();
+        assert!(ledger.take_alerts().is_empty());
+
+        let mut entries = ledger.entries().collect::<Vec<_>>();
+        entries.sort_by_key(|entry| entry.client);
+        assert_eq!(entries[0].available, Amount::from(-3));
+        assert_eq!(entries[0].country.as_deref(), Some("GB"));
+        assert_eq!(entries[0].risk_rating.as_deref(), Some("high"));
+        assert_eq!(entries[0].tier, None);
+        assert_eq!(entries[1].country.as_deref(), Some(""));
+    }
+
+    #[test]
+    fn wallets() {
+        let client_id = ClientId(1);
+        let (spending, savings) = (WalletId::default(), WalletId(1));
+        let mut config = LedgerConfig::default();
+        config.credit_limits.insert(client_id, Amount::from(5));
+        let mut ledger = Ledger::new(config);
+        let deposit = |tx, amount, wallet| {
+            Transaction::Deposit(Deposit {
+                client: client_id,
+                tx: TransactionId(tx),
+                amount: Amount::from(amount),
+                wallet,
+            })
+        };
+        let withdrawal = |tx, amount, wallet| {
+            Transaction::Withdrawal(Withdrawal {
+                client: client_id,
+                tx: TransactionId(tx),
+                amount: Amount::from(amount),
+                wallet,
+            })
+        };
+        let transfer = |tx, amount, wallet, to_wallet| {
+            Transaction::Transfer(Transfer {
+                client: client_id,
+                tx: TransactionId(tx),
+                amount: Amount::from(amount),
+                wallet,
+                to_wallet,
+            })
+        };
+        let funds = |ledger: &Ledger, wallet| {
+            let wallet = ledger.account(client_id).unwrap().wallet(wallet);
+            (wallet.available, wallet.held)
+        };
+
+        ledger.update(deposit(1, 10, spending)).unwrap();
+        ledger.update(deposit(2, 20, savings)).unwrap();
+        ledger.update(transfer(3, 4, spending, savings)).unwrap();
+        assert_eq!(funds(&ledger, spending), (Amount::from(6), Amount::from(0)));
+        assert_eq!(funds(&ledger, savings), (Amount::from(24), Amount::from(0)));
+
+        // Assert that only the default wallet may be overdrawn, and transfers may not overdraw it.
+        assert!(matches!(
+            ledger.update(withdrawal(4, 25, savings)),
+            Err(TransactionError::WithdrawalInsufficientFunds { .. })
+        ));
+        assert!(matches!(
+            ledger.update(transfer(5, 7, spending, savings)),
+            Err(TransactionError::TransferInsufficientFunds { .. })
+        ));
+        assert!(matches!(
+            ledger.update(transfer(5, -7, savings, spending)),
+            Err(TransactionError::NonPositiveAmount { .. })
+        ));
+        ledger.update(withdrawal(6, 10, spending)).unwrap();
+        assert_eq!(
+            funds(&ledger, spending),
+            (Amount::from(-4), Amount::from(0))
+        );
+
+        // Assert that a dispute holds funds in the wallet the deposit was made to.
+        ledger
+            .update(Transaction::Dispute(Dispute {
+                client: client_id,
+                tx: TransactionId(2),
+                amount: None,
+            }))
+            .unwrap();
+        assert_eq!(funds(&ledger, savings), (Amount::from(4), Amount::from(20)));
+        ledger
+            .update(Transaction::Resolve(Resolve {
+                client: client_id,
+                tx: TransactionId(2),
+                amount: Some(Amount::from(5)),
+            }))
+            .unwrap();
+        assert_eq!(funds(&ledger, savings), (Amount::from(9), Amount::from(15)));
+        assert_eq!(
+            funds(&ledger, spending),
+            (Amount::from(-4), Amount::from(0))
+        );
+
+        // Assert that reversing a withdrawal returns the funds to its wallet.
+        ledger
+            .update(Transaction::Reversal(Reversal {
+                client: client_id,
+                tx: TransactionId(6),
+            }))
+            .unwrap();
+        assert_eq!(funds(&ledger, spending), (Amount::from(6), Amount::from(0)));
+
+        let entries = ledger.wallet_entries().collect::<Vec<_>>();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].wallet, Some(spending));
+        assert_eq!(entries[0].total, Amount::from(6));
+        assert_eq!(entries[1].wallet, Some(savings));
+        assert_eq!(entries[1].total, Amount::from(24));
+        let entry = ledger.entries().next().unwrap();
+        assert_eq!(entry.wallet, None);
+        assert_eq!(entry.total, Amount::from(30));
+    }
+
+    #[test]
+    fn wallet_reserves() {
+        let client_id = ClientId(1);
+        let savings = WalletId(1);
+        let mut ledger = Ledger::new(LedgerConfig {
+            reserves: HashMap::from([(
+                client_id,
+                ReservePolicy {
+                    percent: Decimal::TEN,
+                    days: 2,
+                },
+            )]),
+            ..LedgerConfig::default()
+        });
+        let deposit = |tx, amount| {
+            Transaction::Deposit(Deposit {
+                client: client_id,
+                tx: TransactionId(tx),
+                amount: Amount::from(amount),
+                wallet: savings,
+            })
+        };
+        let funds = |ledger: &Ledger, wallet| {
+            let account = ledger.account(client_id).unwrap();
+            account.wallet(wallet).available
         };
 
-        // Update the client's account
-        self.clients
-            .get_mut(&client)
-            .ok_or(TransactionError::ClientDoesNotExist { client_id: client })?
-            .resolve(*amount)?;
+        // Assert that the reserve withheld from a deposit counts towards the wallet's total.
+        ledger.update(deposit(1, 100)).unwrap();
+        assert_eq!(funds(&ledger, savings), Amount::from(90));
+        let entries = ledger.wallet_entries().collect::<Vec<_>>();
+        assert_eq!(
+            (entries[1].wallet, entries[1].total),
+            (Some(savings), Amount::from(100))
+        );
 
-        // Clear the dispute
-        self.disputes.remove(&(client, tx));
-        Ok(())
+        // Assert that the reserve is released back to the wallet it was withheld from.
+        ledger.advance_clock(Timestamp(2 * Timestamp::SECONDS_PER_DAY));
+        assert_eq!(funds(&ledger, savings), Amount::from(100));
+        assert_eq!(funds(&ledger, WalletId::default()), Amount::from(0));
+
+        // Assert that a chargeback covers the shortfall it leaves from the reserve, returning the
+        // reserve to the wallet it was withheld from.
+        ledger.update(deposit(2, 50)).unwrap();
+        ledger
+            .update(Transaction::Withdrawal(Withdrawal {
+                client: client_id,
+                tx: TransactionId(3),
+                amount: Amount::from(145),
+                wallet: savings,
+            }))
+            .unwrap();
+        for transaction in [
+            Transaction::Dispute(Dispute {
+                client: client_id,
+                tx: TransactionId(2),
+                amount: None,
+            }),
+            Transaction::Chargeback(Chargeback {
+                client: client_id,
+                tx: TransactionId(2),
+                amount: None,
+            }),
+        ] {
+            ledger.update(transaction).unwrap();
+        }
+        let account = ledger.account(client_id).unwrap();
+        assert_eq!(account.available, Amount::from(-45));
+        assert_eq!(account.reserve.balance(), Amount::from(0));
+        assert_eq!(funds(&ledger, savings), Amount::from(-45));
+        assert_eq!(funds(&ledger, WalletId::default()), Amount::from(0));
     }
 
-    /// Chargeback a client's disputed deposit. Fails if the client does not exist.
-    fn chargeback(
-        &mut self,
-        Chargeback { client, tx }: Chargeback,
-    ) -> Result<(), TransactionError> {
-        // Confirm a dispute exists
-        self.disputes
-            .get(&(client, tx))
-            .ok_or(TransactionError::ChargebackFailed {
-                client_id: client,
-                transaction_id: tx,
-            })?;
+    #[test]
+    fn reorder_window() {
+        let mut ledger = Ledger::new(LedgerConfig {
+            reorder_window: Some(2),
+            ..LedgerConfig::default()
+        });
+        let rdr = "type, client, tx, amount\n\
+                   dispute, 1, 1,\n\
+                   resolve, 1, 1, 2\n\
+                   deposit, 1, 1, 10\n\
+                   dispute, 2, 5,\n\
+                   deposit, 2, 6, 1\n\
+                   deposit, 2, 7, 1\n\
+                   deposit, 2, 5, 3\n\
+                   chargeback, 3, 9,\n"
+            .as_bytes();
 
-        // Find the transaction amount
-        let amount = match self.deposits.get(&(client, tx)) {
-            Some(deposit) => deposit,
-            None => {
-                return Err(TransactionError::ChargebackFailed {
-                    client_id: client,
-                    transaction_id: tx,
-                })
+        let mut rejected = Vec::new();
+        let summary = ledger.load_with(read_input(rdr), |report| {
+            if let Report::Reject(reject) = report {
+                rejected.push(reject.position.line)
+            }
+        });
+
+        // Assert that the dispute and resolve arriving early are applied once their deposit
+        // arrives, while the dispute whose deposit arrives too late expires, and the chargeback
+        // still parked at the end of the input is rejected.
+        assert_eq!(
+            summary,
+            LoadSummary {
+                applied: 6,
+                rejected: 2
             }
+        );
+        assert_eq!(rejected, [5, 9]);
+        let account = ledger.account(ClientId(1)).unwrap();
+        assert_eq!(
+            (account.available, account.held),
+            (Amount::from(2), Amount::from(8))
+        );
+        let account = ledger.account(ClientId(2)).unwrap();
+        assert_eq!(
+            (account.available, account.held),
+            (Amount::from(5), Amount::from(0))
+        );
+        assert!(ledger.parked.is_empty() && ledger.waiting.is_empty());
+    }
+
+    #[test]
+    fn next_unparked() {
+        let mut ledger = Ledger::new(LedgerConfig {
+            reorder_window: Some(2),
+            ..LedgerConfig::default()
+        });
+        let mut rows = read_input(
+            "type, client, tx, amount\n\
+             dispute, 1, 1, 4\n\
+             deposit, 1, 1, 10\n\
+             withdrawal, 1, 2, 1\n"
+                .as_bytes(),
+        );
+        let client_id = ClientId(1);
+        let balances = |ledger: &Ledger| {
+            let account = ledger.account(client_id).unwrap();
+            (account.available, account.held)
         };
 
-        // Update the client's account
-        self.clients
-            .get_mut(&client)
-            .ok_or(TransactionError::ClientDoesNotExist { client_id: client })?
-            .chargeback(*amount)?;
+        assert!(matches!(
+            ledger.apply(rows.next().unwrap()),
+            Ok(Applied::Parked)
+        ));
+        assert!(matches!(
+            ledger.apply(rows.next().unwrap()),
+            Ok(Applied::Applied)
+        ));
 
-        // Clear the dispute
-        self.disputes.remove(&(client, tx));
-        Ok(())
+        // Assert that the dispute is only retried once it is taken, after the deposit.
+        assert_eq!(balances(&ledger), (Amount::from(10), Amount::from(0)));
+        let unparked = ledger.next_unparked().unwrap();
+        assert_eq!(
+            unparked.transaction.transaction_type(),
+            TransactionType::Dispute
+        );
+        assert!(matches!(unparked.result, Ok(Applied::Applied)));
+        assert_eq!(balances(&ledger), (Amount::from(6), Amount::from(4)));
+        assert!(ledger.next_unparked().is_none());
+
+        // Assert that a row which is not taken is retried before the next row is applied.
+        let mut ledger = Ledger::new(LedgerConfig {
+            reorder_window: Some(2),
+            ..LedgerConfig::default()
+        });
+        for row in read_input(
+            "type, client, tx, amount\n\
+             dispute, 1, 1, 4\n\
+             deposit, 1, 1, 10\n\
+             withdrawal, 1, 2, 7\n"
+                .as_bytes(),
+        ) {
+            ledger.apply(row).ok();
+        }
+        assert_eq!(balances(&ledger), (Amount::from(6), Amount::from(4)));
+        assert_eq!(ledger.take_unparked().len(), 1);
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::types::{Chargeback, Deposit, Dispute, Resolve, Withdrawal};
+    #[test]
+    fn rounding() {
+        let mut fees = FeeSchedule::default();
+        fees.insert(
+            TransactionType::Withdrawal,
+            Fee {
+                flat: Amount::from(0),
+                percent: Decimal::ONE,
+            },
+        );
+        let mut ledger = Ledger::new(LedgerConfig {
+            fees,
+            precision: Precision {
+                decimal_places: 2,
+                rounding: Rounding::HalfUp,
+            },
+            ..LedgerConfig::default()
+        });
+        let rdr = "type, client, tx, amount\n\
+                   deposit, 1, 1, 1.005\n\
+                   withdrawal, 1, 2, 0.5\n"
+            .as_bytes();
+
+        ledger.load(rdr);
+
+        // Assert that the deposit is rounded up to 1.01 and the fee of 0.005 to 0.01, with the
+        // 0.01 gained by rounding up held in the residue account.
+        let account = ledger.account(ClientId(1)).unwrap();
+        assert_eq!(account.available, Amount::from(Decimal::new(50, 2)));
+        assert_eq!(account.fees, Amount::from(Decimal::new(1, 2)));
+        assert_eq!(ledger.residue(), Amount::from(Decimal::new(-1, 2)));
+    }
 
     #[test]
-    fn deposit() {
+    fn amount_overflow() {
         let mut ledger = Ledger::default();
         let client_id = ClientId(1);
-        let transaction_id = TransactionId(1);
-        let amount = Amount(7.into());
-
-        let transaction = Transaction::Deposit(Deposit {
+        let deposit = |tx, amount| {
+            Transaction::Deposit(Deposit {
+                client: client_id,
+                tx: TransactionId(tx),
+                amount: Amount::from(amount),
+                wallet: WalletId::default(),
+            })
+        };
+        let interest = Transaction::Interest(Interest {
             client: client_id,
-            tx: transaction_id,
-            amount: amount.clone(),
+            tx: TransactionId(3),
+            amount: Amount::from(1),
         });
 
-        ledger.update(transaction).unwrap();
+        ledger.update(deposit(1, Decimal::MAX)).unwrap();
+        let overflow = Err(TransactionError::AmountOverflow { client_id });
 
-        // Assert client account has been created and the deposit added.
-        let Account {
-            available,
-            held,
-            locked,
-            ..
-        } = ledger
-            .clients
-            .get(&client_id)
-            .expect("client not found in ledger");
+        // Assert that credits beyond the largest amount are rejected without changing the account.
+        assert_eq!(ledger.update(deposit(2, Decimal::ONE)), overflow);
+        assert_eq!(ledger.update(interest), overflow);
+        let account = ledger.account(client_id).unwrap();
+        assert_eq!(account.available, Amount::from(Decimal::MAX));
+        assert_eq!(ledger.revenue(), Amount::from(0));
+        assert_eq!(ledger.entries().count(), 1);
+
+        // Assert that a negative withdrawal, which credits the account, is rejected in the same
+        // way.
+        let withdrawal = Transaction::Withdrawal(Withdrawal {
+            client: client_id,
+            tx: TransactionId(4),
+            amount: Amount::from(-1),
+            wallet: WalletId::default(),
+        });
+        assert_eq!(ledger.update(withdrawal), overflow);
+        let account = ledger.account(client_id).unwrap();
+        assert_eq!(account.available, Amount::from(Decimal::MAX));
+    }
+
+    #[test]
+    fn fee_overflow() {
+        let mut fees = FeeSchedule::default();
+        fees.insert(
+            TransactionType::Deposit,
+            Fee {
+                flat: Amount::from(0),
+                percent: Decimal::from_i128_with_scale(79_228_162_514_264_337_593_543_950_335, 1),
+            },
+        );
+        let mut ledger = Ledger::new(LedgerConfig {
+            fees,
+            ..LedgerConfig::default()
+        });
+        let client_id = ClientId(1);
+        let deposit = Transaction::Deposit(Deposit {
+            client: client_id,
+            tx: TransactionId(1),
+            amount: Amount::from(100),
+            wallet: WalletId::default(),
+        });
 
+        // Assert that a fee too large to represent rejects the transaction.
+        assert_eq!(ledger.fee(&deposit), None);
         assert_eq!(
-            (available, held, locked),
-            (&amount, &Amount(0.into()), &false)
+            ledger.update(deposit),
+            Err(TransactionError::AmountOverflow { client_id })
         );
+        assert!(ledger.account(client_id).is_none());
+        assert_eq!(ledger.revenue(), Amount::from(0));
+    }
 
-        // Assert that the transaction has been stored.
-        assert!(ledger.deposits.contains_key(&(client_id, transaction_id)));
+    const ROW_TYPES: [&str; 19] = [
+        "deposit",
+        "withdrawal",
+        "dispute",
+        "resolve",
+        "chargeback",
+        "refund",
+        "represent",
+        "arbitrate",
+        "authorize",
+        "capture",
+        "void",
+        "reversal",
+        "credit_limit",
+        "interest",
+        "settle",
+        "hold",
+        "release",
+        "close",
+        "transfer",
+    ];
 
-        // Assert that there are no disputes
-        assert!(ledger.disputes.is_empty());
+    /// Amounts at and beyond the limits of what the ledger can hold, along with the trivial ones.
+    fn extreme_amounts() -> Vec<String> {
+        vec![
+            Decimal::MAX.to_string(),
+            Decimal::MIN.to_string(),
+            Amount::MAX.0.to_string(),
+            (-Amount::MAX.0).to_string(),
+            "999999999999999.9999".to_string(),
+            "0.0000000000000000000000000001".to_string(),
+            "79228162514264337593543950336".to_string(),
+            "1e30".to_string(),
+            "-1".to_string(),
+            "0".to_string(),
+            "1".to_string(),
+            String::new(),
+        ]
+    }
+
+    /// Configurations between them enabling fees, credit, pending deposits and reserves.
+    fn load_configs() -> [LedgerConfig; 3] {
+        let mut fees = FeeSchedule::default();
+        fees.insert(
+            TransactionType::Withdrawal,
+            Fee {
+                flat: Amount::from(1),
+                percent: Decimal::ONE,
+            },
+        );
+        [
+            LedgerConfig::default(),
+            LedgerConfig {
+                fees,
+                credit_limits: HashMap::from([(ClientId(1), Amount::MAX)]),
+                ..LedgerConfig::default()
+            },
+            LedgerConfig {
+                pending_deposits: true,
+                reserves: HashMap::from([(
+                    ClientId(2),
+                    ReservePolicy {
+                        percent: Decimal::TEN,
+                        days: 1,
+                    },
+                )]),
+                ..LedgerConfig::default()
+            },
+        ]
     }
 
     #[test]
-    fn withdrawal() {
-        let mut ledger = Ledger::default();
+    fn extreme_amount_rows() {
+        // A fixed sequence of rows mixing every transaction type with extreme amounts, picked by a
+        // seeded xorshift generator so that every run drives the same rows through the ledger.
+        let mut state = 0x2545_f491_4f6c_dd1d_u64;
+        let mut next = move |bound: usize| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state as usize % bound
+        };
+        let amounts = extreme_amounts();
+
+        let mut input = String::from("type,client,tx,amount,reason,wallet,to_wallet\n");
+        for _ in 0..5_000 {
+            input.push_str(&format!(
+                "{},{},{},{},aml,{},{}\n",
+                ROW_TYPES[next(ROW_TYPES.len())],
+                next(3),
+                next(20),
+                amounts[next(amounts.len())],
+                next(2),
+                next(2),
+            ));
+        }
+
+        // Assert that every row is either applied or rejected without panicking, and that every
+        // account can still be output.
+        for config in load_configs() {
+            let mut ledger = Ledger::new(config);
+            let summary = ledger.load(input.as_bytes());
+            assert_eq!(summary.applied + summary.rejected, 5_000);
+            let mut buffer = Vec::new();
+            parser::write_output(&mut buffer, ledger.entries()).unwrap();
+        }
+    }
+
+    /// An input row of any transaction type, mostly for a few clients and transactions so that
+    /// rows refer to one another, with an extreme, arbitrary or malformed amount.
+    fn arbitrary_row() -> impl Strategy<Value = String> {
+        let amount = prop_oneof![
+            prop::sample::select(extreme_amounts()),
+            (prop_oneof![-1_000..100_000_i64, any::<i64>()], 0..8_u32)
+                .prop_map(|(mantissa, scale)| Decimal::new(mantissa, scale).to_string()),
+            "[-0-9.e]{0,12}",
+        ];
+        (
+            prop::sample::select(ROW_TYPES.as_slice()),
+            prop_oneof![0..4_u32, any::<u32>()],
+            prop_oneof![0..20_u32, any::<u32>()],
+            amount,
+            prop::sample::select(["aml", "fraud", "legal", "other", ""].as_slice()),
+            0..3_u16,
+            0..3_u16,
+        )
+            .prop_map(|(kind, client, tx, amount, reason, wallet, to_wallet)| {
+                format!("{kind},{client},{tx},{amount},{reason},{wallet},{to_wallet}\n")
+            })
+    }
+
+    proptest! {
+        // Assert that any sequence of rows is either applied or rejected without panicking, and
+        // that every account's total stays the sum of its balances.
+        #[test]
+        fn arbitrary_rows(rows in prop::collection::vec(arbitrary_row(), 0..200)) {
+            let input = String::from("type,client,tx,amount,reason,wallet,to_wallet\n")
+                + &rows.concat();
+            for config in load_configs() {
+                let mut ledger = Ledger::new(config);
+                let summary = ledger.load(input.as_bytes());
+                prop_assert_eq!(summary.applied + summary.rejected, rows.len());
+                for entry in ledger.entries() {
+                    let balances = [entry.held, entry.reserve.unwrap_or_default()]
+                        .into_iter()
+                        .chain(entry.pending)
+                        .try_fold(entry.available, Amount::checked_add);
+                    prop_assert_eq!(Some(entry.total), balances, "{:?}", entry);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn reversal() {
+        let mut fees = FeeSchedule::default();
+        fees.insert(
+            TransactionType::Deposit,
+            Fee {
+                flat: Amount::from(1),
+                percent: Decimal::ZERO,
+            },
+        );
+        let mut ledger = Ledger::new(LedgerConfig {
+            fees,
+            ..LedgerConfig::default()
+        });
         let client_id = ClientId(1);
-        let deposit_id = TransactionId(1);
-        let withdrawal_id = TransactionId(2);
+        let reversal = |tx| {
+            Transaction::Reversal(Reversal {
+                client: client_id,
+                tx: TransactionId(tx),
+            })
+        };
 
         let transactions = [
             Transaction::Deposit(Deposit {
                 client: client_id,
-                tx: deposit_id,
-                amount: Amount::from(7),
+                tx: TransactionId(1),
+                amount: Amount::from(10),
+                wallet: WalletId::default(),
+            }),
+            Transaction::Deposit(Deposit {
+                client: client_id,
+                tx: TransactionId(2),
+                amount: Amount::from(5),
+                wallet: WalletId::default(),
+            }),
+            Transaction::Dispute(Dispute {
+                client: client_id,
+                tx: TransactionId(2),
+                amount: Some(Amount::from(1)),
             }),
             Transaction::Withdrawal(Withdrawal {
                 client: client_id,
-                tx: withdrawal_id,
-                amount: Amount::from(3),
+                tx: TransactionId(3),
+                amount: Amount::from(6),
+                wallet: WalletId::default(),
             }),
         ];
-
         transactions
             .into_iter()
             .for_each(|transaction| ledger.update(transaction).unwrap());
-
-        // Assert client account has been created, the deposit added, and withdrawal made.
-        let Account {
-            available,
-            held,
-            locked,
-            ..
-        } = ledger
-            .clients
-            .get(&client_id)
-            .expect("client not found in ledger");
-
         assert_eq!(
-            (available, held, locked),
-            (&Amount::from(4), &Amount::from(0), &false)
+            ledger.account(client_id).unwrap().available,
+            Amount::from(6)
         );
 
-        // Assert that the deposit has been stored.
-        assert!(ledger.deposits.contains_key(&(client_id, deposit_id)));
-
-        // Assert that there are no disputes
-        assert!(ledger.disputes.is_empty());
-    }
-
-    #[test]
-    fn withdrawal_insufficient_funds() {
-        let mut ledger = Ledger::default();
-        let client_id = ClientId(1);
-        let deposit_id = TransactionId(1);
-        let deposit_amount = Amount::from(5);
-        let withdrawal_id = TransactionId(2);
-        let withdrawal_amount = Amount::from(7);
-
-        ledger
-            .update(Transaction::Deposit(Deposit {
-                client: client_id,
-                tx: deposit_id,
-                amount: deposit_amount.clone(),
-            }))
-            .unwrap();
-
-        let withdrawal_result = ledger.update(Transaction::Withdrawal(Withdrawal {
-            client: client_id,
-            tx: withdrawal_id,
-            amount: withdrawal_amount.clone(),
-        }));
-
-        // Assert that the withdrawal failed
+        // Assert that the first deposit cannot be reversed while its funds have been withdrawn,
+        // nor the second once it has been disputed.
         assert_eq!(
-            withdrawal_result,
-            Err(TransactionError::WithdrawalInsufficientFunds {
+            ledger.update(reversal(1)),
+            Err(TransactionError::ReversalInconsistent {
                 client_id,
-                available: deposit_amount.clone(),
-                requested: withdrawal_amount
+                transaction_id: TransactionId(1),
+                reason: "the deposited funds are no longer available",
             })
         );
+        assert!(matches!(
+            ledger.update(reversal(2)),
+            Err(TransactionError::ReversalInconsistent { .. })
+        ));
 
-        // Assert client account has been created, the deposit added, and that the withdrawal failed.
-        let Account {
-            available,
-            held,
-            locked,
-            ..
-        } = ledger
-            .clients
-            .get(&client_id)
-            .expect("client not found in ledger");
-
+        // Assert that reversing the withdrawal and then the deposit undoes both, refunding the fee.
+        ledger.update(reversal(3)).unwrap();
+        ledger.update(reversal(1)).unwrap();
+        let account = ledger.account(client_id).unwrap();
         assert_eq!(
-            (available, held, locked),
-            (&deposit_amount, &Amount::from(0), &false)
+            (account.available, account.held, account.fees),
+            (Amount::from(3), Amount::from(1), Amount::from(1))
         );
+        assert_eq!(ledger.revenue(), Amount::from(1));
+        assert!(!ledger.deposits.contains_key(&(client_id, TransactionId(1))));
 
-        // Assert that the deposit has been stored.
-        assert!(ledger.deposits.contains_key(&(client_id, deposit_id)));
-
-        // Assert that there are no disputes
-        assert!(ledger.disputes.is_empty());
+        // Assert that a transaction can only be reversed once.
+        assert_eq!(
+            ledger.update(reversal(1)),
+            Err(TransactionError::ReversalFailed {
+                client_id,
+                transaction_id: TransactionId(1),
+            })
+        );
     }
 
     #[test]
-    fn dispute() {
+    fn represent_and_arbitrate() {
         let mut ledger = Ledger::default();
         let client_id = ClientId(1);
-        let deposit_id_1 = TransactionId(1);
-        let deposit_id_2 = TransactionId(2);
-        let amount_available = Amount::from(5);
-        let amount_held = Amount::from(3);
+        let deposit_id = TransactionId(1);
+        let state = |ledger: &Ledger| {
+            let account = ledger.account(client_id).unwrap();
+            (account.available, account.held, account.locked)
+        };
 
         let transactions = [
             Transaction::Deposit(Deposit {
                 client: client_id,
-                tx: deposit_id_1,
-                amount: amount_available.clone(),
+                tx: deposit_id,
+                amount: Amount::from(10),
+                wallet: WalletId::default(),
             }),
-            Transaction::Deposit(Deposit {
+            Transaction::Dispute(Dispute {
                 client: client_id,
-                tx: deposit_id_2,
-                amount: amount_held.clone(),
+                tx: deposit_id,
+                amount: None,
             }),
-            Transaction::Dispute(Dispute {
+            Transaction::Chargeback(Chargeback {
                 client: client_id,
-                tx: deposit_id_2,
+                tx: deposit_id,
+                amount: None,
             }),
         ];
-
         transactions
             .into_iter()
             .for_each(|transaction| ledger.update(transaction).unwrap());
+        assert_eq!(state(&ledger), (Amount::from(0), Amount::from(0), true));
 
-        // Assert that the client account has been created, the deposits added, and the correct amount is held in dispute.
-        let Account {
-            available,
-            held,
-            locked,
-            ..
-        } = ledger
-            .clients
-            .get(&client_id)
-            .expect("client not found in ledger");
-
+        // Assert that an arbitration fails before the deposit has been represented.
         assert_eq!(
-            (available, held, locked),
-            (&amount_available, &amount_held, &false)
+            ledger.update(Transaction::Arbitrate(Arbitrate {
+                client: client_id,
+                tx: deposit_id,
+                amount: None,
+            })),
+            Err(TransactionError::ArbitrateFailed {
+                client_id,
+                transaction_id: deposit_id,
+            })
         );
 
-        // Assert that the dispute is being tracked
-        assert!(ledger.disputes.contains(&(client_id, deposit_id_2)));
-    }
-
-    #[test]
-    fn dispute_no_transaction() {
-        // If the tx specified by the dispute doesn't exist this is an error on the partner's side.
-        let mut ledger = Ledger::default();
-        let client_id = ClientId(1);
-        let deposit_id = TransactionId(1);
-        let deposit_amount = Amount::from(7);
-        let dispute_id = TransactionId(2);
-
+        // Assert that a representment or arbitration of a negative amount fails.
+        assert_eq!(
+            ledger.update(Transaction::Represent(Represent {
+                client: client_id,
+                tx: deposit_id,
+                amount: Some(Amount::from(-4)),
+            })),
+            Err(TransactionError::NonPositiveAmount {
+                client_id,
+                requested: Amount::from(-4),
+            })
+        );
         ledger
-            .update(Transaction::Deposit(Deposit {
+            .update(Transaction::Represent(Represent {
                 client: client_id,
                 tx: deposit_id,
-                amount: deposit_amount.clone(),
+                amount: Some(Amount::from(1)),
             }))
             .unwrap();
-
-        let dispute_result = ledger.update(Transaction::Dispute(Dispute {
-            client: client_id,
-            tx: dispute_id,
-        }));
-
-        // Assert that the dispute failed
         assert_eq!(
-            dispute_result,
-            Err(TransactionError::DisputeFailed {
-                client_id: client_id,
-                transaction_id: dispute_id
+            ledger.update(Transaction::Arbitrate(Arbitrate {
+                client: client_id,
+                tx: deposit_id,
+                amount: Some(Amount::from(-1)),
+            })),
+            Err(TransactionError::NonPositiveAmount {
+                client_id,
+                requested: Amount::from(-1),
             })
         );
 
-        // Assert client account has been created, the deposit added, and that no funds are held.
-        let Account {
-            available,
-            held,
-            locked,
-            ..
-        } = ledger
-            .clients
-            .get(&client_id)
-            .expect("client not found in ledger");
+        // Assert that a partial representment restores funds but leaves the account locked while
+        // the rest remains charged back.
+        ledger
+            .update(Transaction::Represent(Represent {
+                client: client_id,
+                tx: deposit_id,
+                amount: Some(Amount::from(3)),
+            }))
+            .unwrap();
+        assert_eq!(state(&ledger), (Amount::from(4), Amount::from(0), true));
 
-        assert_eq!(
-            (available, held, locked),
-            (&deposit_amount, &Amount::from(0), &false)
-        );
+        // Assert that representing the remainder unlocks the account.
+        ledger
+            .update(Transaction::Represent(Represent {
+                client: client_id,
+                tx: deposit_id,
+                amount: None,
+            }))
+            .unwrap();
+        assert_eq!(state(&ledger), (Amount::from(10), Amount::from(0), false));
+        assert!(ledger.chargebacks.is_empty());
 
-        // Assert that there are no disputes
-        assert!(ledger.disputes.is_empty());
+        // Assert that an arbitration re-debits the represented funds and relocks the account.
+        ledger
+            .update(Transaction::Arbitrate(Arbitrate {
+                client: client_id,
+                tx: deposit_id,
+                amount: None,
+            }))
+            .unwrap();
+        assert_eq!(state(&ledger), (Amount::from(0), Amount::from(0), true));
+        assert!(ledger.representments.is_empty());
     }
 
     #[test]
-    fn resolve() {
+    fn represent_after_arbitration() {
         let mut ledger = Ledger::default();
         let client_id = ClientId(1);
-        let deposit_id_1 = TransactionId(1);
-        let deposit_id_2 = TransactionId(2);
-        let amount_1 = Amount::from(5);
-        let amount_2 = Amount::from(3);
+        let deposit_id = TransactionId(1);
+        let amount = |amount: i32| Some(Amount::from(amount));
 
         let transactions = [
             Transaction::Deposit(Deposit {
                 client: client_id,
-                tx: deposit_id_1,
-                amount: amount_1.clone(),
+                tx: deposit_id,
+                amount: Amount::from(100),
+                wallet: WalletId::default(),
             }),
-            Transaction::Deposit(Deposit {
+            Transaction::Dispute(Dispute {
                 client: client_id,
-                tx: deposit_id_2,
-                amount: amount_2.clone(),
+                tx: deposit_id,
+                amount: None,
             }),
-            Transaction::Dispute(Dispute {
+            Transaction::Chargeback(Chargeback {
                 client: client_id,
-                tx: deposit_id_2,
+                tx: deposit_id,
+                amount: None,
             }),
-            Transaction::Resolve(Resolve {
+            Transaction::Represent(Represent {
                 client: client_id,
-                tx: deposit_id_2,
+                tx: deposit_id,
+                amount: amount(50),
+            }),
+            Transaction::Arbitrate(Arbitrate {
+                client: client_id,
+                tx: deposit_id,
+                amount: amount(50),
+            }),
+            Transaction::Represent(Represent {
+                client: client_id,
+                tx: deposit_id,
+                amount: amount(50),
             }),
         ];
-
         transactions
             .into_iter()
             .for_each(|transaction| ledger.update(transaction).unwrap());
 
-        // Assert that the client account has been created, the deposits added, and that the dispute has been resolved.
-        let Account {
-            available,
-            held,
-            locked,
-            ..
-        } = ledger
-            .clients
-            .get(&client_id)
-            .expect("client not found in ledger");
-
+        // Assert that representing the rest of the chargeback leaves the account locked by the
+        // arbitration.
+        let account = ledger.account(client_id).unwrap();
         assert_eq!(
-            (available, held, locked),
-            (
-                &Amount::from(amount_1.0 + amount_2.0),
-                &Amount::from(0),
-                &false
-            )
+            (account.available, account.locked),
+            (Amount::from(50), true)
         );
-
-        // Assert that there are no disputes
-        assert!(ledger.disputes.is_empty());
+        assert!(ledger.chargebacks.is_empty());
     }
 
     #[test]
-    fn resolve_transaction_not_disputed() {
-        // If the tx specified by the resolve isn't under dispute this is an error on the partner's side.
-        let mut ledger = Ledger::default();
+    fn represent_after_rule_lock() {
+        let mut rules = RuleSet::default();
+        rules.add(Action::Lock, Box::new(WithdrawalVelocity::new(1, 1)));
+        let mut ledger = Ledger::new(LedgerConfig {
+            rules,
+            ..LedgerConfig::default()
+        });
         let client_id = ClientId(1);
-        let deposit_id_1 = TransactionId(1);
-        let deposit_id_2 = TransactionId(2);
-        let amount_1 = Amount::from(5);
+        let deposit_id = TransactionId(1);
+
+        let transactions = [
+            Transaction::Deposit(Deposit {
+                client: client_id,
+                tx: deposit_id,
+                amount: Amount::from(10),
+                wallet: WalletId::default(),
+            }),
+            Transaction::Dispute(Dispute {
+                client: client_id,
+                tx: deposit_id,
+                amount: None,
+            }),
+            Transaction::Chargeback(Chargeback {
+                client: client_id,
+                tx: deposit_id,
+                amount: None,
+            }),
+        ];
+        transactions
+            .into_iter()
+            .for_each(|transaction| ledger.update(transaction).unwrap());
 
+        // Assert that a withdrawal tripping the rule locks the account, and that representing the
+        // chargeback does not lift that lock.
+        assert!(matches!(
+            ledger.update(Transaction::Withdrawal(Withdrawal {
+                client: client_id,
+                tx: TransactionId(2),
+                amount: Amount::from(1),
+                wallet: WalletId::default(),
+            })),
+            Err(TransactionError::RuleLocked { .. })
+        ));
         ledger
-            .update(Transaction::Deposit(Deposit {
+            .update(Transaction::Represent(Represent {
                 client: client_id,
-                tx: deposit_id_1,
-                amount: amount_1.clone(),
+                tx: deposit_id,
+                amount: None,
             }))
             .unwrap();
-
-        let resolve_result = ledger.update(Transaction::Resolve(Resolve {
-            client: client_id,
-            tx: deposit_id_2,
-        }));
-
-        // Assert that the resolve failed
+        let account = ledger.account(client_id).unwrap();
         assert_eq!(
-            resolve_result,
-            Err(TransactionError::ResolveFailed {
-                client_id: client_id,
-                transaction_id: deposit_id_2
-            })
+            (account.available, account.locked),
+            (Amount::from(10), true)
         );
     }
 
     #[test]
-    fn chargeback() {
+    fn authorize_capture_void() {
         let mut ledger = Ledger::default();
         let client_id = ClientId(1);
-        let deposit_id_1 = TransactionId(1);
-        let deposit_id_2 = TransactionId(2);
-        let amount_1 = Amount::from(5);
-        let amount_2 = Amount::from(3);
+        let auth_id = TransactionId(2);
+        let capture = |amount: Option<i32>| {
+            Transaction::Capture(Capture {
+                client: client_id,
+                tx: auth_id,
+                amount: amount.map(Amount::from),
+            })
+        };
+        let void = Transaction::Void(Void {
+            client: client_id,
+            tx: auth_id,
+        });
 
         let transactions = [
             Transaction::Deposit(Deposit {
                 client: client_id,
-                tx: deposit_id_1,
-                amount: amount_1.clone(),
-            }),
-            Transaction::Deposit(Deposit {
-                client: client_id,
-                tx: deposit_id_2,
-                amount: amount_2.clone(),
-            }),
-            Transaction::Dispute(Dispute {
-                client: client_id,
-                tx: deposit_id_2,
+                tx: TransactionId(1),
+                amount: Amount::from(10),
+                wallet: WalletId::default(),
             }),
-            Transaction::Chargeback(Chargeback {
+            Transaction::Authorize(Authorize {
                 client: client_id,
-                tx: deposit_id_2,
+                tx: auth_id,
+                amount: Amount::from(8),
             }),
         ];
-
         transactions
             .into_iter()
             .for_each(|transaction| ledger.update(transaction).unwrap());
 
-        // Assert that the client account has been created, the deposits added, the dispute has been charged back,
-        // and that the client has been locked
-        let Account {
-            available,
-            held,
-            locked,
-            ..
-        } = ledger
-            .clients
-            .get(&client_id)
-            .expect("client not found in ledger");
+        // Assert that the authorized funds are held.
+        let balances = |ledger: &Ledger| {
+            let account = ledger.account(client_id).unwrap();
+            (account.available, account.held)
+        };
+        assert_eq!(balances(&ledger), (Amount::from(2), Amount::from(8)));
 
+        // Assert that an authorization beyond the available funds fails.
         assert_eq!(
-            (available, held, locked),
-            (&amount_1, &Amount::from(0), &true)
+            ledger.update(Transaction::Authorize(Authorize {
+                client: client_id,
+                tx: TransactionId(3),
+                amount: Amount::from(3),
+            })),
+            Err(TransactionError::AuthorizeInsufficientFunds {
+                client_id,
+                available: Amount::from(2),
+                requested: Amount::from(3),
+            })
         );
 
-        // Assert that there are no disputes
-        assert!(ledger.disputes.is_empty());
+        // Assert that an authorization or capture of a negative or zero amount fails.
+        for amount in [-5, 0] {
+            let rejected = Err(TransactionError::NonPositiveAmount {
+                client_id,
+                requested: Amount::from(amount),
+            });
+            let authorize = Transaction::Authorize(Authorize {
+                client: client_id,
+                tx: TransactionId(3),
+                amount: Amount::from(amount),
+            });
+            assert_eq!(ledger.update(authorize), rejected);
+            assert_eq!(ledger.update(capture(Some(amount))), rejected);
+        }
+        assert_eq!(balances(&ledger), (Amount::from(2), Amount::from(8)));
+
+        // Assert that a partial capture debits only the amount captured.
+        ledger.update(capture(Some(5))).unwrap();
+        assert_eq!(balances(&ledger), (Amount::from(2), Amount::from(3)));
+        assert_eq!(
+            ledger.update(capture(Some(4))),
+            Err(TransactionError::CaptureExceedsAuthorization {
+                client_id,
+                transaction_id: auth_id,
+                requested: Amount::from(4),
+                remaining: Amount::from(3),
+            })
+        );
+
+        // Assert that a void releases the remainder, after which the authorization is closed.
+        ledger.update(void).unwrap();
+        assert_eq!(balances(&ledger), (Amount::from(5), Amount::from(0)));
+        assert_eq!(
+            ledger.update(capture(None)),
+            Err(TransactionError::CaptureFailed {
+                client_id,
+                transaction_id: auth_id,
+            })
+        );
+        assert_eq!(
+            ledger.update(void),
+            Err(TransactionError::VoidFailed {
+                client_id,
+                transaction_id: auth_id,
+            })
+        );
+        assert!(ledger.authorizations.is_empty());
     }
 
     #[test]
-    fn chargeback_transaction_not_disputed() {
-        // If the tx specified by the chargeback isn't under dispute this is an error on the partner's side.
-        let mut ledger = Ledger::default();
-        let client_id = ClientId(1);
-        let deposit_id_1 = TransactionId(1);
-        let deposit_id_2 = TransactionId(2);
-        let amount_1 = Amount::from(5);
-
-        ledger
-            .update(Transaction::Deposit(Deposit {
-                client: client_id,
-                tx: deposit_id_1,
-                amount: amount_1.clone(),
-            }))
-            .unwrap();
+    fn rules() {
+        let mut rules = RuleSet::default();
+        rules.add(Action::Flag, Box::new(WithdrawalVelocity::new(2, 3)));
+        rules.add(Action::Reject, Box::new(WithdrawalVelocity::new(3, 3)));
+        rules.add(Action::Lock, Box::new(DepositWithdrawDispute::new(1)));
+        let mut ledger = Ledger::new(LedgerConfig {
+            rules,
+            ..LedgerConfig::default()
+        });
+        let rdr = "type, client, tx, amount\n\
+                   deposit, 1, 1, 10\n\
+                   withdrawal, 1, 2, 1\n\
+                   withdrawal, 1, 3, 1\n\
+                   withdrawal, 1, 4, 1\n\
+                   deposit, 1, 5, 10\n\
+                   withdrawal, 1, 6, 100\n\
+                   dispute, 1, 1,\n"
+            .as_bytes();
 
-        let resolve_result = ledger.update(Transaction::Chargeback(Chargeback {
-            client: client_id,
-            tx: deposit_id_2,
-        }));
+        let mut reports = Vec::new();
+        let summary = ledger.load_with(read_input(rdr), |report| match report {
+            Report::Alert(alert) => reports.push((alert.position.line, alert.verdict.action)),
+            Report::Reject(reject) => reports.push((reject.position.line, Action::Reject)),
+        });
 
-        // Assert that the resolve failed
+        // Assert that the second withdrawal is flagged and the third rejected. The oversized
+        // withdrawal is flagged by the rule but rejected by the ledger, so raises no alert.
+        // Disputing the deposit which was immediately withdrawn against locks the account.
         assert_eq!(
-            resolve_result,
-            Err(TransactionError::ChargebackFailed {
-                client_id: client_id,
-                transaction_id: deposit_id_2
-            })
+            summary,
+            LoadSummary {
+                applied: 4,
+                rejected: 3
+            }
+        );
+        assert_eq!(
+            reports,
+            [
+                (4, Action::Flag),
+                (5, Action::Reject),
+                (7, Action::Reject),
+                (8, Action::Reject)
+            ]
         );
+        let account = ledger.account(ClientId(1)).unwrap();
+        assert!(account.locked);
+        assert_eq!(account.available, Amount::from(18));
+        assert!(ledger.disputes.is_empty());
     }
 
     #[test]
@@ -755,14 +5467,17 @@ mod tests {
                 client: client_id,
                 tx: deposit_id_1,
                 amount: Amount::from(5),
+                wallet: WalletId::default(),
             }),
             Transaction::Dispute(Dispute {
                 client: client_id,
                 tx: deposit_id_1,
+                amount: None,
             }),
             Transaction::Chargeback(Chargeback {
                 client: client_id,
                 tx: deposit_id_1,
+                amount: None,
             }),
         ];
 
@@ -776,6 +5491,7 @@ mod tests {
                 client: client_id,
                 tx: TransactionId(2),
                 amount: Amount::from(3),
+                wallet: WalletId::default(),
             })),
             Err(TransactionError::ClientAccountLocked { client_id })
         );
@@ -786,6 +5502,7 @@ mod tests {
                 client: client_id,
                 tx: TransactionId(3),
                 amount: Amount::from(3),
+                wallet: WalletId::default(),
             })),
             Err(TransactionError::ClientAccountLocked { client_id })
         );
@@ -795,6 +5512,7 @@ mod tests {
             ledger.update(Transaction::Dispute(Dispute {
                 client: client_id,
                 tx: TransactionId(1),
+                amount: None,
             })),
             Err(TransactionError::ClientAccountLocked { client_id })
         );
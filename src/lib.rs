@@ -1,3 +1,11 @@
+pub mod clients;
+pub mod compression;
+pub mod fees;
+pub mod interest;
 pub mod ledger;
+pub mod limits;
 pub mod parser;
+pub mod precision;
+pub mod reserves;
+pub mod rules;
 pub mod types;
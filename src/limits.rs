@@ -0,0 +1,229 @@
+use crate::types::{Amount, ClientId, Timestamp};
+use std::collections::HashMap;
+use std::fmt;
+
+/// The tier applied to clients which have not been assigned one.
+pub const DEFAULT_TIER: &str = "default";
+
+/// A withdrawal limit which may be exceeded.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Limit {
+    PerTransaction,
+    Daily,
+    Monthly,
+}
+
+impl fmt::Display for Limit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Limit::PerTransaction => write!(f, "per transaction"),
+            Limit::Daily => write!(f, "daily"),
+            Limit::Monthly => write!(f, "monthly"),
+        }
+    }
+}
+
+/// The maximum amounts which may be withdrawn, where `None` is unlimited.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Limits {
+    pub per_transaction: Option<Amount>,
+    pub daily: Option<Amount>,
+    pub monthly: Option<Amount>,
+}
+
+/// The withdrawal limits of each client tier, and the tier each client belongs to.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct LimitSchedule {
+    tiers: HashMap<String, Limits>,
+    clients: HashMap<ClientId, String>,
+}
+
+impl LimitSchedule {
+    /// Set the limits of a tier, replacing any existing limits.
+    pub fn insert_tier(&mut self, tier: impl Into<String>, limits: Limits) {
+        self.tiers.insert(tier.into(), limits);
+    }
+
+    /// Whether no tier has any limits set.
+    pub fn is_empty(&self) -> bool {
+        self.tiers.is_empty()
+    }
+
+    /// Assign a client to a tier.
+    pub fn assign(&mut self, client_id: ClientId, tier: impl Into<String>) {
+        self.clients.insert(client_id, tier.into());
+    }
+
+    /// The limits applying to a client, from their assigned tier or otherwise the default tier.
+    pub fn limits(&self, client_id: ClientId) -> Option<&Limits> {
+        let tier = self
+            .clients
+            .get(&client_id)
+            .map_or(DEFAULT_TIER, String::as_str);
+        self.tiers.get(tier)
+    }
+}
+
+/// The amounts a client has withdrawn in the current day and month. Days and months are taken
+/// from the ledger's clock, so when no row has a timestamp every withdrawal falls in the same day
+/// and the daily and monthly limits apply to the client's withdrawals over the whole input.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct WithdrawalUsage {
+    day: u64,
+    daily: Amount,
+    month: u64,
+    monthly: Amount,
+}
+
+impl WithdrawalUsage {
+    /// Check that withdrawing an amount at the given time is within the limits, returning the
+    /// limit which would be exceeded and the remaining headroom under it otherwise.
+    pub fn check(
+        &self,
+        limits: &Limits,
+        now: Timestamp,
+        amount: Amount,
+    ) -> Result<(), (Limit, Amount)> {
+        let daily = self.daily_total(now);
+        let monthly = self.monthly_total(now);
+        let checks = [
+            (
+                Limit::PerTransaction,
+                limits.per_transaction,
+                Amount::from(0),
+            ),
+            (Limit::Daily, limits.daily, daily),
+            (Limit::Monthly, limits.monthly, monthly),
+        ];
+        for (limit, maximum, used) in checks {
+            if let Some(maximum) = maximum {
+                if used.0.saturating_add(amount.0) > maximum.0 {
+                    let remaining = (maximum.0 - used.0).max(Default::default());
+                    return Err((limit, Amount(remaining)));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Record an amount withdrawn at the given time.
+    pub fn record(&mut self, now: Timestamp, amount: Amount) {
+        *self = WithdrawalUsage {
+            day: now.day(),
+            daily: Amount(self.daily_total(now).0.saturating_add(amount.0)),
+            month: now.month(),
+            monthly: Amount(self.monthly_total(now).0.saturating_add(amount.0)),
+        };
+    }
+
+    /// Release an amount withdrawn at the given time, for example because the withdrawal was
+    /// reversed. Withdrawals from an earlier day or month no longer count towards the limits, so
+    /// only the totals for the period the withdrawal was made in are reduced.
+    pub fn release(&mut self, withdrawn: Timestamp, amount: Amount) {
+        if self.day == withdrawn.day() {
+            self.daily.0 = (self.daily.0 - amount.0).max(Default::default());
+        }
+        if self.month == withdrawn.month() {
+            self.monthly.0 = (self.monthly.0 - amount.0).max(Default::default());
+        }
+    }
+
+    fn daily_total(&self, now: Timestamp) -> Amount {
+        match self.day == now.day() {
+            true => self.daily,
+            false => Amount::from(0),
+        }
+    }
+
+    fn monthly_total(&self, now: Timestamp) -> Amount {
+        match self.month == now.month() {
+            true => self.monthly,
+            false => Amount::from(0),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tiers() {
+        let mut schedule = LimitSchedule::default();
+        assert!(schedule.is_empty());
+        let standard = Limits {
+            daily: Some(Amount::from(100)),
+            ..Limits::default()
+        };
+        let premium = Limits {
+            daily: Some(Amount::from(1000)),
+            ..Limits::default()
+        };
+        schedule.insert_tier(DEFAULT_TIER, standard);
+        schedule.insert_tier("premium", premium);
+        assert!(!schedule.is_empty());
+        schedule.assign(ClientId(2), "premium");
+        schedule.assign(ClientId(3), "unknown");
+
+        assert_eq!(schedule.limits(ClientId(1)), Some(&standard));
+        assert_eq!(schedule.limits(ClientId(2)), Some(&premium));
+        assert_eq!(schedule.limits(ClientId(3)), None);
+    }
+
+    #[test]
+    fn usage() {
+        let limits = Limits {
+            per_transaction: Some(Amount::from(50)),
+            daily: Some(Amount::from(80)),
+            monthly: Some(Amount::from(150)),
+        };
+        let day = Timestamp::SECONDS_PER_DAY;
+        let mut usage = WithdrawalUsage::default();
+
+        assert_eq!(
+            usage.check(&limits, Timestamp(0), Amount::from(60)),
+            Err((Limit::PerTransaction, Amount::from(50)))
+        );
+
+        usage.record(Timestamp(0), Amount::from(50));
+        assert_eq!(
+            usage.check(&limits, Timestamp(10), Amount::from(40)),
+            Err((Limit::Daily, Amount::from(30)))
+        );
+
+        // The daily total resets the following day, but the monthly total does not.
+        assert_eq!(
+            usage.check(&limits, Timestamp(day), Amount::from(40)),
+            Ok(())
+        );
+        usage.record(Timestamp(day), Amount::from(50));
+        usage.record(Timestamp(2 * day), Amount::from(40));
+        assert_eq!(
+            usage.check(&limits, Timestamp(3 * day), Amount::from(20)),
+            Err((Limit::Monthly, Amount::from(10)))
+        );
+
+        // Both totals reset the following month.
+        assert_eq!(
+            usage.check(&limits, Timestamp(31 * day), Amount::from(50)),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn release() {
+        let day = Timestamp::SECONDS_PER_DAY;
+        let mut usage = WithdrawalUsage::default();
+        usage.record(Timestamp(0), Amount::from(30));
+        usage.record(Timestamp(day), Amount::from(20));
+
+        // Releasing a withdrawal from an earlier day only reduces the monthly total.
+        usage.release(Timestamp(0), Amount::from(30));
+        assert_eq!(usage.daily_total(Timestamp(day)), Amount::from(20));
+        assert_eq!(usage.monthly_total(Timestamp(day)), Amount::from(20));
+
+        usage.release(Timestamp(day), Amount::from(20));
+        assert_eq!(usage.daily_total(Timestamp(day)), Amount::from(0));
+        assert_eq!(usage.monthly_total(Timestamp(day)), Amount::from(0));
+    }
+}
@@ -1,32 +1,313 @@
-use ledger::Ledger;
-use std::env;
+use cli::{expand_inputs, Cli, Command, InputArgs, IoArgs};
+use payments_engine::ledger::{Alert, Applied, Ledger, LoadSummary, Report};
+use payments_engine::parser::{
+    self, read_input_with, InputOptions, LedgerEntry, ParseError, Position, Row, StatementEntry,
+};
+use payments_engine::types::{Amount, ClientId, Timestamp, Transaction, TransactionId};
 use std::fs::File;
-use std::io::{Error, ErrorKind};
+use std::io::{self, BufWriter, Error, ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
 
-mod ledger;
-mod parser;
-mod types;
+mod cli;
 
-fn main() -> Result<(), Error> {
-    // Ensure there is a single command line argument specifying the input file.
-    let args: Vec<String> = env::args().collect();
-    if args.len() != 2 {
-        return Err(Error::new(
-            ErrorKind::Other,
-            "please specify a single input file argument",
-        ));
+/// Exit code when the command line arguments are invalid.
+const EXIT_USAGE: u8 = 2;
+/// Exit code when an input or output file could not be read or written.
+const EXIT_IO: u8 = 3;
+/// Exit code when processing completed but one or more input rows were rejected.
+const EXIT_REJECTED: u8 = 4;
+
+fn main() -> ExitCode {
+    let cli = match Cli::try_parse_args(std::env::args_os()) {
+        Ok(cli) => cli,
+        Err(e) => {
+            let _ = e.print();
+            return match e.use_stderr() {
+                true => ExitCode::from(EXIT_USAGE),
+                false => ExitCode::SUCCESS,
+            };
+        }
+    };
+
+    match run(cli.command) {
+        Ok(summary) if summary.rejected > 0 => ExitCode::from(EXIT_REJECTED),
+        Ok(_) => ExitCode::SUCCESS,
+        Err(Failure::Usage(e)) => {
+            eprintln!("{}", e);
+            ExitCode::from(EXIT_USAGE)
+        }
+        Err(Failure::Io(e)) => {
+            eprintln!("{}", e);
+            ExitCode::from(EXIT_IO)
+        }
+    }
+}
+
+/// Why a command failed, which decides its exit code.
+enum Failure {
+    /// The arguments are invalid, including inputs which match no files and configuration files
+    /// which cannot be parsed.
+    Usage(Error),
+    /// An input or output file could not be read or written.
+    Io(Error),
+}
+
+impl Failure {
+    /// Classify an error from expanding the inputs or loading the configuration, which is a usage
+    /// error unless a file could not be read.
+    fn arguments(e: Error) -> Self {
+        match e.kind() {
+            ErrorKind::InvalidInput => Failure::Usage(e),
+            _ => Failure::Io(e),
+        }
+    }
+}
+
+impl From<Error> for Failure {
+    fn from(e: Error) -> Self {
+        Failure::Io(e)
+    }
+}
+
+fn run(command: Command) -> Result<LoadSummary, Failure> {
+    match command {
+        Command::Process(io) => process(io, None),
+        Command::Validate(input) => validate(input),
+        Command::Statement { io, client } => statement(io, ClientId(client)),
+        Command::Replay { io, until } => process(io, Some(TransactionId(until))),
+    }
+}
+
+/// Apply all transactions, or those up to and including `until`, and write the client accounts.
+fn process(io: IoArgs, until: Option<TransactionId>) -> Result<LoadSummary, Failure> {
+    let mut ledger = Ledger::new(io.input.ledger.config().map_err(Failure::arguments)?);
+    let paths = expand_inputs(&io.input.inputs).map_err(Failure::arguments)?;
+    let options = io.input.options();
+    let mut failure = None;
+    let mut reached = false;
+
+    // The inputs are loaded as one sequence, so rows parked in one input can still meet their
+    // deposit in a later one. Replaying stops after the row creating `until`, not at rows which
+    // only reference it, such as a dispute of it.
+    let rows = read_inputs(&paths, &options, &mut failure).take_while(|row| {
+        let take = !reached;
+        reached |= matches!(
+            (&row.entry, until),
+            (Ok(transaction), Some(until)) if transaction.creates_tx() && transaction.tx() == until
+        );
+        take
+    });
+    let summary = ledger.load_with(rows, report(&paths));
+    if let Some(e) = failure {
+        return Err(e.into());
+    }
+    if let Some(until) = until.filter(|_| !reached) {
+        return Err(Failure::Usage(Error::new(
+            ErrorKind::InvalidInput,
+            format!("transaction {} does not occur in the inputs", until.0),
+        )));
+    }
+
+    let output = open_output(&io.output)?;
+    if io.wallets {
+        parser::write_output_with(output, io.output_format, ledger.wallet_entries())?;
+    } else {
+        parser::write_output_with(output, io.output_format, ledger.entries())?;
+    }
+    warn_untimed_limits(&ledger);
+    if !ledger.residue().0.is_zero() {
+        eprintln!("rounding residue: {}", ledger.residue().0);
+    }
+    Ok(summary)
+}
+
+/// Dry run the inputs against a scratch ledger, reporting every row which would be rejected
+/// without writing any accounts.
+fn validate(input: InputArgs) -> Result<LoadSummary, Failure> {
+    let ledger = Ledger::new(input.ledger.config().map_err(Failure::arguments)?);
+    let paths = expand_inputs(&input.inputs).map_err(Failure::arguments)?;
+    let options = input.options();
+    let mut failure = None;
+    let summary = ledger.dry_run(read_inputs(&paths, &options, &mut failure), report(&paths));
+    if let Some(e) = failure {
+        return Err(e.into());
+    }
+    eprintln!(
+        "{} rows valid, {} rows rejected",
+        summary.applied, summary.rejected
+    );
+    Ok(summary)
+}
+
+/// Apply all transactions and write a statement of those applied to a single client.
+fn statement(io: IoArgs, client: ClientId) -> Result<LoadSummary, Failure> {
+    let paths = expand_inputs(&io.input.inputs).map_err(Failure::arguments)?;
+    let options = io.input.options();
+    let mut failure = None;
+    let mut ledger = Ledger::new(io.input.ledger.config().map_err(Failure::arguments)?);
+    let mut summary = LoadSummary::default();
+    let charges_fees = !ledger.config().fees.is_empty();
+    let mut report = report(&paths);
+
+    // The lines are buffered so that nothing is written if an input cannot be read.
+    let rows = read_inputs(&paths, &options, &mut failure);
+    let lines = rows.flat_map(|row| {
+        let mut lines = Vec::new();
+        let transaction = row.entry.as_ref().ok().copied();
+        let fee = transaction.and_then(|transaction| ledger.fee(&transaction));
+        let position = row.position;
+        let result = ledger.apply(row);
+        summary.record(&result);
+        match (result, transaction, fee) {
+            (Ok(Applied::Applied), Some(transaction), Some(fee)) => {
+                let fee = charges_fees.then_some(fee);
+                lines.extend(statement_line(&ledger, client, transaction, fee));
+            }
+            (Err(reject), _, _) => report(Report::Reject(reject)),
+            _ => {}
+        }
+
+        // Parked rows whose deposit this row was follow it, each with the balances after it.
+        while let Some(unparked) = ledger.next_unparked() {
+            summary.record(&unparked.result);
+            match unparked.result {
+                Ok(_) => {
+                    let fee = charges_fees.then_some(unparked.fee);
+                    lines.extend(statement_line(&ledger, client, unparked.transaction, fee));
+                }
+                Err(reject) => report(Report::Reject(reject)),
+            }
+        }
+        for verdict in ledger.take_alerts() {
+            report(Report::Alert(Alert { position, verdict }));
+        }
+        lines
+    });
+    let lines = lines.collect::<Vec<_>>();
+    if let Some(e) = failure {
+        return Err(e.into());
+    }
+    parser::write_output_with(
+        open_output(&io.output)?,
+        io.output_format,
+        lines.into_iter(),
+    )?;
+
+    // Reject anything still waiting for its deposit at the end of the last input.
+    ledger.expire_parked();
+    for unparked in ledger.take_unparked() {
+        summary.record(&unparked);
+        if let Err(reject) = unparked {
+            report(Report::Reject(reject));
+        }
+    }
+    warn_untimed_limits(&ledger);
+    Ok(summary)
+}
+
+/// Warn when withdrawal limits are configured but no row had a timestamp, since every withdrawal
+/// then falls on the same day and the daily and monthly limits cover the whole input.
+fn warn_untimed_limits(ledger: &Ledger) {
+    if !ledger.config().limits.is_empty() && ledger.now() == Timestamp::default() {
+        eprintln!(
+            "warning: no row has a timestamp, so daily and monthly withdrawal limits applied to all withdrawals together"
+        );
     }
+}
 
-    // Attempt to open the specified file.
-    let input_file = File::open(args[1].clone())?;
+/// The statement line for a transaction which has just been applied, if it is one of the
+/// client's, with the client's balances after it.
+fn statement_line(
+    ledger: &Ledger,
+    client: ClientId,
+    transaction: Transaction,
+    fee: Option<Amount>,
+) -> Option<StatementEntry> {
+    if transaction.client() != client {
+        return None;
+    }
+    let LedgerEntry {
+        available,
+        held,
+        total,
+        locked,
+        ..
+    } = ledger.account(client)?.entry(&ledger.config().precision);
+    Some(StatementEntry {
+        transaction_type: transaction.transaction_type(),
+        tx: transaction.tx(),
+        amount: transaction.amount(),
+        available,
+        held,
+        total,
+        locked,
+        fee,
+    })
+}
 
-    // Create a ledger to track client transactions.
-    let mut ledger = Ledger::default();
+/// The rows of each input in turn, tagged with the index of the input they were read from. Each
+/// input is only opened once the previous one has been read, and the first which cannot be
+/// opened or read, for example as it is corrupt or truncated, ends the rows, leaving its error in
+/// `failure`.
+fn read_inputs<'a>(
+    paths: &'a [PathBuf],
+    options: &'a InputOptions,
+    failure: &'a mut Option<Error>,
+) -> impl Iterator<Item = Row> + 'a {
+    let mut inputs = paths.iter().enumerate();
+    let mut rows: Option<Box<dyn Iterator<Item = Row>>> = None;
+    std::iter::from_fn(move || loop {
+        if failure.is_some() {
+            return None;
+        }
+        if let Some(row) = rows.as_mut().and_then(Iterator::next) {
+            if let Err(ParseError::Io(e)) = row.entry {
+                let path = paths[row.position.input].display();
+                *failure = Some(Error::new(e.kind(), format!("failed to read {path}: {e}")));
+                return None;
+            }
+            return Some(row);
+        }
+        let (input, path) = inputs.next()?;
+        match open_input(path) {
+            Ok(rdr) => {
+                let input_rows = read_input_with(rdr, options).map(move |row| Row {
+                    position: Position {
+                        input,
+                        ..row.position
+                    },
+                    ..row
+                });
+                rows = Some(Box::new(input_rows));
+            }
+            Err(e) => *failure = Some(e),
+        }
+    })
+}
 
-    // Load transactions into the ledger.
-    ledger.load(input_file);
+/// Report a rejected row or alert to stderr, prefixed with the input it was read from.
+fn report(paths: &[PathBuf]) -> impl FnMut(Report) + '_ {
+    move |report| {
+        let path = &paths[report.position().input];
+        eprintln!("{}: {}", path.display(), report)
+    }
+}
 
-    // Print client accounts to stdout.
-    ledger.print(std::io::stdout())?;
-    Ok(())
+fn open_input(path: &Path) -> Result<Box<dyn io::Read>, Error> {
+    if path == Path::new("-") {
+        return Ok(Box::new(io::stdin().lock()));
+    }
+    File::open(path)
+        .map(|file| Box::new(file) as Box<dyn io::Read>)
+        .map_err(|e| Error::new(e.kind(), format!("failed to open {}: {e}", path.display())))
+}
+
+fn open_output(path: &str) -> Result<Box<dyn Write>, Error> {
+    match path {
+        "-" => Ok(Box::new(BufWriter::new(io::stdout().lock()))),
+        path => File::create(path)
+            .map(|file| Box::new(BufWriter::new(file)) as Box<dyn Write>)
+            .map_err(|e| Error::new(e.kind(), format!("failed to create {path}: {e}"))),
+    }
 }
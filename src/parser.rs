@@ -1,27 +1,198 @@
+use crate::clients::{ClientMaster, ClientRecord, ClientStatus, RiskRating};
+use crate::compression::Decompressor;
+use crate::fees::{Fee, FeeSchedule};
+use crate::limits::{LimitSchedule, Limits};
+use crate::precision::Precision;
+use crate::reserves::ReservePolicy;
+use crate::rules::{
+    Action, ClientSuspended, DepositWithdrawDispute, DisputeRatio, HighRiskWithdrawal, Rule,
+    RuleSet, WithdrawalVelocity,
+};
 use crate::types::{
-    Amount, Chargeback, ClientId, Deposit, Dispute, Resolve, Transaction, TransactionId,
-    TransactionType, Withdrawal,
+    AccountId, Amount, Arbitrate, Authorize, Capture, Chargeback, ClientId, Close, CreditLimit,
+    Deposit, Dispute, Hold, HoldReason, Interest, Refund, Release, Represent, Resolve, Reversal,
+    Settle, Timestamp, Transaction, TransactionId, TransactionType, Transfer, Void, WalletId,
+    Withdrawal,
 };
+use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
-use std::io;
+use std::{collections::HashMap, fmt, io, str::FromStr};
 use thiserror::Error;
 
 #[derive(Error, Debug)]
 pub enum ParseError {
+    #[error("failed to read input: {0}")]
+    Io(io::Error),
     #[error("deposit is missing an amount")]
     DepositMissing,
     #[error("withdrawal is missing an amount")]
     WithdrawalMissing,
-    #[error("dispute contains unexpected amount")]
-    DisputeUnexpected,
-    #[error("resolve contains unexpected amount")]
-    ResolveUnexpected,
-    #[error("chargeback contains unexpected amount")]
-    ChargebackUnexpected,
-    #[error("error reading csv")]
+    #[error("authorize is missing an amount")]
+    AuthorizeMissing,
+    #[error("void contains unexpected amount")]
+    VoidUnexpected,
+    #[error("reversal contains unexpected amount")]
+    ReversalUnexpected,
+    #[error("credit limit is missing an amount")]
+    CreditLimitMissing,
+    #[error("interest is missing an amount")]
+    InterestMissing,
+    #[error("settle contains unexpected amount")]
+    SettleUnexpected,
+    #[error("close contains unexpected amount")]
+    CloseUnexpected,
+    #[error("hold is missing an amount")]
+    HoldMissing,
+    #[error("hold is missing a reason")]
+    HoldReasonMissing,
+    #[error("transfer is missing an amount")]
+    TransferMissing,
+    #[error("transfer is missing a destination wallet")]
+    TransferWalletMissing,
+    #[error("transfer must move funds between two different wallets")]
+    TransferSameWallet,
+    #[error("amount {0:?} must be greater than zero")]
+    NonPositiveAmount(Amount),
+    #[error("amount {0:?} has more than {1} decimal places")]
+    ExcessPrecision(Amount, u32),
+    #[error("amount {0:?} exceeds the largest amount of {max:?}", max = Amount::MAX)]
+    AmountTooLarge(Amount),
+    #[error("unknown column {0:?}")]
+    UnknownColumn(String),
+    #[error("missing column header {0:?}")]
+    MissingHeader(&'static str),
+    #[error("row skipped as the input's column headers are invalid")]
+    InvalidHeaders,
+    #[error("fee for {0:?} must not be negative")]
+    NegativeFee(TransactionType),
+    #[error("fee percentage for {0:?} must not exceed 100")]
+    InvalidFeePercent(TransactionType),
+    #[error("invalid timestamp {0:?}, expected seconds since the Unix epoch")]
+    InvalidTimestamp(String),
+    #[error("withdrawal limit for tier {0:?} must not be negative")]
+    NegativeLimit(String),
+    #[error("credit limit for client {0:?} must not be negative")]
+    NegativeCreditLimit(ClientId),
+    #[error("reserve percentage for client {0:?} must be between 0 and 100")]
+    InvalidReservePercent(ClientId),
+    #[error("unknown rule {0:?}")]
+    UnknownRule(String),
+    #[error("rule {rule:?} is missing the {parameter:?} parameter")]
+    MissingRuleParameter {
+        rule: String,
+        parameter: &'static str,
+    },
+    #[error("rule {rule:?} parameter {parameter:?} must be {expected}")]
+    InvalidRuleParameter {
+        rule: String,
+        parameter: &'static str,
+        expected: &'static str,
+    },
+    #[error("error reading csv: {0}")]
     Csv(#[from] csv::Error),
 }
 
+/// The column headers which must be present in strict mode.
+const REQUIRED_HEADERS: [&str; 4] = ["type", "client", "tx", "amount"];
+
+/// Every column header understood by `TransactionEntry`, including serde aliases.
+const KNOWN_HEADERS: [&str; 9] = [
+    "type",
+    "transaction_type",
+    "client",
+    "tx",
+    "amount",
+    "timestamp",
+    "reason",
+    "wallet",
+    "to_wallet",
+];
+
+/// Delimited text formats supported for reading transactions and writing accounts.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum Format {
+    #[default]
+    Csv,
+    Tsv,
+}
+
+impl Format {
+    fn delimiter(self) -> u8 {
+        match self {
+            Format::Csv => b',',
+            Format::Tsv => b'\t',
+        }
+    }
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "csv" => Ok(Format::Csv),
+            "tsv" => Ok(Format::Tsv),
+            _ => Err(format!(
+                "unsupported format {s:?}, expected one of: csv, tsv"
+            )),
+        }
+    }
+}
+
+/// How strictly input rows are validated.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum ParseMode {
+    /// Accept any row which can be deserialized into a well formed transaction.
+    #[default]
+    Lenient,
+    /// Additionally reject non-positive amounts, amounts with more decimal places than the
+    /// configured precision, unknown columns and inputs missing any of the expected column
+    /// headers.
+    Strict,
+}
+
+/// Options controlling how transactions are read from an input.
+#[derive(Debug, Default, Clone)]
+pub struct InputOptions {
+    pub format: Format,
+    pub mode: ParseMode,
+    /// The precision amounts are accepted with in strict mode.
+    pub precision: Precision,
+}
+
+/// Where an input row was read from, along with its client and transaction IDs when they could be
+/// read from the row.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Position {
+    /// The index of the input the row was read from, when several inputs are read in turn.
+    pub input: usize,
+    pub line: u64,
+    pub client: Option<ClientId>,
+    pub tx: Option<TransactionId>,
+}
+
+impl fmt::Display for Position {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}", self.line)?;
+        if let Some(ClientId(client)) = self.client {
+            write!(f, ", client {}", client)?;
+        }
+        if let Some(TransactionId(tx)) = self.tx {
+            write!(f, ", tx {}", tx)?;
+        }
+        Ok(())
+    }
+}
+
+/// A single input row: the parsed transaction, or the reason it could not be parsed, the
+/// `Position` it was read from, and the time it occurred if the input has a `timestamp` column.
+#[derive(Debug)]
+pub struct Row {
+    pub position: Position,
+    pub timestamp: Option<Timestamp>,
+    pub entry: Result<Transaction, ParseError>,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct TransactionEntry {
     #[serde(alias = "type")]
@@ -29,6 +200,15 @@ pub struct TransactionEntry {
     pub client: ClientId,
     pub tx: TransactionId,
     pub amount: Option<Amount>,
+    /// Why a hold was placed, only used by `hold` transactions.
+    #[serde(default)]
+    pub reason: Option<HoldReason>,
+    /// The wallet a deposit, withdrawal or transfer targets, the default wallet when empty.
+    #[serde(default)]
+    pub wallet: Option<WalletId>,
+    /// The wallet a transfer moves funds to, only used by `transfer` transactions.
+    #[serde(default)]
+    pub to_wallet: Option<WalletId>,
 }
 
 impl TryFrom<TransactionEntry> for Transaction {
@@ -40,35 +220,121 @@ impl TryFrom<TransactionEntry> for Transaction {
                 client: entry.client,
                 tx: entry.tx,
                 amount: entry.amount.ok_or(ParseError::DepositMissing)?,
+                wallet: entry.wallet.unwrap_or_default(),
             }),
             TransactionType::Withdrawal => Transaction::Withdrawal(Withdrawal {
                 client: entry.client,
                 tx: entry.tx,
                 amount: entry.amount.ok_or(ParseError::WithdrawalMissing)?,
+                wallet: entry.wallet.unwrap_or_default(),
+            }),
+            TransactionType::Dispute => Transaction::Dispute(Dispute {
+                client: entry.client,
+                tx: entry.tx,
+                amount: entry.amount,
+            }),
+            TransactionType::Resolve => Transaction::Resolve(Resolve {
+                client: entry.client,
+                tx: entry.tx,
+                amount: entry.amount,
+            }),
+            TransactionType::Chargeback => Transaction::Chargeback(Chargeback {
+                client: entry.client,
+                tx: entry.tx,
+                amount: entry.amount,
+            }),
+            TransactionType::Represent => Transaction::Represent(Represent {
+                client: entry.client,
+                tx: entry.tx,
+                amount: entry.amount,
+            }),
+            TransactionType::Arbitrate => Transaction::Arbitrate(Arbitrate {
+                client: entry.client,
+                tx: entry.tx,
+                amount: entry.amount,
+            }),
+            TransactionType::Refund => Transaction::Refund(Refund {
+                client: entry.client,
+                tx: entry.tx,
+                amount: entry.amount,
             }),
-            TransactionType::Dispute => {
+            TransactionType::Reversal => {
                 if entry.amount.is_some() {
-                    return Err(ParseError::DisputeUnexpected);
+                    return Err(ParseError::ReversalUnexpected);
                 }
-                Transaction::Dispute(Dispute {
+                Transaction::Reversal(Reversal {
                     client: entry.client,
                     tx: entry.tx,
                 })
             }
-            TransactionType::Resolve => {
+            TransactionType::CreditLimit => Transaction::CreditLimit(CreditLimit {
+                client: entry.client,
+                tx: entry.tx,
+                amount: entry.amount.ok_or(ParseError::CreditLimitMissing)?,
+            }),
+            TransactionType::Interest => Transaction::Interest(Interest {
+                client: entry.client,
+                tx: entry.tx,
+                amount: entry.amount.ok_or(ParseError::InterestMissing)?,
+            }),
+            TransactionType::Hold => Transaction::Hold(Hold {
+                client: entry.client,
+                tx: entry.tx,
+                amount: entry.amount.ok_or(ParseError::HoldMissing)?,
+                reason: entry.reason.ok_or(ParseError::HoldReasonMissing)?,
+            }),
+            TransactionType::Release => Transaction::Release(Release {
+                client: entry.client,
+                tx: entry.tx,
+                amount: entry.amount,
+            }),
+            TransactionType::Close => {
                 if entry.amount.is_some() {
-                    return Err(ParseError::ResolveUnexpected);
+                    return Err(ParseError::CloseUnexpected);
                 }
-                Transaction::Resolve(Resolve {
+                Transaction::Close(Close {
                     client: entry.client,
                     tx: entry.tx,
                 })
             }
-            TransactionType::Chargeback => {
+            TransactionType::Settle => {
                 if entry.amount.is_some() {
-                    return Err(ParseError::ChargebackUnexpected);
+                    return Err(ParseError::SettleUnexpected);
                 }
-                Transaction::Chargeback(Chargeback {
+                Transaction::Settle(Settle {
+                    client: entry.client,
+                    tx: entry.tx,
+                })
+            }
+            TransactionType::Transfer => {
+                let wallet = entry.wallet.unwrap_or_default();
+                let to_wallet = entry.to_wallet.ok_or(ParseError::TransferWalletMissing)?;
+                if wallet == to_wallet {
+                    return Err(ParseError::TransferSameWallet);
+                }
+                Transaction::Transfer(Transfer {
+                    client: entry.client,
+                    tx: entry.tx,
+                    amount: entry.amount.ok_or(ParseError::TransferMissing)?,
+                    wallet,
+                    to_wallet,
+                })
+            }
+            TransactionType::Authorize => Transaction::Authorize(Authorize {
+                client: entry.client,
+                tx: entry.tx,
+                amount: entry.amount.ok_or(ParseError::AuthorizeMissing)?,
+            }),
+            TransactionType::Capture => Transaction::Capture(Capture {
+                client: entry.client,
+                tx: entry.tx,
+                amount: entry.amount,
+            }),
+            TransactionType::Void => {
+                if entry.amount.is_some() {
+                    return Err(ParseError::VoidUnexpected);
+                }
+                Transaction::Void(Void {
                     client: entry.client,
                     tx: entry.tx,
                 })
@@ -78,37 +344,556 @@ impl TryFrom<TransactionEntry> for Transaction {
     }
 }
 
-#[derive(Debug, Serialize)]
+/// A row of the fee schedule configuration file.
+#[derive(Debug, Deserialize)]
+struct FeeEntry {
+    #[serde(rename = "type")]
+    transaction_type: TransactionType,
+    flat: Option<Amount>,
+    percent: Option<Decimal>,
+}
+
+#[derive(Debug, Clone, Serialize)]
 pub struct LedgerEntry {
-    pub client: ClientId,
+    pub client: AccountId,
+    /// The wallet the balances are for, only output when writing a row per wallet.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub wallet: Option<WalletId>,
     pub available: Amount,
     pub held: Amount,
     pub total: Amount,
     pub locked: bool,
+    /// Total fees charged to the client, only output when a fee schedule is configured.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fees: Option<Amount>,
+    /// The client's overdraft, only output when any client has been given credit.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub credit_limit: Option<Amount>,
+    /// How much of the client's overdraft is drawn, only output alongside `credit_limit`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub used_credit: Option<Amount>,
+    /// Funds withheld from the client's deposits, only output when any client has a reserve.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reserve: Option<Amount>,
+    /// Deposited funds which have not yet settled, only output when deposits settle later.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pending: Option<Amount>,
+    /// Whether the client's account has been closed, only output once any account is closed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub closed: Option<bool>,
+    /// The client's master data fields selected for output, only output when selected.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tier: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub country: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub risk_rating: Option<String>,
+    /// Held funds broken down by category, only output once any compliance hold has been placed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub held_disputes: Option<Amount>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub held_authorizations: Option<Amount>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub held_aml: Option<Amount>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub held_fraud: Option<Amount>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub held_legal: Option<Amount>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub held_other: Option<Amount>,
 }
 
-/// Reads and parses data from a CSV input, returning an iterator of `Transaction` results.
+/// A single line of a client statement: a transaction applied to the client's account and the
+/// balances immediately after it.
+#[derive(Debug, Serialize)]
+pub struct StatementEntry {
+    #[serde(rename = "type")]
+    pub transaction_type: TransactionType,
+    pub tx: TransactionId,
+    pub amount: Option<Amount>,
+    pub available: Amount,
+    pub held: Amount,
+    pub total: Amount,
+    pub locked: bool,
+    /// The fee charged for the transaction, only output when a fee schedule is configured.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fee: Option<Amount>,
+}
+
+/// Reads and parses data from a CSV input, returning an iterator of `Row` results.
 /// This allows streaming of CSV data without loading the entire file into memory.
-pub fn read_input(rdr: impl io::Read) -> impl Iterator<Item = Result<Transaction, ParseError>> {
+pub fn read_input(rdr: impl io::Read) -> impl Iterator<Item = Row> {
+    read_input_with(rdr, &InputOptions::default())
+}
+
+/// Reads and parses data from an input using the given `InputOptions`. Gzip and zstd compressed
+/// input is detected and decompressed as it is read.
+pub fn read_input_with(rdr: impl io::Read, options: &InputOptions) -> impl Iterator<Item = Row> {
     let reader = csv::ReaderBuilder::new()
+        .delimiter(options.format.delimiter())
         .trim(csv::Trim::All)
-        .from_reader(rdr);
-    reader.into_deserialize::<TransactionEntry>().map(|entry| {
+        .from_reader(Decompressor::new(rdr));
+    let (mode, precision) = (options.mode, options.precision);
+    let mut records = reader.into_records();
+    let mut headers = None;
+    let mut headers_valid = true;
+    let mut failed = false;
+
+    std::iter::from_fn(move || {
+        // Once the input cannot be read, for example as it is corrupt or truncated, it ends.
+        if failed {
+            return None;
+        }
+
+        // Headers are read before the first record, after which they are cached. In strict mode
+        // invalid headers are rejected even if the input has no records, and reject every row.
+        let headers: &csv::StringRecord = match &mut headers {
+            Some(headers) => headers,
+            None => {
+                let read = match records.reader_mut().headers() {
+                    Ok(read) => read.clone(),
+                    Err(e) => {
+                        failed = true;
+                        let position = Position {
+                            line: 1,
+                            ..Position::default()
+                        };
+                        return Some(Row {
+                            position,
+                            timestamp: None,
+                            entry: Err(read_error(e)),
+                        });
+                    }
+                };
+                let headers = headers.insert(read);
+                if mode == ParseMode::Strict {
+                    if let Err(e) = validate_headers(headers) {
+                        headers_valid = false;
+                        let position = Position {
+                            line: 1,
+                            ..Position::default()
+                        };
+                        return Some(Row {
+                            position,
+                            timestamp: None,
+                            entry: Err(e),
+                        });
+                    }
+                }
+                headers
+            }
+        };
+        let record = records.next()?;
+
+        // The csv crate positions a record at the first of any blank lines preceding it, so the
+        // line is instead taken from where the record ended, which is exact for single line rows.
+        let start = match &record {
+            Ok(record) => record.position(),
+            Err(e) => e.position(),
+        };
+        let end = records.reader().position().line().saturating_sub(1);
+        let line = start.map_or(end, |start| start.line().max(end));
+
+        if !headers_valid {
+            let position = Position {
+                line,
+                ..Position::default()
+            };
+            return Some(Row {
+                position,
+                timestamp: None,
+                entry: Err(ParseError::InvalidHeaders),
+            });
+        }
+        let record = match record {
+            Ok(record) => record,
+            Err(e) => {
+                failed = e.is_io_error();
+                let position = Position {
+                    line,
+                    ..Position::default()
+                };
+                return Some(Row {
+                    position,
+                    timestamp: None,
+                    entry: Err(read_error(e)),
+                });
+            }
+        };
+
+        let field = |name: &str| {
+            let index = headers.iter().position(|header| header == name)?;
+            record.get(index)
+        };
+        let position = Position {
+            input: 0,
+            line,
+            client: field("client").and_then(|id| id.parse().ok()).map(ClientId),
+            tx: field("tx")
+                .and_then(|id| id.parse().ok())
+                .map(TransactionId),
+        };
+
+        // An empty timestamp is treated as absent, while one which fails to parse rejects the row.
+        let timestamp = field("timestamp")
+            .filter(|timestamp| !timestamp.is_empty())
+            .map(|timestamp| {
+                timestamp
+                    .parse()
+                    .map(Timestamp)
+                    .map_err(|_| ParseError::InvalidTimestamp(timestamp.to_string()))
+            })
+            .transpose();
+
         // Map potential csv::Error then convert into a Transaction
-        let transaction_entry = entry.map_err(ParseError::Csv)?;
-        Transaction::try_from(transaction_entry)
+        let entry = record
+            .deserialize::<TransactionEntry>(Some(headers))
+            .map_err(ParseError::Csv)
+            .and_then(Transaction::try_from)
+            .and_then(validate_magnitude)
+            .and_then(|transaction| match mode {
+                ParseMode::Lenient => Ok(transaction),
+                ParseMode::Strict => validate_amount(transaction, &precision),
+            });
+        let (timestamp, entry) = match timestamp {
+            Ok(timestamp) => (timestamp, entry),
+            Err(e) => (None, Err(e)),
+        };
+        Some(Row {
+            position,
+            timestamp,
+            entry,
+        })
     })
 }
 
-/// Write a sequence of `LedgerEntry` records to a CSV output.
+/// The error for a row which could not be read, which is an `Io` error when the input itself
+/// could not be read.
+fn read_error(e: csv::Error) -> ParseError {
+    match e.is_io_error() {
+        true => ParseError::Io(e.into()),
+        false => ParseError::Csv(e),
+    }
+}
+
+/// Ensure that every expected column header is present and that there are no unknown columns.
+fn validate_headers(headers: &csv::StringRecord) -> Result<(), ParseError> {
+    if let Some(header) = REQUIRED_HEADERS.into_iter().find(|required| {
+        !headers.iter().any(|header| {
+            header == *required || (*required == "type" && header == "transaction_type")
+        })
+    }) {
+        return Err(ParseError::MissingHeader(header));
+    }
+    if let Some(header) = headers
+        .iter()
+        .find(|header| !KNOWN_HEADERS.contains(header))
+    {
+        return Err(ParseError::UnknownColumn(header.to_string()));
+    }
+    Ok(())
+}
+
+/// Ensure that a transaction's amount, if any, is no larger than the largest amount a row may
+/// carry, so that the balances it is applied to cannot overflow.
+fn validate_magnitude(transaction: Transaction) -> Result<Transaction, ParseError> {
+    match transaction.amount() {
+        Some(amount) if amount.0.abs() > Amount::MAX.0 => Err(ParseError::AmountTooLarge(amount)),
+        _ => Ok(transaction),
+    }
+}
+
+/// Ensure that a transaction's amount, if any, is positive and within the given precision.
+fn validate_amount(
+    transaction: Transaction,
+    precision: &Precision,
+) -> Result<Transaction, ParseError> {
+    match transaction.amount() {
+        // A credit limit of zero withdraws a client's overdraft.
+        Some(amount)
+            if amount.0.is_zero() && matches!(transaction, Transaction::CreditLimit(_)) =>
+        {
+            Ok(transaction)
+        }
+        Some(amount) if amount.0 <= Decimal::ZERO => Err(ParseError::NonPositiveAmount(amount)),
+        Some(amount) if precision.exceeds(amount) => Err(ParseError::ExcessPrecision(
+            amount,
+            precision.decimal_places,
+        )),
+        _ => Ok(transaction),
+    }
+}
+
+/// Reads a fee schedule in CSV format, with `type`, `flat` and `percent` columns. Missing flat or
+/// percentage fees default to zero.
+pub fn read_fee_schedule(rdr: impl io::Read) -> Result<FeeSchedule, ParseError> {
+    let mut reader = csv::ReaderBuilder::new()
+        .trim(csv::Trim::All)
+        .from_reader(rdr);
+    let mut schedule = FeeSchedule::default();
+    for entry in reader.deserialize::<FeeEntry>() {
+        let entry = entry?;
+        let fee = Fee {
+            flat: entry.flat.unwrap_or_default(),
+            percent: entry.percent.unwrap_or_default(),
+        };
+        if fee.flat.0.is_sign_negative() || fee.percent.is_sign_negative() {
+            return Err(ParseError::NegativeFee(entry.transaction_type));
+        }
+        if fee.percent > Decimal::ONE_HUNDRED {
+            return Err(ParseError::InvalidFeePercent(entry.transaction_type));
+        }
+        schedule.insert(entry.transaction_type, fee);
+    }
+    Ok(schedule)
+}
+
+/// A row of the withdrawal limits configuration file.
+#[derive(Debug, Deserialize)]
+struct LimitEntry {
+    tier: String,
+    per_transaction: Option<Amount>,
+    daily: Option<Amount>,
+    monthly: Option<Amount>,
+}
+
+/// A row of the rules configuration file. Each rule uses only the parameters relevant to it.
+#[derive(Debug, Deserialize)]
+struct RuleEntry {
+    rule: String,
+    action: Action,
+    count: Option<usize>,
+    window: Option<usize>,
+    ratio: Option<Decimal>,
+    minimum: Option<usize>,
+}
+
+/// A row of the client master data file, where every column other than `client` is optional.
+#[derive(Debug, Deserialize)]
+struct ClientMasterEntry {
+    client: ClientId,
+    #[serde(default)]
+    tier: Option<String>,
+    #[serde(default)]
+    status: Option<ClientStatus>,
+    #[serde(default)]
+    country: Option<String>,
+    #[serde(default)]
+    risk_rating: Option<RiskRating>,
+    #[serde(default)]
+    credit_limit: Option<Amount>,
+}
+
+/// A row of the credit limits configuration file.
+#[derive(Debug, Deserialize)]
+struct CreditLimitEntry {
+    client: ClientId,
+    credit_limit: Amount,
+}
+
+/// A row of the rolling reserves configuration file.
+#[derive(Debug, Deserialize)]
+struct ReserveEntry {
+    client: ClientId,
+    percent: Decimal,
+    days: u64,
+}
+
+/// A row of the client tiers configuration file.
+#[derive(Debug, Deserialize)]
+struct TierEntry {
+    client: ClientId,
+    tier: String,
+}
+
+/// Reads withdrawal limits in CSV format, with `tier`, `per_transaction`, `daily` and `monthly`
+/// columns, into the given schedule. Missing limits are unlimited.
+pub fn read_limits(rdr: impl io::Read, schedule: &mut LimitSchedule) -> Result<(), ParseError> {
+    let mut reader = csv::ReaderBuilder::new()
+        .trim(csv::Trim::All)
+        .from_reader(rdr);
+    for entry in reader.deserialize::<LimitEntry>() {
+        let entry = entry?;
+        let limits = Limits {
+            per_transaction: entry.per_transaction,
+            daily: entry.daily,
+            monthly: entry.monthly,
+        };
+        let negative = [limits.per_transaction, limits.daily, limits.monthly]
+            .into_iter()
+            .flatten()
+            .any(|limit| limit.0.is_sign_negative());
+        if negative {
+            return Err(ParseError::NegativeLimit(entry.tier));
+        }
+        schedule.insert_tier(entry.tier, limits);
+    }
+    Ok(())
+}
+
+/// Reads the tier each client belongs to in CSV format, with `client` and `tier` columns, into
+/// the given schedule.
+pub fn read_client_tiers(
+    rdr: impl io::Read,
+    schedule: &mut LimitSchedule,
+) -> Result<(), ParseError> {
+    let mut reader = csv::ReaderBuilder::new()
+        .trim(csv::Trim::All)
+        .from_reader(rdr);
+    for entry in reader.deserialize::<TierEntry>() {
+        let entry = entry?;
+        schedule.assign(entry.client, entry.tier);
+    }
+    Ok(())
+}
+
+/// Reads the credit limit of each client with an overdraft in CSV format, with `client` and
+/// `credit_limit` columns.
+pub fn read_credit_limits(rdr: impl io::Read) -> Result<HashMap<ClientId, Amount>, ParseError> {
+    let mut reader = csv::ReaderBuilder::new()
+        .trim(csv::Trim::All)
+        .from_reader(rdr);
+    let mut credit_limits = HashMap::new();
+    for entry in reader.deserialize::<CreditLimitEntry>() {
+        let entry = entry?;
+        if entry.credit_limit.0.is_sign_negative() {
+            return Err(ParseError::NegativeCreditLimit(entry.client));
+        }
+        credit_limits.insert(entry.client, entry.credit_limit);
+    }
+    Ok(credit_limits)
+}
+
+/// Reads the master data of each client in CSV format, with `client`, `tier`, `status`,
+/// `country`, `risk_rating` and `credit_limit` columns. Every column other than `client` may be
+/// omitted or left empty.
+pub fn read_client_master(rdr: impl io::Read) -> Result<ClientMaster, ParseError> {
+    let mut reader = csv::ReaderBuilder::new()
+        .trim(csv::Trim::All)
+        .from_reader(rdr);
+    let mut clients = ClientMaster::default();
+    for entry in reader.deserialize::<ClientMasterEntry>() {
+        let entry = entry?;
+        if entry
+            .credit_limit
+            .is_some_and(|limit| limit.0.is_sign_negative())
+        {
+            return Err(ParseError::NegativeCreditLimit(entry.client));
+        }
+        let non_empty = |value: Option<String>| value.filter(|value| !value.is_empty());
+        let record = ClientRecord {
+            tier: non_empty(entry.tier),
+            status: entry.status.unwrap_or_default(),
+            country: non_empty(entry.country),
+            risk_rating: entry.risk_rating,
+            credit_limit: entry.credit_limit,
+        };
+        clients.insert(entry.client, record);
+    }
+    Ok(clients)
+}
+
+/// Reads the rolling reserve of each high risk client in CSV format, with `client`, `percent` and
+/// `days` columns.
+pub fn read_reserves(rdr: impl io::Read) -> Result<HashMap<ClientId, ReservePolicy>, ParseError> {
+    let mut reader = csv::ReaderBuilder::new()
+        .trim(csv::Trim::All)
+        .from_reader(rdr);
+    let mut reserves = HashMap::new();
+    for entry in reader.deserialize::<ReserveEntry>() {
+        let entry = entry?;
+        if entry.percent.is_sign_negative() || entry.percent > Decimal::ONE_HUNDRED {
+            return Err(ParseError::InvalidReservePercent(entry.client));
+        }
+        let policy = ReservePolicy {
+            percent: entry.percent,
+            days: entry.days,
+        };
+        reserves.insert(entry.client, policy);
+    }
+    Ok(reserves)
+}
+
+/// Reads the fraud and velocity rules to evaluate in CSV format, with `rule`, `action`, `count`,
+/// `window`, `ratio` and `minimum` columns. The rules and their parameters are:
+///
+/// - `withdrawal_velocity`: `count` withdrawals within a client's last `window` transactions.
+/// - `deposit_withdraw_dispute`: a dispute of a deposit followed by a withdrawal within the
+///   client's next `window` transactions, by default the very next one.
+/// - `dispute_ratio`: a ratio of disputes to deposits above `ratio`, once a client has made at
+///   least `minimum` deposits.
+/// - `client_status`: any transaction of a client whose master data marks them as suspended.
+/// - `client_risk`: a withdrawal of a client whose master data rates them as high risk.
+///
+/// A `count` or `window` of 0, or a `ratio` outside 0 to 1, is rejected.
+pub fn read_rules(rdr: impl io::Read) -> Result<RuleSet, ParseError> {
+    let mut reader = csv::ReaderBuilder::new()
+        .trim(csv::Trim::All)
+        .from_reader(rdr);
+    let mut rules = RuleSet::default();
+    for entry in reader.deserialize::<RuleEntry>() {
+        let entry = entry?;
+        let missing = |parameter| ParseError::MissingRuleParameter {
+            rule: entry.rule.clone(),
+            parameter,
+        };
+        let invalid = |parameter, expected| ParseError::InvalidRuleParameter {
+            rule: entry.rule.clone(),
+            parameter,
+            expected,
+        };
+        let positive = |value: usize, parameter| match value {
+            0 => Err(invalid(parameter, "at least 1")),
+            value => Ok(value),
+        };
+        let rule: Box<dyn Rule> = match entry.rule.as_str() {
+            WithdrawalVelocity::NAME => Box::new(WithdrawalVelocity::new(
+                positive(entry.count.ok_or_else(|| missing("count"))?, "count")?,
+                positive(entry.window.ok_or_else(|| missing("window"))?, "window")?,
+            )),
+            DepositWithdrawDispute::NAME => Box::new(DepositWithdrawDispute::new(positive(
+                entry.window.unwrap_or(1),
+                "window",
+            )?)),
+            DisputeRatio::NAME => {
+                let ratio = entry.ratio.ok_or_else(|| missing("ratio"))?;
+                if !(Decimal::ZERO..=Decimal::ONE).contains(&ratio) {
+                    return Err(invalid("ratio", "between 0 and 1"));
+                }
+                Box::new(DisputeRatio::new(ratio, entry.minimum.unwrap_or_default()))
+            }
+            ClientSuspended::NAME => Box::new(ClientSuspended),
+            HighRiskWithdrawal::NAME => Box::new(HighRiskWithdrawal),
+            _ => return Err(ParseError::UnknownRule(entry.rule)),
+        };
+        rules.add(entry.action, rule);
+    }
+    Ok(rules)
+}
+
+/// Write a sequence of records, such as `LedgerEntry`, to a CSV output.
 pub fn write_output(
     wtr: impl io::Write,
-    iter: impl Iterator<Item = LedgerEntry>,
+    iter: impl Iterator<Item = impl Serialize>,
 ) -> Result<(), std::io::Error> {
-    let mut writer = csv::Writer::from_writer(wtr);
+    write_output_with(wtr, Format::Csv, iter)
+}
+
+/// Write a sequence of records to an output in the given `Format`.
+pub fn write_output_with(
+    wtr: impl io::Write,
+    format: Format,
+    iter: impl Iterator<Item = impl Serialize>,
+) -> Result<(), std::io::Error> {
+    let mut writer = csv::WriterBuilder::new()
+        .delimiter(format.delimiter())
+        .from_writer(wtr);
     for entry in iter {
         writer.serialize(entry)?
     }
+    writer.flush()?;
     Ok(())
 }
 
@@ -121,7 +906,9 @@ mod tests {
             Amount, Chargeback, ClientId, Deposit, Dispute, Resolve, TransactionId, Withdrawal,
         },
     };
+    use flate2::{write::GzEncoder, Compression};
     use rust_decimal::Decimal;
+    use std::io::Write;
 
     #[test]
     fn read_transactions() {
@@ -134,7 +921,7 @@ mod tests {
             .as_bytes();
 
         let got = read_input(rdr)
-            .map(|transaction| transaction.unwrap())
+            .map(|row| row.entry.unwrap())
             .collect::<Vec<_>>();
 
         let want = [
@@ -142,23 +929,55 @@ mod tests {
                 client: ClientId(1),
                 tx: TransactionId(1),
                 amount: Amount::from(Decimal::new(14567, 4)),
+                wallet: WalletId::default(),
             }),
             Transaction::Withdrawal(Withdrawal {
                 client: ClientId(1),
                 tx: TransactionId(4),
                 amount: Amount::from(Decimal::new(11864, 4)),
+                wallet: WalletId::default(),
             }),
             Transaction::Dispute(Dispute {
                 client: ClientId(1),
                 tx: TransactionId(1),
+                amount: None,
             }),
             Transaction::Resolve(Resolve {
                 client: ClientId(2),
                 tx: TransactionId(3),
+                amount: None,
             }),
             Transaction::Chargeback(Chargeback {
                 client: ClientId(2),
                 tx: TransactionId(2),
+                amount: None,
+            }),
+        ];
+
+        assert_eq!(got, want);
+    }
+
+    #[test]
+    fn read_representments() {
+        let rdr = "type, client, tx, amount\n
+                        represent, 2, 2,\n
+                        arbitrate, 2, 2, 0.5\n"
+            .as_bytes();
+
+        let got = read_input(rdr)
+            .map(|row| row.entry.unwrap())
+            .collect::<Vec<_>>();
+
+        let want = [
+            Transaction::Represent(Represent {
+                client: ClientId(2),
+                tx: TransactionId(2),
+                amount: None,
+            }),
+            Transaction::Arbitrate(Arbitrate {
+                client: ClientId(2),
+                tx: TransactionId(2),
+                amount: Some(Amount::from(Decimal::new(5, 1))),
             }),
         ];
 
@@ -174,16 +993,20 @@ mod tests {
                 available: Amount::from(Decimal::new(16587, 4)),
                 held: Amount::from(Decimal::new(47654, 4)),
                 locked: false,
+                ..Account::new(ClientId(1))
             },
             Account {
                 client_id: ClientId(2),
                 available: Amount::from(Decimal::new(63625, 4)),
                 held: Amount::from(Decimal::new(94532, 4)),
                 locked: true,
+                ..Account::new(ClientId(2))
             },
         ];
 
-        let entries = accounts.iter().map(|account| LedgerEntry::from(account));
+        let entries = accounts
+            .iter()
+            .map(|account| account.entry(&Precision::default()));
 
         let mut buffer = Cursor::new(Vec::new());
         write_output(&mut buffer, entries.into_iter()).expect("Failed to write output");
@@ -196,6 +1019,423 @@ mod tests {
         assert_eq!(got, want);
     }
 
+    #[test]
+    fn read_tsv_transactions() {
+        let rdr = "type\tclient\ttx\tamount\ndeposit\t1\t1\t2.5\ndispute\t1\t1\t\n".as_bytes();
+        let options = InputOptions {
+            format: Format::Tsv,
+            ..InputOptions::default()
+        };
+
+        let got = read_input_with(rdr, &options)
+            .map(|row| row.entry.unwrap())
+            .collect::<Vec<_>>();
+
+        let want = [
+            Transaction::Deposit(Deposit {
+                client: ClientId(1),
+                tx: TransactionId(1),
+                amount: Amount::from(Decimal::new(25, 1)),
+                wallet: WalletId::default(),
+            }),
+            Transaction::Dispute(Dispute {
+                client: ClientId(1),
+                tx: TransactionId(1),
+                amount: None,
+            }),
+        ];
+
+        assert_eq!(got, want);
+    }
+
+    #[test]
+    fn read_corrupt_compressed_input() {
+        let csv = "type, client, tx, amount\ndeposit, 1, 1, 2.5\n";
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(csv.as_bytes()).unwrap();
+        let gzip = encoder.finish().unwrap();
+        let zstd = zstd::encode_all(csv.as_bytes(), 0).unwrap();
+
+        // Assert that corrupt input, and input truncated before its first record, end the rows
+        // with an error reading the input rather than being taken as empty.
+        for input in [b"\x1f\x8bgarbage".as_slice(), &gzip[..12], &zstd[..8]] {
+            let got = read_input(input).collect::<Vec<_>>();
+            assert_eq!(got.len(), 1);
+            assert!(matches!(got[0].entry, Err(ParseError::Io(_))));
+        }
+    }
+
+    #[test]
+    fn read_compressed_transactions() {
+        let csv = "type, client, tx, amount\ndeposit, 1, 1, 2.5\n";
+        let compressed = zstd::encode_all(csv.as_bytes(), 0).unwrap();
+
+        let got = read_input(compressed.as_slice())
+            .map(|row| row.entry.unwrap())
+            .collect::<Vec<_>>();
+
+        let want = [Transaction::Deposit(Deposit {
+            client: ClientId(1),
+            tx: TransactionId(1),
+            amount: Amount::from(Decimal::new(25, 1)),
+            wallet: WalletId::default(),
+        })];
+
+        assert_eq!(got, want);
+    }
+
+    #[test]
+    fn read_positions() {
+        let rdr = "type, client, tx, amount\n\
+                   deposit, 1, 1, 1.0\n\
+                   \n\
+                   withdrawal, 2, 7,\n\
+                   deposit, 3, x, 1.0\n"
+            .as_bytes();
+
+        let got = read_input(rdr).collect::<Vec<_>>();
+
+        // Assert that the line numbers account for the header and blank lines.
+        let positions = got.iter().map(|row| row.position).collect::<Vec<_>>();
+        assert_eq!(
+            positions,
+            [
+                Position {
+                    input: 0,
+                    line: 2,
+                    client: Some(ClientId(1)),
+                    tx: Some(TransactionId(1))
+                },
+                Position {
+                    input: 0,
+                    line: 4,
+                    client: Some(ClientId(2)),
+                    tx: Some(TransactionId(7))
+                },
+                Position {
+                    input: 0,
+                    line: 5,
+                    client: Some(ClientId(3)),
+                    tx: None
+                },
+            ]
+        );
+
+        // Assert that the malformed rows are rejected.
+        assert!(got[0].entry.is_ok());
+        assert!(matches!(got[1].entry, Err(ParseError::WithdrawalMissing)));
+        assert!(matches!(got[2].entry, Err(ParseError::Csv(_))));
+        assert_eq!(got[1].position.to_string(), "line 4, client 2, tx 7");
+    }
+
+    #[test]
+    fn strict_amounts() {
+        let rdr = "type, client, tx, amount\n\
+                   deposit, 1, 1, 1.00000\n\
+                   deposit, 1, 2, 1.00001\n\
+                   deposit, 1, 3, 0\n\
+                   withdrawal, 1, 4, -1\n\
+                   dispute, 1, 1,\n\
+                   credit_limit, 1, 5, 0\n";
+        let strict = InputOptions {
+            mode: ParseMode::Strict,
+            ..InputOptions::default()
+        };
+
+        // Assert that lenient mode accepts every row.
+        assert!(read_input(rdr.as_bytes()).all(|row| row.entry.is_ok()));
+
+        let got = read_input_with(rdr.as_bytes(), &strict)
+            .map(|row| row.entry)
+            .collect::<Vec<_>>();
+        assert!(got[0].is_ok());
+        assert!(matches!(got[1], Err(ParseError::ExcessPrecision(_, 4))));
+        assert!(matches!(got[2], Err(ParseError::NonPositiveAmount(_))));
+        assert!(matches!(got[3], Err(ParseError::NonPositiveAmount(_))));
+        assert!(got[4].is_ok());
+
+        // Assert that a credit limit may be withdrawn with a limit of zero.
+        assert!(got[5].is_ok());
+
+        // Assert that the accepted precision is configurable.
+        let precise = InputOptions {
+            precision: Precision {
+                decimal_places: 5,
+                ..Precision::default()
+            },
+            ..strict
+        };
+        let mut rows = read_input_with(rdr.as_bytes(), &precise);
+        assert!(rows.nth(1).unwrap().entry.is_ok());
+    }
+
+    #[test]
+    fn amount_too_large() {
+        let rdr = "type, client, tx, amount\n\
+                   deposit, 1, 1, 1000000000000000\n\
+                   deposit, 1, 2, 1000000000000000.0001\n\
+                   withdrawal, 1, 3, -79228162514264337593543950335\n";
+
+        // Assert that amounts beyond the largest amount are rejected in lenient mode too.
+        let got = read_input(rdr.as_bytes())
+            .map(|row| row.entry)
+            .collect::<Vec<_>>();
+        assert!(got[0].is_ok());
+        assert!(matches!(got[1], Err(ParseError::AmountTooLarge(_))));
+        assert!(matches!(got[2], Err(ParseError::AmountTooLarge(_))));
+    }
+
+    #[test]
+    fn strict_headers() {
+        let strict = InputOptions {
+            mode: ParseMode::Strict,
+            ..InputOptions::default()
+        };
+        let read = |rdr: &str| {
+            read_input_with(rdr.as_bytes(), &strict)
+                .map(|row| row.entry)
+                .collect::<Vec<_>>()
+        };
+
+        // Assert that an unknown column rejects the input as a whole, with every row skipped.
+        let got =
+            read("type, client, tx, amount, note\ndeposit, 1, 1, 1.0, a\ndeposit, 1, 2, 1.0, b\n");
+        assert_eq!(got.len(), 3);
+        assert!(matches!(&got[0], Err(ParseError::UnknownColumn(column)) if column == "note"));
+        assert!(matches!(got[1], Err(ParseError::InvalidHeaders)));
+        assert!(matches!(got[2], Err(ParseError::InvalidHeaders)));
+
+        // Assert that a missing header row is rejected.
+        let got = read("deposit, 1, 1, 1.0\ndeposit, 1, 2, 1.0\n");
+        assert_eq!(got.len(), 2);
+        assert!(matches!(got[0], Err(ParseError::MissingHeader("type"))));
+
+        // Assert that invalid headers are rejected even without any records.
+        let got = read("type, client, tx, note\n");
+        assert_eq!(got.len(), 1);
+        assert!(matches!(got[0], Err(ParseError::MissingHeader("amount"))));
+
+        let got = read("type, client, tx\ndispute, 1, 1\n");
+        assert!(matches!(got[0], Err(ParseError::MissingHeader("amount"))));
+
+        // Assert that the serde alias for the type column is accepted.
+        let got = read("transaction_type, client, tx, amount\ndeposit, 1, 1, 1.0\n");
+        assert!(got[0].is_ok());
+    }
+
+    #[test]
+    fn read_fees() {
+        let rdr = "type, flat, percent\n\
+                   withdrawal, 0.5, 1\n\
+                   chargeback, 15,\n"
+            .as_bytes();
+
+        let schedule = read_fee_schedule(rdr).unwrap();
+
+        assert_eq!(
+            schedule.fee(TransactionType::Withdrawal, Some(Amount::from(10))),
+            Some(Amount::from(Decimal::new(6, 1)))
+        );
+        assert_eq!(
+            schedule.fee(TransactionType::Chargeback, None),
+            Some(Amount::from(15))
+        );
+
+        let rdr = "type, flat, percent\ndeposit, -1, 0\n".as_bytes();
+        assert!(matches!(
+            read_fee_schedule(rdr),
+            Err(ParseError::NegativeFee(TransactionType::Deposit))
+        ));
+
+        let rdr = "type, flat, percent\nwithdrawal, 0, 100.5\n".as_bytes();
+        assert!(matches!(
+            read_fee_schedule(rdr),
+            Err(ParseError::InvalidFeePercent(TransactionType::Withdrawal))
+        ));
+    }
+
+    #[test]
+    fn read_timestamps() {
+        let rdr = "type, client, tx, amount, timestamp\n\
+                   deposit, 1, 1, 1.0, 1700000000\n\
+                   deposit, 1, 2, 1.0,\n\
+                   deposit, 1, 3, 1.0, yesterday\n"
+            .as_bytes();
+
+        let got = read_input(rdr).collect::<Vec<_>>();
+
+        assert_eq!(got[0].timestamp, Some(Timestamp(1_700_000_000)));
+        assert!(got[0].entry.is_ok());
+        assert_eq!(got[1].timestamp, None);
+        assert!(got[1].entry.is_ok());
+        assert!(
+            matches!(&got[2].entry, Err(ParseError::InvalidTimestamp(timestamp)) if timestamp == "yesterday")
+        );
+    }
+
+    #[test]
+    fn read_limits_and_tiers() {
+        let mut schedule = LimitSchedule::default();
+        let limits = "tier, per_transaction, daily, monthly\n\
+                      default, 100, 500,\n\
+                      premium, , , 10000\n"
+            .as_bytes();
+        let tiers = "client, tier\n2, premium\n".as_bytes();
+
+        read_limits(limits, &mut schedule).unwrap();
+        read_client_tiers(tiers, &mut schedule).unwrap();
+
+        assert_eq!(
+            schedule.limits(ClientId(1)),
+            Some(&Limits {
+                per_transaction: Some(Amount::from(100)),
+                daily: Some(Amount::from(500)),
+                monthly: None,
+            })
+        );
+        assert_eq!(
+            schedule.limits(ClientId(2)),
+            Some(&Limits {
+                per_transaction: None,
+                daily: None,
+                monthly: Some(Amount::from(10000)),
+            })
+        );
+
+        let limits = "tier, per_transaction, daily, monthly\ndefault, -1, ,\n".as_bytes();
+        assert!(matches!(
+            read_limits(limits, &mut schedule),
+            Err(ParseError::NegativeLimit(tier)) if tier == "default"
+        ));
+    }
+
+    #[test]
+    fn read_credit_limit_file() {
+        let rdr = "client, credit_limit\n1, 100\n2, 0\n".as_bytes();
+        let credit_limits = read_credit_limits(rdr).unwrap();
+        assert_eq!(credit_limits.get(&ClientId(1)), Some(&Amount::from(100)));
+        assert_eq!(credit_limits.get(&ClientId(2)), Some(&Amount::from(0)));
+
+        let rdr = "client, credit_limit\n3, -5\n".as_bytes();
+        assert!(matches!(
+            read_credit_limits(rdr),
+            Err(ParseError::NegativeCreditLimit(ClientId(3)))
+        ));
+    }
+
+    #[test]
+    fn read_reserve_file() {
+        let rdr = "client, percent, days\n1, 10, 30\n".as_bytes();
+        let reserves = read_reserves(rdr).unwrap();
+        assert_eq!(
+            reserves.get(&ClientId(1)),
+            Some(&ReservePolicy {
+                percent: Decimal::TEN,
+                days: 30,
+            })
+        );
+
+        for percent in ["-1", "101"] {
+            let rdr = format!("client, percent, days\n2, {percent}, 30\n");
+            assert!(matches!(
+                read_reserves(rdr.as_bytes()),
+                Err(ParseError::InvalidReservePercent(ClientId(2)))
+            ));
+        }
+    }
+
+    #[test]
+    fn read_client_master_file() {
+        let rdr = "client, tier, status, country, risk_rating, credit_limit\n\
+                   1, premium, suspended, GB, high, 100\n\
+                   2, , , , ,\n"
+            .as_bytes();
+        let clients = read_client_master(rdr).unwrap();
+        assert_eq!(
+            clients.get(ClientId(1)),
+            Some(&ClientRecord {
+                tier: Some("premium".to_string()),
+                status: ClientStatus::Suspended,
+                country: Some("GB".to_string()),
+                risk_rating: Some(RiskRating::High),
+                credit_limit: Some(Amount::from(100)),
+            })
+        );
+        assert_eq!(clients.get(ClientId(2)), Some(&ClientRecord::default()));
+
+        // Assert that every column other than the client may be omitted.
+        let rdr = "client, country\n3, FR\n".as_bytes();
+        let clients = read_client_master(rdr).unwrap();
+        assert_eq!(
+            clients.get(ClientId(3)).unwrap().country.as_deref(),
+            Some("FR")
+        );
+
+        let rdr = "client, credit_limit\n4, -1\n".as_bytes();
+        assert!(matches!(
+            read_client_master(rdr),
+            Err(ParseError::NegativeCreditLimit(ClientId(4)))
+        ));
+    }
+
+    #[test]
+    fn read_rule_set() {
+        let rdr = "rule, action, count, window, ratio, minimum\n\
+                   withdrawal_velocity, flag, 3, 5, ,\n\
+                   deposit_withdraw_dispute, lock, , , ,\n\
+                   dispute_ratio, reject, , , 0.5, 4\n\
+                   client_status, reject, , , ,\n\
+                   client_risk, flag, , , ,\n"
+            .as_bytes();
+        assert!(!read_rules(rdr).unwrap().is_empty());
+
+        let rdr = "rule, action, count, window\nwithdrawal_velocity, flag, 3,\n".as_bytes();
+        assert!(matches!(
+            read_rules(rdr),
+            Err(ParseError::MissingRuleParameter {
+                parameter: "window",
+                ..
+            })
+        ));
+
+        for (rdr, parameter) in [
+            (
+                "rule, action, count, window\nwithdrawal_velocity, flag, 0, 5\n",
+                "count",
+            ),
+            (
+                "rule, action, count, window\nwithdrawal_velocity, flag, 3, 0\n",
+                "window",
+            ),
+            (
+                "rule, action, window\ndeposit_withdraw_dispute, lock, 0\n",
+                "window",
+            ),
+            ("rule, action, ratio\ndispute_ratio, flag, 1.5\n", "ratio"),
+            ("rule, action, ratio\ndispute_ratio, flag, -0.1\n", "ratio"),
+        ] {
+            assert!(matches!(
+                read_rules(rdr.as_bytes()),
+                Err(ParseError::InvalidRuleParameter { parameter: got, .. }) if got == parameter
+            ));
+        }
+
+        let rdr = "rule, action\nlarge_deposit, flag\n".as_bytes();
+        assert!(
+            matches!(read_rules(rdr), Err(ParseError::UnknownRule(rule)) if rule == "large_deposit")
+        );
+
+        let rdr = "rule, action\ndispute_ratio, ignore\n".as_bytes();
+        assert!(matches!(read_rules(rdr), Err(ParseError::Csv(_))));
+    }
+
+    #[test]
+    fn parse_format() {
+        assert_eq!("csv".parse(), Ok(Format::Csv));
+        assert_eq!("tsv".parse(), Ok(Format::Tsv));
+        assert!("json".parse::<Format>().is_err());
+    }
+
     #[test]
     fn deposit() {
         assert!(Transaction::try_from(TransactionEntry {
@@ -203,6 +1443,9 @@ mod tests {
             client: ClientId(1),
             tx: TransactionId(1),
             amount: Some(Amount::from(1)),
+            reason: None,
+            wallet: None,
+            to_wallet: None,
         })
         .is_ok());
     }
@@ -215,6 +1458,9 @@ mod tests {
                 client: ClientId(1),
                 tx: TransactionId(1),
                 amount: None,
+                reason: None,
+                wallet: None,
+                to_wallet: None,
             }),
             Err(ParseError::DepositMissing)
         ));
@@ -227,6 +1473,9 @@ mod tests {
             client: ClientId(1),
             tx: TransactionId(1),
             amount: Some(Amount::from(1)),
+            reason: None,
+            wallet: None,
+            to_wallet: None,
         })
         .is_ok());
     }
@@ -239,6 +1488,9 @@ mod tests {
                 client: ClientId(1),
                 tx: TransactionId(1),
                 amount: None,
+                reason: None,
+                wallet: None,
+                to_wallet: None,
             }),
             Err(ParseError::WithdrawalMissing)
         ));
@@ -251,21 +1503,32 @@ mod tests {
             client: ClientId(1),
             tx: TransactionId(1),
             amount: None,
+            reason: None,
+            wallet: None,
+            to_wallet: None,
         })
         .is_ok());
     }
 
     #[test]
-    fn dispute_unexpected_amount() {
-        assert!(matches!(
+    fn dispute_partial_amount() {
+        assert_eq!(
             Transaction::try_from(TransactionEntry {
                 transaction_type: TransactionType::Dispute,
                 client: ClientId(1),
                 tx: TransactionId(1),
                 amount: Some(Amount::from(1)),
-            }),
-            Err(ParseError::DisputeUnexpected)
-        ));
+                reason: None,
+                wallet: None,
+                to_wallet: None,
+            })
+            .unwrap(),
+            Transaction::Dispute(Dispute {
+                client: ClientId(1),
+                tx: TransactionId(1),
+                amount: Some(Amount::from(1)),
+            })
+        );
     }
 
     #[test]
@@ -275,21 +1538,32 @@ mod tests {
             client: ClientId(1),
             tx: TransactionId(1),
             amount: None,
+            reason: None,
+            wallet: None,
+            to_wallet: None,
         })
         .is_ok());
     }
 
     #[test]
-    fn resolve_unexpected_amount() {
-        assert!(matches!(
+    fn resolve_partial_amount() {
+        assert_eq!(
             Transaction::try_from(TransactionEntry {
                 transaction_type: TransactionType::Resolve,
                 client: ClientId(1),
                 tx: TransactionId(1),
                 amount: Some(Amount::from(1)),
-            }),
-            Err(ParseError::ResolveUnexpected)
-        ));
+                reason: None,
+                wallet: None,
+                to_wallet: None,
+            })
+            .unwrap(),
+            Transaction::Resolve(Resolve {
+                client: ClientId(1),
+                tx: TransactionId(1),
+                amount: Some(Amount::from(1)),
+            })
+        );
     }
 
     #[test]
@@ -299,20 +1573,199 @@ mod tests {
             client: ClientId(1),
             tx: TransactionId(1),
             amount: None,
+            reason: None,
+            wallet: None,
+            to_wallet: None,
         })
         .is_ok());
     }
 
     #[test]
-    fn chargeback_unexpected_amount() {
-        assert!(matches!(
+    fn chargeback_partial_amount() {
+        assert_eq!(
             Transaction::try_from(TransactionEntry {
                 transaction_type: TransactionType::Chargeback,
                 client: ClientId(1),
                 tx: TransactionId(1),
                 amount: Some(Amount::from(1)),
+                reason: None,
+                wallet: None,
+                to_wallet: None,
+            })
+            .unwrap(),
+            Transaction::Chargeback(Chargeback {
+                client: ClientId(1),
+                tx: TransactionId(1),
+                amount: Some(Amount::from(1)),
+            })
+        );
+    }
+
+    #[test]
+    fn authorize_missing_amount() {
+        assert!(matches!(
+            Transaction::try_from(TransactionEntry {
+                transaction_type: TransactionType::Authorize,
+                client: ClientId(1),
+                tx: TransactionId(1),
+                amount: None,
+                reason: None,
+                wallet: None,
+                to_wallet: None,
+            }),
+            Err(ParseError::AuthorizeMissing)
+        ));
+    }
+
+    #[test]
+    fn interest_missing_amount() {
+        assert!(matches!(
+            Transaction::try_from(TransactionEntry {
+                transaction_type: TransactionType::Interest,
+                client: ClientId(1),
+                tx: TransactionId(1),
+                amount: None,
+                reason: None,
+                wallet: None,
+                to_wallet: None,
+            }),
+            Err(ParseError::InterestMissing)
+        ));
+    }
+
+    #[test]
+    fn settle_unexpected_amount() {
+        assert!(matches!(
+            Transaction::try_from(TransactionEntry {
+                transaction_type: TransactionType::Settle,
+                client: ClientId(1),
+                tx: TransactionId(1),
+                amount: Some(Amount::from(1)),
+                reason: None,
+                wallet: None,
+                to_wallet: None,
+            }),
+            Err(ParseError::SettleUnexpected)
+        ));
+    }
+
+    #[test]
+    fn holds() {
+        let rdr = "type, client, tx, amount, reason\n\
+                   hold, 1, 2, 5, aml\n\
+                   hold, 1, 3, 5,\n\
+                   release, 1, 2,,\n";
+        let got = read_input(rdr.as_bytes())
+            .map(|row| row.entry)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            got[0].as_ref().unwrap(),
+            &Transaction::Hold(Hold {
+                client: ClientId(1),
+                tx: TransactionId(2),
+                amount: Amount::from(5),
+                reason: HoldReason::Aml,
+            })
+        );
+        assert!(matches!(got[1], Err(ParseError::HoldReasonMissing)));
+        assert_eq!(
+            got[2].as_ref().unwrap(),
+            &Transaction::Release(Release {
+                client: ClientId(1),
+                tx: TransactionId(2),
+                amount: None,
+            })
+        );
+    }
+
+    #[test]
+    fn wallets() {
+        let rdr = "type, client, tx, amount, wallet, to_wallet\n\
+                   deposit, 1, 1, 5, 2,\n\
+                   withdrawal, 1, 2, 1,,\n\
+                   transfer, 1, 3, 2, 2, 1\n\
+                   transfer, 1, 4, 2,, 0\n\
+                   transfer, 1, 5, 2, 2,\n";
+        let got = read_input(rdr.as_bytes())
+            .map(|row| row.entry)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            got[0].as_ref().unwrap(),
+            &Transaction::Deposit(Deposit {
+                client: ClientId(1),
+                tx: TransactionId(1),
+                amount: Amount::from(5),
+                wallet: WalletId(2),
+            })
+        );
+        assert_eq!(got[1].as_ref().unwrap().wallet(), WalletId::default());
+        assert_eq!(
+            got[2].as_ref().unwrap(),
+            &Transaction::Transfer(Transfer {
+                client: ClientId(1),
+                tx: TransactionId(3),
+                amount: Amount::from(2),
+                wallet: WalletId(2),
+                to_wallet: WalletId(1),
+            })
+        );
+        assert!(matches!(got[3], Err(ParseError::TransferSameWallet)));
+        assert!(matches!(got[4], Err(ParseError::TransferWalletMissing)));
+    }
+
+    #[test]
+    fn close_unexpected_amount() {
+        assert!(matches!(
+            Transaction::try_from(TransactionEntry {
+                transaction_type: TransactionType::Close,
+                client: ClientId(1),
+                tx: TransactionId(1),
+                amount: Some(Amount::from(1)),
+                reason: None,
+                wallet: None,
+                to_wallet: None,
+            }),
+            Err(ParseError::CloseUnexpected)
+        ));
+    }
+
+    #[test]
+    fn capture() {
+        // Assert that the amount of a capture is optional.
+        for amount in [None, Some(Amount::from(1))] {
+            assert_eq!(
+                Transaction::try_from(TransactionEntry {
+                    transaction_type: TransactionType::Capture,
+                    client: ClientId(1),
+                    tx: TransactionId(1),
+                    amount,
+                    reason: None,
+                    wallet: None,
+                    to_wallet: None,
+                })
+                .unwrap(),
+                Transaction::Capture(Capture {
+                    client: ClientId(1),
+                    tx: TransactionId(1),
+                    amount,
+                })
+            );
+        }
+    }
+
+    #[test]
+    fn void_unexpected_amount() {
+        assert!(matches!(
+            Transaction::try_from(TransactionEntry {
+                transaction_type: TransactionType::Void,
+                client: ClientId(1),
+                tx: TransactionId(1),
+                amount: Some(Amount::from(1)),
+                reason: None,
+                wallet: None,
+                to_wallet: None,
             }),
-            Err(ParseError::ChargebackUnexpected)
+            Err(ParseError::VoidUnexpected)
         ));
     }
 }
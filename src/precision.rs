@@ -0,0 +1,107 @@
+use crate::types::Amount;
+use rust_decimal::prelude::RoundingStrategy;
+use std::str::FromStr;
+
+/// The number of decimal places amounts are held to unless configured otherwise.
+pub const DEFAULT_DECIMAL_PLACES: u32 = 4;
+
+/// The most decimal places a precision may be configured with, which is the most `Decimal` holds.
+pub const MAX_DECIMAL_PLACES: u32 = 28;
+
+/// How amounts with more decimal places than the ledger's precision are rounded.
+#[derive(Debug, Default, Clone, Copy, Eq, PartialEq)]
+pub enum Rounding {
+    /// Round half way values to the nearest even digit.
+    #[default]
+    Bankers,
+    /// Round half way values away from zero.
+    HalfUp,
+    /// Discard the excess decimal places.
+    Truncate,
+}
+
+impl Rounding {
+    fn strategy(self) -> RoundingStrategy {
+        match self {
+            Rounding::Bankers => RoundingStrategy::MidpointNearestEven,
+            Rounding::HalfUp => RoundingStrategy::MidpointAwayFromZero,
+            Rounding::Truncate => RoundingStrategy::ToZero,
+        }
+    }
+}
+
+impl FromStr for Rounding {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "bankers" => Ok(Rounding::Bankers),
+            "half_up" => Ok(Rounding::HalfUp),
+            "truncate" => Ok(Rounding::Truncate),
+            _ => Err(format!(
+                "unknown rounding {s:?}, expected one of: bankers, half_up, truncate"
+            )),
+        }
+    }
+}
+
+/// The number of decimal places amounts are held to, and how amounts are rounded to it.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct Precision {
+    pub decimal_places: u32,
+    pub rounding: Rounding,
+}
+
+impl Default for Precision {
+    fn default() -> Self {
+        Precision {
+            decimal_places: DEFAULT_DECIMAL_PLACES,
+            rounding: Rounding::default(),
+        }
+    }
+}
+
+impl Precision {
+    /// Round an amount to the precision.
+    pub fn round(&self, amount: Amount) -> Amount {
+        Amount(
+            amount
+                .0
+                .round_dp_with_strategy(self.decimal_places, self.rounding.strategy()),
+        )
+    }
+
+    /// Whether an amount has more decimal places than the precision holds.
+    pub fn exceeds(&self, amount: Amount) -> bool {
+        amount.0.normalize().scale() > self.decimal_places
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal::Decimal;
+
+    #[test]
+    fn round() {
+        let amount = |mantissa, scale| Amount::from(Decimal::new(mantissa, scale));
+        let precision = |rounding| Precision {
+            decimal_places: 2,
+            rounding,
+        };
+
+        let bankers = precision(Rounding::Bankers);
+        assert_eq!(bankers.round(amount(1225, 3)), amount(122, 2));
+        assert_eq!(bankers.round(amount(1235, 3)), amount(124, 2));
+        let half_up = precision(Rounding::HalfUp);
+        assert_eq!(half_up.round(amount(1225, 3)), amount(123, 2));
+        assert_eq!(half_up.round(amount(-1225, 3)), amount(-123, 2));
+        let truncate = precision(Rounding::Truncate);
+        assert_eq!(truncate.round(amount(1229, 3)), amount(122, 2));
+
+        assert!(bankers.exceeds(amount(1225, 3)));
+        assert!(!bankers.exceeds(amount(12200, 4)));
+        assert_eq!("half_up".parse(), Ok(Rounding::HalfUp));
+        assert!("up".parse::<Rounding>().is_err());
+    }
+}
@@ -0,0 +1,212 @@
+use crate::precision::Precision;
+use crate::types::{Amount, Timestamp, TransactionId, WalletId};
+use rust_decimal::Decimal;
+use std::collections::VecDeque;
+
+/// A rolling reserve applied to a high risk client, withholding a percentage of each deposit for a
+/// number of days before it is released to their available funds.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct ReservePolicy {
+    pub percent: Decimal,
+    pub days: u64,
+}
+
+impl ReservePolicy {
+    /// The amount withheld from a deposit, rounded to the ledger's precision, or `None` if it is
+    /// too large to represent.
+    pub fn withhold(&self, amount: Amount, precision: &Precision) -> Option<Amount> {
+        let withheld = amount.0.checked_mul(self.percent)? / Decimal::ONE_HUNDRED;
+        Some(precision.round(Amount(withheld)))
+    }
+
+    /// When an amount withheld at the given time is released.
+    pub fn release_at(&self, now: Timestamp) -> Timestamp {
+        Timestamp(
+            now.0
+                .saturating_add(self.days.saturating_mul(Timestamp::SECONDS_PER_DAY)),
+        )
+    }
+}
+
+/// An amount withheld from a single deposit, the wallet it was deposited to, and when it is
+/// released.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Tranche {
+    tx: TransactionId,
+    wallet: WalletId,
+    release_at: Timestamp,
+    amount: Amount,
+}
+
+/// The funds withheld from a client's deposits, in the order they are released.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Reserve {
+    tranches: VecDeque<Tranche>,
+}
+
+impl Reserve {
+    /// The total amount currently withheld.
+    pub fn balance(&self) -> Amount {
+        Amount(self.tranches.iter().map(|tranche| tranche.amount.0).sum())
+    }
+
+    /// The amount currently withheld from deposits to one of the client's wallets.
+    pub fn wallet_balance(&self, wallet: WalletId) -> Amount {
+        Amount(
+            self.tranches
+                .iter()
+                .filter(|tranche| tranche.wallet == wallet)
+                .map(|tranche| tranche.amount.0)
+                .sum(),
+        )
+    }
+
+    /// Withhold an amount from a deposit to a wallet until the given time.
+    pub fn withhold(
+        &mut self,
+        tx: TransactionId,
+        wallet: WalletId,
+        release_at: Timestamp,
+        amount: Amount,
+    ) {
+        if amount.0.is_zero() {
+            return;
+        }
+        // Deposits are normally withheld in release order, but keep the tranches sorted in case the
+        // policy changes between deposits.
+        let index = self
+            .tranches
+            .partition_point(|tranche| tranche.release_at <= release_at);
+        self.tranches.insert(
+            index,
+            Tranche {
+                tx,
+                wallet,
+                release_at,
+                amount,
+            },
+        );
+    }
+
+    /// Release every tranche due by the given time, returning the amount released from each, along
+    /// with the wallet it is returned to.
+    pub fn release(&mut self, now: Timestamp) -> Vec<(WalletId, Amount)> {
+        let mut released = Vec::new();
+        while let Some(tranche) = self.tranches.front() {
+            if tranche.release_at > now {
+                break;
+            }
+            released.push((tranche.wallet, tranche.amount));
+            self.tranches.pop_front();
+        }
+        released
+    }
+
+    /// Draw up to an amount from the reserve, taking from the tranches due to be released last,
+    /// and returning the amount drawn from each, along with the wallet it is returned to.
+    pub fn draw(&mut self, amount: Amount) -> Vec<(WalletId, Amount)> {
+        let mut drawn = Vec::new();
+        let mut remaining = amount.0;
+        while let Some(tranche) = self.tranches.back_mut() {
+            if remaining <= Decimal::ZERO {
+                break;
+            }
+            let take = tranche.amount.0.min(remaining);
+            tranche.amount.0 -= take;
+            remaining -= take;
+            drawn.push((tranche.wallet, Amount(take)));
+            if !tranche.amount.0.is_zero() {
+                break;
+            }
+            self.tranches.pop_back();
+        }
+        drawn
+    }
+
+    /// The amount which remains withheld from a deposit.
+    pub fn withheld(&self, tx: TransactionId) -> Amount {
+        Amount(
+            self.tranches
+                .iter()
+                .filter(|tranche| tranche.tx == tx)
+                .map(|tranche| tranche.amount.0)
+                .sum(),
+        )
+    }
+
+    /// Remove whatever remains withheld from a deposit.
+    pub fn remove(&mut self, tx: TransactionId) {
+        self.tranches.retain(|tranche| tranche.tx != tx);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn policy() {
+        let policy = ReservePolicy {
+            percent: Decimal::new(125, 1),
+            days: 2,
+        };
+        assert_eq!(
+            policy.withhold(Amount::from(Decimal::new(10001, 3)), &Precision::default()),
+            Some(Amount::from(Decimal::new(12501, 4)))
+        );
+        assert_eq!(
+            policy.withhold(Amount::from(Decimal::MAX), &Precision::default()),
+            None
+        );
+        assert_eq!(
+            policy.release_at(Timestamp(1)),
+            Timestamp(1 + 2 * Timestamp::SECONDS_PER_DAY)
+        );
+    }
+
+    #[test]
+    fn reserve() {
+        let mut reserve = Reserve::default();
+        reserve.withhold(
+            TransactionId(1),
+            WalletId(0),
+            Timestamp(10),
+            Amount::from(5),
+        );
+        reserve.withhold(
+            TransactionId(2),
+            WalletId(1),
+            Timestamp(20),
+            Amount::from(3),
+        );
+        reserve.withhold(
+            TransactionId(3),
+            WalletId(2),
+            Timestamp(30),
+            Amount::from(2),
+        );
+        assert_eq!(reserve.balance(), Amount::from(10));
+        assert_eq!(reserve.wallet_balance(WalletId(1)), Amount::from(3));
+
+        // Assert that draws are taken from the tranches released last, returning each to the
+        // wallet it was deposited to.
+        assert_eq!(
+            reserve.draw(Amount::from(4)),
+            vec![
+                (WalletId(2), Amount::from(2)),
+                (WalletId(1), Amount::from(2))
+            ]
+        );
+        assert_eq!(reserve.withheld(TransactionId(3)), Amount::from(0));
+        assert_eq!(reserve.withheld(TransactionId(2)), Amount::from(1));
+        reserve.remove(TransactionId(2));
+        assert_eq!(reserve.balance(), Amount::from(5));
+
+        assert_eq!(reserve.release(Timestamp(9)), vec![]);
+        assert_eq!(
+            reserve.release(Timestamp(10)),
+            vec![(WalletId(0), Amount::from(5))]
+        );
+        assert_eq!(reserve.draw(Amount::from(1)), vec![]);
+    }
+}
@@ -0,0 +1,480 @@
+use crate::clients::{ClientRecord, ClientStatus, RiskRating};
+use crate::types::{ClientId, Deposit, Dispute, Transaction, TransactionId, Withdrawal};
+use rust_decimal::Decimal;
+use serde::Deserialize;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt;
+
+/// The action taken when a rule is triggered by a transaction.
+#[derive(Debug, Deserialize, Clone, Copy, Eq, Ord, PartialEq, PartialOrd)]
+#[serde(rename_all = "lowercase")]
+pub enum Action {
+    /// Apply the transaction, but raise an alert.
+    Flag,
+    /// Reject the transaction.
+    Reject,
+    /// Reject the transaction and lock the client's account.
+    Lock,
+}
+
+/// A fraud or velocity rule, evaluated against each transaction before it is applied to the
+/// ledger. Rules keep whatever history they need by observing the transactions which are applied.
+pub trait Rule: fmt::Debug {
+    /// The name the rule is configured and reported by.
+    fn name(&self) -> &'static str;
+
+    /// Evaluate a transaction before it is applied, returning the reason it triggers the rule. The
+    /// client's master data is given when the client has a record.
+    fn evaluate(&self, transaction: &Transaction, client: Option<&ClientRecord>) -> Option<String>;
+
+    /// Observe a transaction once it has been applied to the ledger.
+    fn observe(&mut self, transaction: &Transaction);
+
+    fn clone_box(&self) -> Box<dyn Rule>;
+}
+
+impl Clone for Box<dyn Rule> {
+    fn clone(&self) -> Self {
+        self.clone_box()
+    }
+}
+
+/// The outcome of a rule triggered by a transaction.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Verdict {
+    pub rule: &'static str,
+    pub action: Action,
+    pub client: ClientId,
+    pub tx: TransactionId,
+    pub reason: String,
+}
+
+impl fmt::Display for Verdict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} rule: {}", self.rule, self.reason)
+    }
+}
+
+/// The rules evaluated by the ledger, each with the action taken when it is triggered.
+#[derive(Debug, Default, Clone)]
+pub struct RuleSet {
+    rules: Vec<(Action, Box<dyn Rule>)>,
+}
+
+impl RuleSet {
+    pub fn add(&mut self, action: Action, rule: Box<dyn Rule>) {
+        self.rules.push((action, rule));
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// Evaluate every rule against a transaction, returning a verdict for each rule triggered.
+    pub fn evaluate(
+        &self,
+        transaction: &Transaction,
+        client: Option<&ClientRecord>,
+    ) -> Vec<Verdict> {
+        self.rules
+            .iter()
+            .filter_map(|(action, rule)| {
+                let reason = rule.evaluate(transaction, client)?;
+                Some(Verdict {
+                    rule: rule.name(),
+                    action: *action,
+                    client: transaction.client(),
+                    tx: transaction.tx(),
+                    reason,
+                })
+            })
+            .collect()
+    }
+
+    /// Let every rule observe a transaction which has been applied.
+    pub fn observe(&mut self, transaction: &Transaction) {
+        for (_, rule) in &mut self.rules {
+            rule.observe(transaction);
+        }
+    }
+}
+
+/// Triggered by a withdrawal which brings the number of withdrawals among a client's most recent
+/// `window` transactions to at least `count`.
+#[derive(Debug, Clone)]
+pub struct WithdrawalVelocity {
+    count: usize,
+    window: usize,
+    recent: HashMap<ClientId, VecDeque<bool>>,
+}
+
+impl WithdrawalVelocity {
+    pub const NAME: &'static str = "withdrawal_velocity";
+
+    pub fn new(count: usize, window: usize) -> Self {
+        WithdrawalVelocity {
+            count,
+            window,
+            recent: HashMap::new(),
+        }
+    }
+}
+
+impl Rule for WithdrawalVelocity {
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn evaluate(
+        &self,
+        transaction: &Transaction,
+        _client: Option<&ClientRecord>,
+    ) -> Option<String> {
+        let Transaction::Withdrawal(Withdrawal { client, .. }) = transaction else {
+            return None;
+        };
+        let previous = self.recent.get(client).map_or(0, |recent| {
+            recent
+                .iter()
+                .rev()
+                .take(self.window.saturating_sub(1))
+                .filter(|withdrawal| **withdrawal)
+                .count()
+        });
+        (previous + 1 >= self.count).then(|| {
+            format!(
+                "{} withdrawals in the last {} transactions",
+                previous + 1,
+                self.window
+            )
+        })
+    }
+
+    fn observe(&mut self, transaction: &Transaction) {
+        let recent = self.recent.entry(transaction.client()).or_default();
+        recent.push_back(matches!(transaction, Transaction::Withdrawal(_)));
+        if recent.len() >= self.window {
+            recent.pop_front();
+        }
+    }
+
+    fn clone_box(&self) -> Box<dyn Rule> {
+        Box::new(self.clone())
+    }
+}
+
+/// Triggered by a dispute of a deposit which was followed by a withdrawal within the client's
+/// next `window` transactions, a pattern used to withdraw funds before reversing their deposit.
+#[derive(Debug, Clone)]
+pub struct DepositWithdrawDispute {
+    window: usize,
+    recent: HashMap<ClientId, VecDeque<Option<TransactionId>>>,
+    withdrawn: HashSet<(ClientId, TransactionId)>,
+}
+
+impl DepositWithdrawDispute {
+    pub const NAME: &'static str = "deposit_withdraw_dispute";
+
+    pub fn new(window: usize) -> Self {
+        DepositWithdrawDispute {
+            window,
+            recent: HashMap::new(),
+            withdrawn: HashSet::new(),
+        }
+    }
+}
+
+impl Rule for DepositWithdrawDispute {
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn evaluate(
+        &self,
+        transaction: &Transaction,
+        _client: Option<&ClientRecord>,
+    ) -> Option<String> {
+        let Transaction::Dispute(Dispute { client, tx, .. }) = transaction else {
+            return None;
+        };
+        self.withdrawn
+            .contains(&(*client, *tx))
+            .then(|| "disputed deposit was immediately followed by a withdrawal".to_string())
+    }
+
+    fn observe(&mut self, transaction: &Transaction) {
+        let client = transaction.client();
+        let recent = self.recent.entry(client).or_default();
+
+        // Mark the deposits among the client's recent transactions as withdrawn against.
+        if let Transaction::Withdrawal(_) = transaction {
+            for tx in recent.iter().flatten() {
+                self.withdrawn.insert((client, *tx));
+            }
+        }
+
+        // Track the client's most recent transactions, noting which were deposits.
+        let deposit = match transaction {
+            Transaction::Deposit(Deposit { tx, .. }) => Some(*tx),
+            _ => None,
+        };
+        recent.push_back(deposit);
+        if recent.len() > self.window {
+            recent.pop_front();
+        }
+    }
+
+    fn clone_box(&self) -> Box<dyn Rule> {
+        Box::new(self.clone())
+    }
+}
+
+/// Triggered by a dispute which takes the ratio of a client's disputes to deposits above
+/// `ratio`, once the client has made at least `minimum` deposits.
+#[derive(Debug, Clone)]
+pub struct DisputeRatio {
+    ratio: Decimal,
+    minimum: usize,
+    counts: HashMap<ClientId, (usize, usize)>,
+}
+
+impl DisputeRatio {
+    pub const NAME: &'static str = "dispute_ratio";
+
+    pub fn new(ratio: Decimal, minimum: usize) -> Self {
+        DisputeRatio {
+            ratio,
+            minimum,
+            counts: HashMap::new(),
+        }
+    }
+}
+
+impl Rule for DisputeRatio {
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn evaluate(
+        &self,
+        transaction: &Transaction,
+        _client: Option<&ClientRecord>,
+    ) -> Option<String> {
+        let Transaction::Dispute(Dispute { client, .. }) = transaction else {
+            return None;
+        };
+        let (deposits, disputes) = self.counts.get(client).copied().unwrap_or_default();
+        if deposits == 0 || deposits < self.minimum {
+            return None;
+        }
+        let ratio = Decimal::from(disputes + 1) / Decimal::from(deposits);
+        (ratio > self.ratio).then(|| {
+            format!(
+                "{} disputes for {} deposits exceeds ratio {}",
+                disputes + 1,
+                deposits,
+                self.ratio
+            )
+        })
+    }
+
+    fn observe(&mut self, transaction: &Transaction) {
+        let (deposits, disputes) = self.counts.entry(transaction.client()).or_default();
+        match transaction {
+            Transaction::Deposit(_) => *deposits += 1,
+            Transaction::Dispute(_) => *disputes += 1,
+            _ => {}
+        }
+    }
+
+    fn clone_box(&self) -> Box<dyn Rule> {
+        Box::new(self.clone())
+    }
+}
+
+/// Triggered by any transaction of a client whose master data marks them as suspended.
+#[derive(Debug, Clone)]
+pub struct ClientSuspended;
+
+impl ClientSuspended {
+    pub const NAME: &'static str = "client_status";
+}
+
+impl Rule for ClientSuspended {
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn evaluate(
+        &self,
+        _transaction: &Transaction,
+        client: Option<&ClientRecord>,
+    ) -> Option<String> {
+        let client = client?;
+        (client.status == ClientStatus::Suspended).then(|| "client is suspended".to_string())
+    }
+
+    fn observe(&mut self, _transaction: &Transaction) {}
+
+    fn clone_box(&self) -> Box<dyn Rule> {
+        Box::new(self.clone())
+    }
+}
+
+/// Triggered by a withdrawal of a client whose master data rates them as high risk.
+#[derive(Debug, Clone)]
+pub struct HighRiskWithdrawal;
+
+impl HighRiskWithdrawal {
+    pub const NAME: &'static str = "client_risk";
+}
+
+impl Rule for HighRiskWithdrawal {
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn evaluate(&self, transaction: &Transaction, client: Option<&ClientRecord>) -> Option<String> {
+        let Transaction::Withdrawal(_) = transaction else {
+            return None;
+        };
+        (client?.risk_rating? == RiskRating::High)
+            .then(|| "withdrawal by a high risk client".to_string())
+    }
+
+    fn observe(&mut self, _transaction: &Transaction) {}
+
+    fn clone_box(&self) -> Box<dyn Rule> {
+        Box::new(self.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{Amount, WalletId};
+
+    fn deposit(tx: u32) -> Transaction {
+        Transaction::Deposit(Deposit {
+            client: ClientId(1),
+            tx: TransactionId(tx),
+            amount: Amount::from(10),
+            wallet: WalletId::default(),
+        })
+    }
+
+    fn withdrawal(tx: u32) -> Transaction {
+        Transaction::Withdrawal(Withdrawal {
+            client: ClientId(1),
+            tx: TransactionId(tx),
+            amount: Amount::from(1),
+            wallet: WalletId::default(),
+        })
+    }
+
+    fn dispute(tx: u32) -> Transaction {
+        Transaction::Dispute(Dispute {
+            client: ClientId(1),
+            tx: TransactionId(tx),
+            amount: None,
+        })
+    }
+
+    #[test]
+    fn withdrawal_velocity() {
+        // Three withdrawals in four transactions.
+        let mut rule = WithdrawalVelocity::new(3, 4);
+
+        for transaction in [withdrawal(1), deposit(2), withdrawal(3)] {
+            assert_eq!(rule.evaluate(&transaction, None), None);
+            rule.observe(&transaction);
+        }
+        assert!(rule.evaluate(&withdrawal(4), None).is_some());
+        assert_eq!(rule.evaluate(&deposit(4), None), None);
+
+        // The first withdrawal falls out of the window.
+        rule.observe(&deposit(4));
+        assert_eq!(rule.evaluate(&withdrawal(5), None), None);
+    }
+
+    #[test]
+    fn deposit_withdraw_dispute() {
+        let mut rule = DepositWithdrawDispute::new(1);
+
+        for transaction in [deposit(1), deposit(2), withdrawal(3), deposit(4)] {
+            rule.observe(&transaction);
+        }
+
+        // Only the deposit immediately before the withdrawal is suspicious.
+        assert_eq!(rule.evaluate(&dispute(1), None), None);
+        assert!(rule.evaluate(&dispute(2), None).is_some());
+        assert_eq!(rule.evaluate(&dispute(4), None), None);
+    }
+
+    #[test]
+    fn dispute_ratio() {
+        let mut rule = DisputeRatio::new(Decimal::new(5, 1), 2);
+
+        rule.observe(&deposit(1));
+        assert_eq!(rule.evaluate(&dispute(1), None), None);
+
+        rule.observe(&deposit(2));
+        assert_eq!(rule.evaluate(&dispute(1), None), None);
+
+        rule.observe(&dispute(1));
+        assert!(rule.evaluate(&dispute(2), None).is_some());
+    }
+
+    #[test]
+    fn client_rules() {
+        let suspended = ClientRecord {
+            status: ClientStatus::Suspended,
+            risk_rating: Some(RiskRating::Medium),
+            ..ClientRecord::default()
+        };
+        let high_risk = ClientRecord {
+            risk_rating: Some(RiskRating::High),
+            ..ClientRecord::default()
+        };
+
+        assert!(ClientSuspended
+            .evaluate(&deposit(1), Some(&suspended))
+            .is_some());
+        assert_eq!(
+            ClientSuspended.evaluate(&deposit(1), Some(&high_risk)),
+            None
+        );
+        assert_eq!(ClientSuspended.evaluate(&deposit(1), None), None);
+
+        assert!(HighRiskWithdrawal
+            .evaluate(&withdrawal(1), Some(&high_risk))
+            .is_some());
+        assert_eq!(
+            HighRiskWithdrawal.evaluate(&deposit(1), Some(&high_risk)),
+            None
+        );
+        assert_eq!(
+            HighRiskWithdrawal.evaluate(&withdrawal(1), Some(&suspended)),
+            None
+        );
+    }
+
+    #[test]
+    fn rule_set() {
+        let mut rules = RuleSet::default();
+        rules.add(Action::Reject, Box::new(WithdrawalVelocity::new(1, 1)));
+        rules.add(Action::Flag, Box::new(DisputeRatio::new(Decimal::ZERO, 0)));
+
+        let verdicts = rules.evaluate(&withdrawal(1), None);
+        assert_eq!(
+            verdicts,
+            [Verdict {
+                rule: WithdrawalVelocity::NAME,
+                action: Action::Reject,
+                client: ClientId(1),
+                tx: TransactionId(1),
+                reason: "1 withdrawals in the last 1 transactions".to_string(),
+            }]
+        );
+    }
+}
@@ -1,9 +1,39 @@
 use rust_decimal::Decimal;
-use serde::{Deserialize, Serialize};
+use serde::de::Error as _;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
-#[derive(Debug, Deserialize, Serialize, Copy, Clone, PartialEq)]
+#[derive(Debug, Default, Serialize, Copy, Clone, PartialEq)]
 pub struct Amount(pub Decimal);
 
+impl Amount {
+    /// The largest amount a single row may carry, one quadrillion. Balances built from any
+    /// realistic number of rows of at most this amount stay far below the largest `Decimal`.
+    pub const MAX: Amount = Amount(Decimal::from_parts(2_764_472_320, 232_830, 0, false, 0));
+
+    /// Add two amounts, returning `None` if the sum overflows.
+    pub fn checked_add(self, other: Amount) -> Option<Amount> {
+        self.0.checked_add(other.0).map(Amount)
+    }
+
+    /// Subtract one amount from another, returning `None` if the difference overflows.
+    pub fn checked_sub(self, other: Amount) -> Option<Amount> {
+        self.0.checked_sub(other.0).map(Amount)
+    }
+}
+
+/// Amounts are parsed from their text, rather than letting the deserializer infer a number type,
+/// which would round amounts with more significant digits than an `f64` holds. Trailing zeros are
+/// dropped, as they were when amounts were inferred as numbers, so `1.50` is output as `1.5`.
+impl<'de> Deserialize<'de> for Amount {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let text = String::deserialize(deserializer)?;
+        text.parse()
+            .or_else(|_| Decimal::from_scientific(&text))
+            .map(|amount| Amount(amount.normalize()))
+            .map_err(D::Error::custom)
+    }
+}
+
 impl From<i32> for Amount {
     fn from(value: i32) -> Self {
         Amount(Decimal::from(value))
@@ -16,13 +46,77 @@ impl From<Decimal> for Amount {
     }
 }
 
-#[derive(Debug, Deserialize, Serialize, Copy, Clone, Eq, Hash, PartialEq)]
+#[derive(Debug, Deserialize, Serialize, Copy, Clone, Eq, Hash, PartialEq, Ord, PartialOrd)]
 pub struct ClientId(pub u16);
 
-#[derive(Debug, Deserialize, Copy, Clone, Eq, Hash, PartialEq)]
+/// The account an output row is for: a client's account, or the house revenue account. The house
+/// has no client ID, so every client ID remains free for input rows, and it is output as `house`.
+#[derive(Debug, Copy, Clone, Eq, Hash, PartialEq, Ord, PartialOrd)]
+pub enum AccountId {
+    Client(ClientId),
+    House,
+}
+
+impl AccountId {
+    /// The client the account belongs to, which the house account has none of.
+    pub fn client(self) -> Option<ClientId> {
+        match self {
+            AccountId::Client(client) => Some(client),
+            AccountId::House => None,
+        }
+    }
+}
+
+impl Serialize for AccountId {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            AccountId::Client(client) => client.serialize(serializer),
+            AccountId::House => serializer.serialize_str("house"),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Copy, Clone, Eq, Hash, PartialEq)]
 pub struct TransactionId(pub u32);
 
-#[derive(Debug, Deserialize)]
+/// One of a client's wallets, which segregate their funds. Wallet 0 is the client's default
+/// wallet, holding whatever funds are not in any other wallet.
+#[derive(
+    Debug, Default, Deserialize, Serialize, Copy, Clone, Eq, Hash, Ord, PartialEq, PartialOrd,
+)]
+pub struct WalletId(pub u16);
+
+/// A point in time, in seconds since the Unix epoch.
+#[derive(
+    Debug, Default, Deserialize, Serialize, Copy, Clone, Eq, Hash, Ord, PartialEq, PartialOrd,
+)]
+pub struct Timestamp(pub u64);
+
+impl Timestamp {
+    pub const SECONDS_PER_DAY: u64 = 86_400;
+
+    /// The number of whole days since the Unix epoch.
+    pub fn day(self) -> u64 {
+        self.0 / Self::SECONDS_PER_DAY
+    }
+
+    /// The number of calendar months since the Unix epoch, in UTC.
+    pub fn month(self) -> u64 {
+        // Convert days since the epoch into a civil year and month, see
+        // https://howardhinnant.github.io/date_algorithms.html#civil_from_days
+        let z = self.day() + 719_468;
+        let era = z / 146_097;
+        let doe = z - era * 146_097;
+        let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 };
+        let year = yoe + era * 400 + u64::from(month <= 2);
+        (year - 1970) * 12 + (month - 1)
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Copy, Clone, Eq, Hash, PartialEq)]
 #[serde(rename_all = "lowercase")]
 pub enum TransactionType {
     Deposit,
@@ -30,46 +124,421 @@ pub enum TransactionType {
     Dispute,
     Resolve,
     Chargeback,
+    Authorize,
+    Capture,
+    Void,
+    Represent,
+    Arbitrate,
+    Refund,
+    Reversal,
+    #[serde(rename = "credit_limit")]
+    CreditLimit,
+    Interest,
+    Settle,
+    Hold,
+    Release,
+    Close,
+    Transfer,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Transaction {
     Deposit(Deposit),
     Withdrawal(Withdrawal),
     Dispute(Dispute),
     Resolve(Resolve),
     Chargeback(Chargeback),
+    Authorize(Authorize),
+    Capture(Capture),
+    Void(Void),
+    Represent(Represent),
+    Arbitrate(Arbitrate),
+    Refund(Refund),
+    Reversal(Reversal),
+    CreditLimit(CreditLimit),
+    Interest(Interest),
+    Settle(Settle),
+    Hold(Hold),
+    Release(Release),
+    Close(Close),
+    Transfer(Transfer),
+}
+
+impl Transaction {
+    /// The client whose account the transaction applies to.
+    pub fn client(&self) -> ClientId {
+        match self {
+            Transaction::Deposit(Deposit { client, .. })
+            | Transaction::Withdrawal(Withdrawal { client, .. })
+            | Transaction::Dispute(Dispute { client, .. })
+            | Transaction::Resolve(Resolve { client, .. })
+            | Transaction::Chargeback(Chargeback { client, .. })
+            | Transaction::Authorize(Authorize { client, .. })
+            | Transaction::Capture(Capture { client, .. })
+            | Transaction::Void(Void { client, .. })
+            | Transaction::Represent(Represent { client, .. })
+            | Transaction::Arbitrate(Arbitrate { client, .. })
+            | Transaction::Refund(Refund { client, .. })
+            | Transaction::Reversal(Reversal { client, .. })
+            | Transaction::CreditLimit(CreditLimit { client, .. })
+            | Transaction::Interest(Interest { client, .. })
+            | Transaction::Settle(Settle { client, .. })
+            | Transaction::Hold(Hold { client, .. })
+            | Transaction::Release(Release { client, .. })
+            | Transaction::Close(Close { client, .. })
+            | Transaction::Transfer(Transfer { client, .. }) => *client,
+        }
+    }
+
+    /// The transaction ID, which for disputes, resolves, chargebacks, representments, arbitrations,
+    /// refunds and settlements references the deposit, for captures and voids references the
+    /// authorization, for releases references the hold, and for reversals references the
+    /// transaction reversed.
+    pub fn tx(&self) -> TransactionId {
+        match self {
+            Transaction::Deposit(Deposit { tx, .. })
+            | Transaction::Withdrawal(Withdrawal { tx, .. })
+            | Transaction::Dispute(Dispute { tx, .. })
+            | Transaction::Resolve(Resolve { tx, .. })
+            | Transaction::Chargeback(Chargeback { tx, .. })
+            | Transaction::Authorize(Authorize { tx, .. })
+            | Transaction::Capture(Capture { tx, .. })
+            | Transaction::Void(Void { tx, .. })
+            | Transaction::Represent(Represent { tx, .. })
+            | Transaction::Arbitrate(Arbitrate { tx, .. })
+            | Transaction::Refund(Refund { tx, .. })
+            | Transaction::Reversal(Reversal { tx, .. })
+            | Transaction::CreditLimit(CreditLimit { tx, .. })
+            | Transaction::Interest(Interest { tx, .. })
+            | Transaction::Settle(Settle { tx, .. })
+            | Transaction::Hold(Hold { tx, .. })
+            | Transaction::Release(Release { tx, .. })
+            | Transaction::Close(Close { tx, .. })
+            | Transaction::Transfer(Transfer { tx, .. }) => *tx,
+        }
+    }
+
+    /// Whether the transaction ID is the transaction's own, rather than referencing an earlier
+    /// transaction as described for `tx`.
+    pub fn creates_tx(&self) -> bool {
+        match self {
+            Transaction::Deposit(_)
+            | Transaction::Withdrawal(_)
+            | Transaction::Authorize(_)
+            | Transaction::CreditLimit(_)
+            | Transaction::Interest(_)
+            | Transaction::Hold(_)
+            | Transaction::Close(_)
+            | Transaction::Transfer(_) => true,
+            Transaction::Dispute(_)
+            | Transaction::Resolve(_)
+            | Transaction::Chargeback(_)
+            | Transaction::Capture(_)
+            | Transaction::Void(_)
+            | Transaction::Represent(_)
+            | Transaction::Arbitrate(_)
+            | Transaction::Refund(_)
+            | Transaction::Reversal(_)
+            | Transaction::Settle(_)
+            | Transaction::Release(_) => false,
+        }
+    }
+
+    /// The amount carried by the transaction, if any.
+    pub fn amount(&self) -> Option<Amount> {
+        match self {
+            Transaction::Deposit(Deposit { amount, .. })
+            | Transaction::Withdrawal(Withdrawal { amount, .. })
+            | Transaction::Authorize(Authorize { amount, .. })
+            | Transaction::CreditLimit(CreditLimit { amount, .. })
+            | Transaction::Interest(Interest { amount, .. })
+            | Transaction::Hold(Hold { amount, .. })
+            | Transaction::Transfer(Transfer { amount, .. }) => Some(*amount),
+            Transaction::Dispute(Dispute { amount, .. })
+            | Transaction::Resolve(Resolve { amount, .. })
+            | Transaction::Chargeback(Chargeback { amount, .. })
+            | Transaction::Capture(Capture { amount, .. })
+            | Transaction::Represent(Represent { amount, .. })
+            | Transaction::Arbitrate(Arbitrate { amount, .. })
+            | Transaction::Refund(Refund { amount, .. })
+            | Transaction::Release(Release { amount, .. }) => *amount,
+            Transaction::Void(_)
+            | Transaction::Reversal(_)
+            | Transaction::Settle(_)
+            | Transaction::Close(_) => None,
+        }
+    }
+
+    /// The amount carried by the transaction, if any, for it to be modified in place.
+    pub fn amount_mut(&mut self) -> Option<&mut Amount> {
+        match self {
+            Transaction::Deposit(Deposit { amount, .. })
+            | Transaction::Withdrawal(Withdrawal { amount, .. })
+            | Transaction::Authorize(Authorize { amount, .. })
+            | Transaction::CreditLimit(CreditLimit { amount, .. })
+            | Transaction::Interest(Interest { amount, .. })
+            | Transaction::Hold(Hold { amount, .. })
+            | Transaction::Transfer(Transfer { amount, .. }) => Some(amount),
+            Transaction::Dispute(Dispute { amount, .. })
+            | Transaction::Resolve(Resolve { amount, .. })
+            | Transaction::Chargeback(Chargeback { amount, .. })
+            | Transaction::Capture(Capture { amount, .. })
+            | Transaction::Represent(Represent { amount, .. })
+            | Transaction::Arbitrate(Arbitrate { amount, .. })
+            | Transaction::Refund(Refund { amount, .. })
+            | Transaction::Release(Release { amount, .. }) => amount.as_mut(),
+            Transaction::Void(_)
+            | Transaction::Reversal(_)
+            | Transaction::Settle(_)
+            | Transaction::Close(_) => None,
+        }
+    }
+
+    pub fn transaction_type(&self) -> TransactionType {
+        match self {
+            Transaction::Deposit(_) => TransactionType::Deposit,
+            Transaction::Withdrawal(_) => TransactionType::Withdrawal,
+            Transaction::Dispute(_) => TransactionType::Dispute,
+            Transaction::Resolve(_) => TransactionType::Resolve,
+            Transaction::Chargeback(_) => TransactionType::Chargeback,
+            Transaction::Authorize(_) => TransactionType::Authorize,
+            Transaction::Capture(_) => TransactionType::Capture,
+            Transaction::Void(_) => TransactionType::Void,
+            Transaction::Represent(_) => TransactionType::Represent,
+            Transaction::Arbitrate(_) => TransactionType::Arbitrate,
+            Transaction::Refund(_) => TransactionType::Refund,
+            Transaction::Reversal(_) => TransactionType::Reversal,
+            Transaction::CreditLimit(_) => TransactionType::CreditLimit,
+            Transaction::Interest(_) => TransactionType::Interest,
+            Transaction::Settle(_) => TransactionType::Settle,
+            Transaction::Hold(_) => TransactionType::Hold,
+            Transaction::Release(_) => TransactionType::Release,
+            Transaction::Close(_) => TransactionType::Close,
+            Transaction::Transfer(_) => TransactionType::Transfer,
+        }
+    }
+
+    /// The wallet the transaction credits or draws on, which is the default wallet for every
+    /// transaction other than deposits, withdrawals and transfers.
+    pub fn wallet(&self) -> WalletId {
+        match self {
+            Transaction::Deposit(Deposit { wallet, .. })
+            | Transaction::Withdrawal(Withdrawal { wallet, .. })
+            | Transaction::Transfer(Transfer { wallet, .. }) => *wallet,
+            _ => WalletId::default(),
+        }
+    }
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct Deposit {
     pub client: ClientId,
     pub tx: TransactionId,
     pub amount: Amount,
+    pub wallet: WalletId,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct Withdrawal {
     pub client: ClientId,
-    #[allow(dead_code)]
     pub tx: TransactionId,
     pub amount: Amount,
+    pub wallet: WalletId,
 }
 
-#[derive(Debug, PartialEq)]
+/// Disputes a deposit, in full when no amount is given.
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct Dispute {
     pub client: ClientId,
     pub tx: TransactionId,
+    pub amount: Option<Amount>,
 }
 
-#[derive(Debug, PartialEq)]
+/// Resolves a disputed deposit, in full when no amount is given.
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct Resolve {
     pub client: ClientId,
     pub tx: TransactionId,
+    pub amount: Option<Amount>,
 }
 
-#[derive(Debug, PartialEq)]
+/// Charges back a disputed deposit, in full when no amount is given.
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct Chargeback {
     pub client: ClientId,
     pub tx: TransactionId,
+    pub amount: Option<Amount>,
+}
+
+/// Contests a chargeback of a deposit, in full when no amount is given.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Represent {
+    pub client: ClientId,
+    pub tx: TransactionId,
+    pub amount: Option<Amount>,
+}
+
+/// Upholds a second dispute of a represented deposit, in full when no amount is given.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Arbitrate {
+    pub client: ClientId,
+    pub tx: TransactionId,
+    pub amount: Option<Amount>,
+}
+
+/// Returns part of a deposit to the client's source, in full when no amount is given.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Refund {
+    pub client: ClientId,
+    pub tx: TransactionId,
+    pub amount: Option<Amount>,
+}
+
+/// Undoes an earlier deposit, withdrawal or authorization loaded in error.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Reversal {
+    pub client: ClientId,
+    pub tx: TransactionId,
+}
+
+/// Sets the overdraft a client's available funds may be drawn down to by withdrawals.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CreditLimit {
+    pub client: ClientId,
+    pub tx: TransactionId,
+    pub amount: Amount,
+}
+
+/// Why a compliance hold was placed on a client's funds.
+#[derive(Debug, Deserialize, Serialize, Copy, Clone, Eq, Hash, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum HoldReason {
+    /// An anti money laundering investigation.
+    Aml,
+    Fraud,
+    Legal,
+    Other,
+}
+
+/// Places a compliance hold on an amount of a client's available funds, identified by its `tx`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Hold {
+    pub client: ClientId,
+    pub tx: TransactionId,
+    pub amount: Amount,
+    pub reason: HoldReason,
+}
+
+/// Releases a compliance hold, in full when no amount is given.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Release {
+    pub client: ClientId,
+    pub tx: TransactionId,
+    pub amount: Option<Amount>,
+}
+
+/// Closes a client's account, paying out their remaining available funds.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Close {
+    pub client: ClientId,
+    pub tx: TransactionId,
+}
+
+/// Moves an amount of a client's available funds from one of their wallets to another.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Transfer {
+    pub client: ClientId,
+    pub tx: TransactionId,
+    pub amount: Amount,
+    pub wallet: WalletId,
+    pub to_wallet: WalletId,
+}
+
+/// Settles a pending deposit, making its funds available.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Settle {
+    pub client: ClientId,
+    pub tx: TransactionId,
+}
+
+/// Credits interest earned on a client's available funds, paid by the house.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Interest {
+    pub client: ClientId,
+    pub tx: TransactionId,
+    pub amount: Amount,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Authorize {
+    pub client: ClientId,
+    pub tx: TransactionId,
+    pub amount: Amount,
+}
+
+/// Captures an authorization, in full when no amount is given.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Capture {
+    pub client: ClientId,
+    pub tx: TransactionId,
+    pub amount: Option<Amount>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Void {
+    pub client: ClientId,
+    pub tx: TransactionId,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn timestamp_periods() {
+        // 2024-02-29T23:59:59Z and 2024-03-01T00:00:00Z
+        let leap_day = Timestamp(1_709_251_199);
+        let march = Timestamp(1_709_251_200);
+
+        assert_eq!(march.day() - leap_day.day(), 1);
+        assert_eq!(leap_day.month(), (2024 - 1970) * 12 + 1);
+        assert_eq!(march.month(), (2024 - 1970) * 12 + 2);
+        assert_eq!(Timestamp(0).month(), 0);
+    }
+
+    #[test]
+    fn parse_amounts() {
+        let parse = |text: &str| {
+            let mut rdr = csv::ReaderBuilder::new()
+                .has_headers(false)
+                .from_reader(text.as_bytes());
+            rdr.deserialize::<Amount>().next().unwrap().unwrap()
+        };
+
+        assert_eq!(parse("1.50").0.to_string(), "1.5");
+        assert_eq!(parse("5.0").0.to_string(), "5");
+        assert_eq!(parse("2.5e1").0.to_string(), "25");
+        assert_eq!(parse("1.2212").0.to_string(), "1.2212");
+        assert_eq!(parse("79228162514264337593543950335").0, Decimal::MAX);
+    }
+
+    #[test]
+    fn checked_amounts() {
+        assert_eq!(Amount::MAX, Amount::from(Decimal::from(10_i64.pow(15))));
+
+        let max = Amount::from(Decimal::MAX);
+        assert_eq!(max.checked_add(Amount::from(1)), None);
+        assert_eq!(
+            Amount::from(Decimal::MIN).checked_sub(Amount::from(1)),
+            None
+        );
+        assert_eq!(
+            max.checked_sub(Amount::from(1)),
+            Some(Amount::from(Decimal::MAX - Decimal::ONE))
+        );
+        assert_eq!(
+            Amount::from(1).checked_add(Amount::from(2)),
+            Some(Amount::from(3))
+        );
+    }
 }
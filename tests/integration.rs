@@ -1,7 +1,10 @@
 #[cfg(test)]
 mod tests {
     use payments_engine::ledger::Ledger;
+    use std::fs;
     use std::io::Cursor;
+    use std::path::PathBuf;
+    use std::process::{Command, Stdio};
 
     #[test]
     fn end_to_end() {
@@ -45,4 +48,118 @@ mod tests {
 
         assert!(want_options.contains(&got));
     }
+
+    /// A scratch directory for a test's files, unique to the test and this run.
+    fn scratch_dir(test: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("payments-engine-{}-{}", test, std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    /// Run the binary with the given arguments and return its exit code and standard output.
+    fn run(args: &[&str]) -> (i32, String) {
+        let output = Command::new(env!("CARGO_BIN_EXE_payments-engine"))
+            .args(args)
+            .stdin(Stdio::null())
+            .stderr(Stdio::null())
+            .output()
+            .unwrap();
+        let stdout = String::from_utf8(output.stdout).expect("Invalid UTF-8");
+        (output.status.code().unwrap(), stdout)
+    }
+
+    #[test]
+    fn exit_codes() {
+        let dir = scratch_dir("exit-codes");
+        let path = |name: &str| dir.join(name).display().to_string();
+        fs::write(
+            path("valid.csv"),
+            "type,client,tx,amount\ndeposit,1,1,1.0\n",
+        )
+        .unwrap();
+        fs::write(
+            path("rejected.csv"),
+            "type,client,tx,amount\nwithdrawal,1,1,1.0\n",
+        )
+        .unwrap();
+        fs::write(path("fees.csv"), "type,flat,percent\ndeposit,one,0\n").unwrap();
+        fs::write(path("corrupt.csv.gz"), b"\x1f\x8bgarbage").unwrap();
+        fs::create_dir_all(path("empty")).unwrap();
+
+        let codes = [
+            (0, vec![path("valid.csv")]),
+            (2, vec!["-".to_string(), "-".to_string()]),
+            (2, vec![path("[.csv")]),
+            (2, vec![path("*.tsv")]),
+            (2, vec![path("empty")]),
+            (
+                2,
+                vec![path("valid.csv"), "--fees".to_string(), path("fees.csv")],
+            ),
+            (3, vec![path("missing.csv")]),
+            (3, vec![path("corrupt.csv.gz")]),
+            (4, vec![path("rejected.csv")]),
+        ];
+        let got = codes
+            .iter()
+            .map(|(_, args)| run(&args.iter().map(String::as_str).collect::<Vec<_>>()).0)
+            .collect::<Vec<_>>();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(got, codes.map(|(code, _)| code));
+    }
+
+    #[test]
+    fn replay_until() {
+        let dir = scratch_dir("replay-until");
+        let input = dir.join("transactions.csv").display().to_string();
+        fs::write(
+            &input,
+            "type,client,tx,amount\n\
+            deposit,1,1,10\n\
+            dispute,1,2,\n\
+            deposit,1,2,5\n\
+            deposit,1,3,1\n",
+        )
+        .unwrap();
+
+        // Assert that the dispute referencing transaction 2 before it arrives, and is rejected,
+        // does not end the replay, but the deposit creating it does.
+        let replayed = run(&["replay", &input, "--until", "2"]);
+        let missing = run(&["replay", &input, "--until", "4"]);
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(
+            replayed,
+            (
+                4,
+                "client,available,held,total,locked\n1,15,0,15,false\n".to_string()
+            )
+        );
+        assert_eq!(missing, (2, String::new()));
+    }
+
+    #[test]
+    fn statement_unreadable_input() {
+        let dir = scratch_dir("statement-unreadable-input");
+        let path = |name: &str| dir.join(name).display().to_string();
+        fs::write(
+            path("valid.csv"),
+            "type,client,tx,amount\ndeposit,1,1,1.0\n",
+        )
+        .unwrap();
+
+        // Assert that no statement is written when a later input cannot be read.
+        let got = run(&[
+            "statement",
+            &path("valid.csv"),
+            &path("missing.csv"),
+            "--client",
+            "1",
+        ]);
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(got, (3, String::new()));
+    }
 }